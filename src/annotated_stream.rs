@@ -0,0 +1,786 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! # Annotated stream
+//!
+//! An annotated stream stores an input stream alongside the tokens that were found in it by a tokenizer. Unlike a tagged
+//! stream, the original input is left as it is, so it's easy to find the input symbols that correspond to any token, or
+//! the token that covers a particular input symbol.
+//!
+//! ```
+//! # use concordance::*;
+//! #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+//! enum Tokens {
+//!     Number
+//! };
+//!
+//! let mut token_matcher = TokenMatcher::new();
+//! token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), Tokens::Number);
+//!
+//! let dfa         = token_matcher.prepare_to_match();
+//! let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12 42".read_symbols());
+//! let tokens      = annotated.read_tokens().to_vec(); // 2 tokens, at 0..2 and 3..5
+//! # assert!(tokens == vec![Token { output: Tokens::Number, location: 0..2 }, Token { output: Tokens::Number, location: 3..5 }]);
+//! ```
+//!
+//! If the input is already in memory (for instance, if it's a memory-mapped file), `from_tokenizer_borrowed()` creates an
+//! annotated stream that refers to it instead of making a copy.
+//!
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::ops::Range;
+use std::slice::Iter;
+
+use super::countable::*;
+use super::regular_pattern::*;
+use super::state_machine::*;
+use super::symbol_reader::*;
+use super::symbol_range_dfa::*;
+use super::tokenizer::*;
+
+///
+/// A token found in an annotated stream
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Token<OutputSymbol> {
+    /// The output symbol that was generated for this token
+    pub output: OutputSymbol,
+
+    /// The range of symbols in the original input that make up this token
+    pub location: Range<usize>
+}
+
+///
+/// A token along with the line and column in the input where it starts
+///
+/// Lines and columns are both counted from 0, and columns are counted in symbols.
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LocatedToken<OutputSymbol> {
+    /// The output symbol that was generated for this token
+    pub output: OutputSymbol,
+
+    /// The range of symbols in the original input that make up this token
+    pub location: Range<usize>,
+
+    /// The line that the token starts on
+    pub line: usize,
+
+    /// The column within the line where the token starts
+    pub column: usize
+}
+
+///
+/// The kinds of problem that can be reported while tokenizing a stream
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DiagnosticKind {
+    /// The input did not match any token and was skipped
+    UnmatchedInput,
+
+    /// The input matched more than one pattern, and the lowest output symbol was chosen
+    AmbiguousMatch
+}
+
+///
+/// A problem found while tokenizing a stream
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    /// The range of symbols in the original input that the problem applies to
+    pub location: Range<usize>,
+
+    /// What the problem was
+    pub kind: DiagnosticKind
+}
+
+///
+/// An input stream annotated with the tokens that were found in it
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AnnotatedStream<'a, InputSymbol: Clone+'a, OutputSymbol> {
+    /// The original input symbols, which are either owned by this stream or borrowed from the caller
+    original: Cow<'a, [InputSymbol]>,
+
+    /// The tokens found in the input, in order
+    tokens: Vec<Token<OutputSymbol>>,
+
+    /// If recorded, the state of the DFA at the start of each token (None for error tokens, which the DFA didn't match)
+    boundary_states: Vec<Option<StateId>>
+}
+
+impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+    ///
+    /// Creates an annotated stream by tokenizing the contents of a symbol reader
+    ///
+    pub fn from_tokenizer<Reader: SymbolReader<InputSymbol>>(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, reader: Reader) -> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+        let mut reader = reader;
+        Self::tokenize(dfa, Cow::Owned(reader.to_vec()), false, None, |_| { })
+    }
+
+    ///
+    /// Creates an annotated stream by tokenizing a slice of symbols, keeping a reference to the slice instead of copying it
+    ///
+    /// The tokens are the same as those found by `from_tokenizer()`, and their locations are indexes into `input`.
+    ///
+    pub fn from_tokenizer_borrowed(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, input: &'a [InputSymbol]) -> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+        Self::tokenize(dfa, Cow::Borrowed(input), false, None, |_| { })
+    }
+
+    ///
+    /// Creates an annotated stream by tokenizing the contents of a symbol reader, generating an error token for input that
+    /// doesn't match any pattern
+    ///
+    /// Each run of unmatched input becomes a single token with the specified output symbol, so every input symbol is
+    /// covered by a token.
+    ///
+    pub fn from_tokenizer_with_error_token<Reader: SymbolReader<InputSymbol>>(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, reader: Reader, error_output: OutputSymbol) -> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+        let mut reader = reader;
+        Self::tokenize(dfa, Cow::Owned(reader.to_vec()), false, Some(error_output), |_| { })
+    }
+
+    ///
+    /// Creates an annotated stream by tokenizing the contents of a symbol reader, recording the state of the DFA at each
+    /// token boundary
+    ///
+    /// The boundary state of a token is the state that the DFA began in when matching it. This is usually state 0, but
+    /// is a different state for the token at the start of the input if the DFA was compiled from patterns containing a
+    /// `start_anchor()`. The boundary states can be used to restart tokenizing part way through the stream (for instance,
+    /// after the input has been edited), by matching from the state for the first token that needs to be re-read.
+    ///
+    pub fn from_tokenizer_with_boundary_states<Reader: SymbolReader<InputSymbol>>(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, reader: Reader) -> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+        let mut reader = reader;
+        Self::tokenize(dfa, Cow::Owned(reader.to_vec()), true, None, |_| { })
+    }
+
+    ///
+    /// Tokenizes the original input symbols, optionally recording the boundary states and generating error tokens
+    ///
+    /// The setup function is called with the tokenizer before any tokens are read, so it can add skip patterns.
+    ///
+    fn tokenize<SetupFn>(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, original: Cow<'a, [InputSymbol]>, record_boundary_states: bool, error_output: Option<OutputSymbol>, setup: SetupFn) -> AnnotatedStream<'a, InputSymbol, OutputSymbol>
+    where SetupFn: FnOnce(&mut Tokenizer<InputSymbol, OutputSymbol, Iter<InputSymbol>>) {
+        let mut tokens          = vec![];
+        let mut boundary_states = vec![];
+
+        {
+            let mut tokenizer       = Tokenizer::new_prepared((*original).read_symbols(), dfa);
+            let mut unmatched_start = None;
+
+            setup(&mut tokenizer);
+
+            loop {
+                let at_end  = tokenizer.at_end_of_reader();
+                let token   = if at_end { None } else { tokenizer.next_token() };
+
+                // Finish any run of unmatched input when a token is found or the input ends
+                if at_end || token.is_some() {
+                    if let (Some(start), Some(error_output)) = (unmatched_start.take(), error_output.as_ref()) {
+                        let end = match token {
+                            Some((ref location, _)) => location.start,
+                            None                    => tokenizer.get_source_position()
+                        };
+
+                        // Error tokens aren't matched by the DFA, so they don't have a boundary state
+                        if record_boundary_states {
+                            boundary_states.push(None);
+                        }
+
+                        tokens.push(Token { output: error_output.clone(), location: start..end });
+                    }
+                }
+
+                if at_end {
+                    break;
+                }
+
+                if let Some((location, output)) = token {
+                    // The tokenizer runs the DFA from its start of input state for a token at the start of the input, and its
+                    // usual start state everywhere else
+                    if record_boundary_states {
+                        boundary_states.push(Some(dfa.initial_state(location.start == 0)));
+                    }
+
+                    tokens.push(Token { output, location });
+                } else {
+                    // Input that doesn't match any token is skipped one symbol at a time
+                    if unmatched_start.is_none() {
+                        unmatched_start = Some(tokenizer.get_source_position());
+                    }
+
+                    tokenizer.skip_input();
+                }
+            }
+        }
+
+        AnnotatedStream { original, tokens, boundary_states }
+    }
+}
+
+impl<'a, InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+    ///
+    /// Creates an annotated stream by tokenizing the contents of a symbol reader, consuming any input that matches a skip
+    /// pattern (such as whitespace or comments) without generating a token for it
+    ///
+    /// The skip pattern works in the same way as `Tokenizer::add_skip_pattern()`: it's only used if it matches more input
+    /// than any token at the same position. Skipped input isn't covered by any token in the stream.
+    ///
+    pub fn from_tokenizer_with_skip_pattern<Reader: SymbolReader<InputSymbol>, TPattern: ToPattern<InputSymbol>>(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, reader: Reader, skip_pattern: TPattern) -> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+        let mut reader  = reader;
+        let skip        = skip_pattern.to_pattern();
+
+        Self::tokenize(dfa, Cow::Owned(reader.to_vec()), false, None, move |tokenizer| tokenizer.add_skip_pattern(skip))
+    }
+
+    ///
+    /// Creates an annotated stream by tokenizing a slice of symbols with a skip pattern, keeping a reference to the slice
+    /// instead of copying it
+    ///
+    pub fn from_tokenizer_borrowed_with_skip_pattern<TPattern: ToPattern<InputSymbol>>(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, input: &'a [InputSymbol], skip_pattern: TPattern) -> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+        let skip = skip_pattern.to_pattern();
+
+        Self::tokenize(dfa, Cow::Borrowed(input), false, None, move |tokenizer| tokenizer.add_skip_pattern(skip))
+    }
+}
+
+impl<'a, InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<InputSymbol>> Tokenizer<'a, InputSymbol, OutputSymbol, Reader> {
+    ///
+    /// Tokenizes the contents of a symbol reader, returning the tokens and a list of the problems that were found along the way
+    ///
+    /// A diagnostic is generated for every run of input that doesn't match a token, and for every token that matched more
+    /// than one output in the token matcher. The output used for an ambiguous token is the same one that the DFA from
+    /// `prepare_to_match()` would produce, following the token matcher's priority mode.
+    ///
+    pub fn tokenize_with_diagnostics(reader: Reader, token_matcher: &TokenMatcher<InputSymbol, OutputSymbol>) -> (AnnotatedStream<'static, InputSymbol, OutputSymbol>, Vec<Diagnostic>) {
+        // Build a DFA that keeps every candidate output (in priority order) so that ambiguous tokens can be spotted
+        let dfa             = token_matcher.prepare_with_all_outputs();
+
+        let mut tokenizer   = Tokenizer::new_prepared(reader, &dfa);
+        let mut original    = vec![];
+        let mut tokens      = vec![];
+        let mut diagnostics = vec![];
+
+        loop {
+            if let Some(token) = tokenizer.next_rich_token() {
+                if token.output.len() > 1 {
+                    diagnostics.push(Diagnostic { location: token.location.clone(), kind: DiagnosticKind::AmbiguousMatch });
+                }
+
+                original.extend(token.symbols);
+                tokens.push(Token { output: token.output[0].clone(), location: token.location });
+            } else if let Some(symbol) = tokenizer.skip_input() {
+                let position = original.len();
+                original.push(symbol);
+
+                // Runs of unmatched input are reported as a single diagnostic
+                match diagnostics.last_mut() {
+                    Some(&mut Diagnostic { ref mut location, kind: DiagnosticKind::UnmatchedInput }) if location.end == position => {
+                        location.end = position+1;
+                    },
+
+                    _ => diagnostics.push(Diagnostic { location: position..position+1, kind: DiagnosticKind::UnmatchedInput })
+                }
+            } else {
+                break;
+            }
+        }
+
+        (AnnotatedStream { original: Cow::Owned(original), tokens, boundary_states: vec![] }, diagnostics)
+    }
+}
+
+impl<'a, InputSymbol: Clone, OutputSymbol> AnnotatedStream<'a, InputSymbol, OutputSymbol> {
+    ///
+    /// Returns a reader for the original input symbols
+    ///
+    pub fn read_input(&self) -> Iter<'_, InputSymbol> {
+        self.original.iter()
+    }
+
+    ///
+    /// Returns a reader for the original input symbols, where each symbol is paired with the index of the token that covers
+    /// it (or `None` if the symbol isn't part of a token)
+    ///
+    pub fn read_input_with_tokens<'b>(&'b self) -> Box<dyn SymbolReader<(InputSymbol, Option<usize>)>+'b> {
+        let tokens = &self.tokens;
+
+        let with_tokens = self.original.iter().enumerate().scan(0, move |token_index, (position, symbol)| {
+            // Tokens are in order, so we only need to move forward past the tokens that end before this symbol
+            while *token_index < tokens.len() && tokens[*token_index].location.end <= position {
+                *token_index += 1;
+            }
+
+            let covering = if *token_index < tokens.len() && tokens[*token_index].location.start <= position {
+                Some(*token_index)
+            } else {
+                None
+            };
+
+            Some((symbol.clone(), covering))
+        });
+
+        Box::new(with_tokens.into_symbol_reader())
+    }
+
+    ///
+    /// Returns a reader for the tokens in this stream
+    ///
+    pub fn read_tokens(&self) -> Iter<'_, Token<OutputSymbol>> {
+        self.tokens.iter()
+    }
+
+    ///
+    /// Returns an iterator over the tokens in this stream, where each output symbol is paired with the input symbols
+    /// that make up its token
+    ///
+    pub fn iter_tokens_with_input<'b>(&'b self) -> impl Iterator<Item=(&'b [InputSymbol], &'b OutputSymbol)>+'b {
+        self.tokens.iter().map(move |token| (&self.original[token.location.clone()], &token.output))
+    }
+
+    ///
+    /// Finds the token that covers a particular position in the input
+    ///
+    pub fn find_token(&self, position: usize) -> Option<&Token<OutputSymbol>> {
+        // Tokens are ordered and don't overlap, so we can binary search for the position
+        let index = self.tokens.binary_search_by(|token| {
+            if token.location.end <= position {
+                Ordering::Less
+            } else if token.location.start > position {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        });
+
+        index.ok().map(|index| &self.tokens[index])
+    }
+
+    ///
+    /// Returns the token at a particular index in the list of tokens found in this stream
+    ///
+    /// `find_token()` finds a token by its position in the input: this finds it by its position in the output instead.
+    ///
+    #[inline]
+    pub fn token_at_output_index(&self, index: usize) -> Option<&Token<OutputSymbol>> {
+        self.tokens.get(index)
+    }
+
+    ///
+    /// Retrieves the input symbols that make up a particular token
+    ///
+    pub fn input_for_token(&self, token: &Token<OutputSymbol>) -> &[InputSymbol] {
+        &self.original[token.location.clone()]
+    }
+
+    ///
+    /// Returns the state the DFA was in at the start of a token
+    ///
+    /// This returns `None` if the stream wasn't created by `from_tokenizer_with_boundary_states()`, if there's no token
+    /// with the specified index, or if the token is an error token (which isn't matched by the DFA).
+    ///
+    pub fn boundary_state(&self, token_index: usize) -> Option<StateId> {
+        self.boundary_states.get(token_index).cloned().unwrap_or(None)
+    }
+
+    ///
+    /// Groups runs of consecutive tokens that have the same key
+    ///
+    /// The tokens are visited in order, and a new group is started whenever the key for a token is different from the key
+    /// for the token before it.
+    ///
+    pub fn group_tokens_by<Key: Eq, KeyFn: Fn(&Token<OutputSymbol>) -> Key>(&self, key: KeyFn) -> Vec<(Key, Vec<Token<OutputSymbol>>)>
+    where OutputSymbol: Clone {
+        let mut groups: Vec<(Key, Vec<Token<OutputSymbol>>)> = vec![];
+
+        for token in self.tokens.iter() {
+            let token_key = key(token);
+
+            match groups.last_mut() {
+                Some((ref last_key, ref mut tokens)) if *last_key == token_key  => tokens.push(token.clone()),
+                _                                                               => groups.push((token_key, vec![token.clone()]))
+            }
+        }
+
+        groups
+    }
+
+    ///
+    /// True if there is no input between two tokens (`a` ends exactly where `b` begins)
+    ///
+    pub fn tokens_adjacent(&self, a: &Token<OutputSymbol>, b: &Token<OutputSymbol>) -> bool {
+        a.location.end == b.location.start
+    }
+}
+
+impl<'a, OutputSymbol: Clone> AnnotatedStream<'a, char, OutputSymbol> {
+    ///
+    /// Returns the line and column of a position in the input
+    ///
+    /// Lines end in the same way as for a `PositionReader` ('\n', '\r' or "\r\n"). Both the line and the column are counted
+    /// from 0. The position can be the length of the input (to find where the input ends), but this returns `None` for
+    /// positions after that.
+    ///
+    pub fn line_column(&self, position: usize) -> Option<(usize, usize)> {
+        if position > self.original.len() {
+            return None;
+        }
+
+        let mut reader = PositionReader::new(self.original[0..position].read_symbols());
+        while reader.next_symbol().is_some() { }
+
+        Some(reader.position())
+    }
+
+    ///
+    /// Returns the tokens in this stream along with the line and column where each one starts
+    ///
+    pub fn located_tokens(&self) -> Vec<LocatedToken<OutputSymbol>> {
+        let mut result      = vec![];
+        let mut reader      = PositionReader::new(self.original.read_symbols());
+
+        for token in self.tokens.iter() {
+            // Read up to the start of this token
+            while reader.offset() < token.location.start {
+                reader.next_symbol();
+            }
+
+            let (line, column) = reader.position();
+            result.push(LocatedToken { output: token.output.clone(), location: token.location.clone(), line, column });
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+    enum TestToken {
+        Digit,
+        Plus
+    }
+
+    fn dfa() -> SymbolRangeDfa<char, TestToken> {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+        token_matcher.add_pattern(exactly("+"), TestToken::Plus);
+
+        token_matcher.prepare_to_match()
+    }
+
+    #[test]
+    fn can_tokenize_stream() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12+34".read_symbols());
+
+        assert!(annotated.read_tokens().to_vec() == vec![
+            Token { output: TestToken::Digit, location: 0..2 },
+            Token { output: TestToken::Plus, location: 2..3 },
+            Token { output: TestToken::Digit, location: 3..5 }
+        ]);
+        assert!(annotated.read_input().to_vec() == vec!['1', '2', '+', '3', '4']);
+    }
+
+    #[test]
+    fn borrowed_stream_has_same_tokens() {
+        let dfa         = dfa();
+        let input       = "12+34 @ 5".chars().collect::<Vec<_>>();
+        let owned       = AnnotatedStream::from_tokenizer(&dfa, "12+34 @ 5".read_symbols());
+        let borrowed    = AnnotatedStream::from_tokenizer_borrowed(&dfa, &input);
+
+        assert!(borrowed.read_tokens().to_vec() == owned.read_tokens().to_vec());
+        assert!(borrowed == owned);
+
+        // Tokens in the borrowed stream refer directly to the caller's buffer
+        let last = borrowed.read_tokens().last().unwrap();
+        assert!(borrowed.input_for_token(last).as_ptr() == input[last.location.clone()].as_ptr());
+    }
+
+    #[test]
+    fn can_find_token_and_input() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12 + 34".read_symbols());
+
+        let token = annotated.find_token(6).unwrap();
+        assert!(token.location == (5..7));
+        assert!(annotated.input_for_token(token) == ['3', '4']);
+        assert!(annotated.find_token(2).is_none());
+    }
+
+    #[test]
+    fn can_find_token_by_output_index() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12 42 13".read_symbols());
+
+        assert!(annotated.token_at_output_index(1) == Some(&Token { output: TestToken::Digit, location: 3..5 }));
+        assert!(annotated.token_at_output_index(2) == Some(&Token { output: TestToken::Digit, location: 6..8 }));
+        assert!(annotated.input_for_token(annotated.token_at_output_index(2).unwrap()) == ['1', '3']);
+        assert!(annotated.token_at_output_index(3).is_none());
+    }
+
+    #[test]
+    fn can_iterate_tokens_with_input() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12 42 13".read_symbols());
+
+        let digits = annotated.iter_tokens_with_input()
+            .filter(|(_, output)| **output == TestToken::Digit)
+            .map(|(input, _)| input.iter().collect::<String>())
+            .collect::<Vec<_>>();
+
+        assert!(digits == vec!["12", "42", "13"]);
+    }
+
+    #[test]
+    fn can_read_input_with_tokens() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12@34".read_symbols());
+
+        assert!(annotated.read_input_with_tokens().to_vec() == vec![
+            ('1', Some(0)),
+            ('2', Some(0)),
+            ('@', None),
+            ('3', Some(1)),
+            ('4', Some(1))
+        ]);
+    }
+
+    #[test]
+    fn tokens_separated_by_whitespace_are_not_adjacent() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12 34".read_symbols());
+        let tokens      = annotated.read_tokens().to_vec();
+
+        assert!(tokens.len() == 2);
+        assert!(!annotated.tokens_adjacent(&tokens[0], &tokens[1]));
+    }
+
+    #[test]
+    fn neighbouring_tokens_are_adjacent() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12+34".read_symbols());
+        let tokens      = annotated.read_tokens().to_vec();
+
+        assert!(annotated.tokens_adjacent(&tokens[0], &tokens[1]));
+        assert!(annotated.tokens_adjacent(&tokens[1], &tokens[2]));
+    }
+
+    #[test]
+    fn can_restart_from_boundary_state() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(start_anchor().append(exactly("+")), TestToken::Plus);
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+
+        let dfa         = token_matcher.prepare_to_match();
+        let input       = "+12+34";
+        let annotated   = AnnotatedStream::from_tokenizer_with_boundary_states(&dfa, input.read_symbols());
+        let tokens      = annotated.read_tokens().to_vec();
+
+        // The '+' only matches at the start of the input, where the DFA begins in a different state
+        assert!(tokens.len() == 3);
+        assert!(annotated.boundary_state(0) == Some(dfa.initial_state(true)));
+        assert!(annotated.boundary_state(0) != Some(0));
+        assert!(annotated.boundary_state(1) == Some(0));
+        assert!(annotated.boundary_state(2) == Some(0));
+
+        // Restarting the DFA from the boundary state of any token matches the same token again
+        let input: Vec<char> = input.chars().collect();
+
+        for (index, token) in tokens.iter().enumerate() {
+            let state = annotated.boundary_state(index).unwrap();
+
+            match match_pattern(dfa.start_from_state(state), &mut input[token.location.start..].read_symbols()) {
+                Accept(length, output)  => assert!(length == token.location.len() && *output == token.output),
+                _                       => panic!()
+            }
+        }
+    }
+
+    #[test]
+    fn error_tokens_have_no_boundary_state() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::tokenize(&dfa, ::std::borrow::Cow::Owned("1x+".chars().collect()), true, Some(TestToken::Plus), |_| { });
+
+        assert!(annotated.read_tokens().count() == 3);
+        assert!(annotated.boundary_state(0) == Some(0));
+        assert!(annotated.boundary_state(1).is_none());
+        assert!(annotated.boundary_state(2) == Some(0));
+        assert!(annotated.boundary_state(3).is_none());
+    }
+
+    #[test]
+    fn boundary_states_are_optional() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "12+34".read_symbols());
+
+        assert!(annotated.boundary_state(0).is_none());
+    }
+
+    #[test]
+    fn can_group_tokens() {
+        let dfa         = dfa();
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa, "1 2+3++45".read_symbols());
+        let groups      = annotated.group_tokens_by(|token| token.output == TestToken::Digit);
+
+        let group_starts: Vec<_> = groups.iter()
+            .map(|(key, tokens)| (*key, tokens.iter().map(|token| token.location.start).collect::<Vec<_>>()))
+            .collect();
+
+        assert!(group_starts == vec![
+            (true, vec![0, 2]),
+            (false, vec![3]),
+            (true, vec![4]),
+            (false, vec![5, 6]),
+            (true, vec![7])
+        ]);
+    }
+
+    #[test]
+    fn unmatched_input_is_reported() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+
+        let (annotated, diagnostics) = Tokenizer::tokenize_with_diagnostics("12@34".read_symbols(), &token_matcher);
+
+        assert!(annotated.read_tokens().to_vec() == vec![
+            Token { output: TestToken::Digit, location: 0..2 },
+            Token { output: TestToken::Digit, location: 3..5 }
+        ]);
+        assert!(annotated.read_input().to_vec() == vec!['1', '2', '@', '3', '4']);
+        assert!(diagnostics == vec![Diagnostic { location: 2..3, kind: DiagnosticKind::UnmatchedInput }]);
+    }
+
+    #[test]
+    fn unmatched_runs_and_ambiguities_are_reported() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+        token_matcher.add_pattern(exactly("+"), TestToken::Plus);
+        token_matcher.add_pattern(exactly("1"), TestToken::Plus);
+
+        let (annotated, diagnostics) = Tokenizer::tokenize_with_diagnostics("1ab+".read_symbols(), &token_matcher);
+
+        assert!(annotated.read_tokens().to_vec() == vec![
+            Token { output: TestToken::Digit, location: 0..1 },
+            Token { output: TestToken::Plus, location: 3..4 }
+        ]);
+        assert!(diagnostics == vec![
+            Diagnostic { location: 0..1, kind: DiagnosticKind::AmbiguousMatch },
+            Diagnostic { location: 1..3, kind: DiagnosticKind::UnmatchedInput }
+        ]);
+    }
+
+    #[test]
+    fn diagnostics_follow_anchors_and_priority_mode() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.set_priority_mode(PriorityMode::LongestThenInsertion);
+        token_matcher.add_pattern(start_anchor().append(exactly("+")), TestToken::Plus);
+        token_matcher.add_pattern(exactly("1"), TestToken::Plus);
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+
+        let (annotated, diagnostics) = Tokenizer::tokenize_with_diagnostics("+1+".read_symbols(), &token_matcher);
+
+        // The anchored '+' only matches at the start, and the '1' pattern wins as it was added first
+        assert!(annotated.read_tokens().to_vec() == vec![
+            Token { output: TestToken::Plus, location: 0..1 },
+            Token { output: TestToken::Plus, location: 1..2 }
+        ]);
+        assert!(diagnostics == vec![
+            Diagnostic { location: 1..2, kind: DiagnosticKind::AmbiguousMatch },
+            Diagnostic { location: 2..3, kind: DiagnosticKind::UnmatchedInput }
+        ]);
+    }
+
+    #[test]
+    fn skip_patterns_consume_input() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+        token_matcher.add_pattern(exactly("+"), TestToken::Plus);
+
+        // The comment would otherwise produce a '+' token
+        let dfa         = token_matcher.prepare_to_match();
+        let comment     = exactly("/*").append(MatchRange('+', '9').repeat_forever(0)).append(exactly("*/"));
+        let input: Vec<char> = "12/*+*/+3".chars().collect();
+
+        let annotated   = AnnotatedStream::from_tokenizer_with_skip_pattern(&dfa, input.read_symbols(), comment.clone());
+        let borrowed    = AnnotatedStream::from_tokenizer_borrowed_with_skip_pattern(&dfa, &input, comment);
+
+        assert!(annotated.read_tokens().to_vec() == vec![
+            Token { output: TestToken::Digit, location: 0..2 },
+            Token { output: TestToken::Plus, location: 7..8 },
+            Token { output: TestToken::Digit, location: 8..9 }
+        ]);
+        assert!(annotated.read_input().count() == 9);
+        assert!(borrowed == annotated);
+    }
+
+    #[test]
+    fn unmatched_input_can_generate_error_tokens() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Number,
+            Error
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Number);
+
+        let dfa         = token_matcher.prepare_to_match();
+        let annotated   = AnnotatedStream::from_tokenizer_with_error_token(&dfa, "12$34".read_symbols(), TestToken::Error);
+        let trailing    = AnnotatedStream::from_tokenizer_with_error_token(&dfa, "1$%".read_symbols(), TestToken::Error);
+
+        assert!(annotated.read_tokens().to_vec() == vec![
+            Token { output: TestToken::Number, location: 0..2 },
+            Token { output: TestToken::Error, location: 2..3 },
+            Token { output: TestToken::Number, location: 3..5 }
+        ]);
+        assert!(trailing.read_tokens().to_vec() == vec![
+            Token { output: TestToken::Number, location: 0..1 },
+            Token { output: TestToken::Error, location: 1..3 }
+        ]);
+    }
+
+    #[test]
+    fn tokens_know_their_line_and_column() {
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa(), "1 + 2\n34+5".read_symbols());
+        let located     = annotated.located_tokens();
+
+        assert!(located.len() == 6);
+        assert!((located[0].line, located[0].column) == (0, 0));
+        assert!((located[2].line, located[2].column) == (0, 4));
+        assert!(located[3] == LocatedToken { output: TestToken::Digit, location: 6..8, line: 1, column: 0 });
+        assert!((located[5].line, located[5].column) == (1, 3));
+
+        assert!(annotated.line_column(located[4].location.start) == Some((1, 2)));
+    }
+
+    #[test]
+    fn carriage_returns_end_lines() {
+        let annotated   = AnnotatedStream::from_tokenizer(&dfa(), "1\r\n2\r3".read_symbols());
+        let located     = annotated.located_tokens();
+
+        assert!(located.len() == 3);
+        assert!((located[1].line, located[1].column) == (1, 0));
+        assert!((located[2].line, located[2].column) == (2, 0));
+
+        assert!(annotated.line_column(2) == Some((1, 0)));
+        assert!(annotated.line_column(6) == Some((2, 1)));
+        assert!(annotated.line_column(7) == None);
+    }
+}
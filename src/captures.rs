@@ -0,0 +1,453 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! A `CaptureMatcher` finds the ranges of input matched by the parts of a pattern marked with `capture()`, in the same
+//! way as the capture groups of a regular expression.
+//!
+//! ```
+//! # use concordance::*;
+//! let key_value   = MatchRange('a', 'z').repeat_forever(1).append("=").append(MatchRange('0', '9').repeat_forever(1).capture(1));
+//! let matcher     = CaptureMatcher::new(&key_value);
+//!
+//! let (length, captures) = matcher.match_captures("key=42").unwrap();
+//!
+//! // length == 6, captures[&1] == 4..6
+//! # assert!(length == 6);
+//! # assert!(captures[&1] == (4..6));
+//! ```
+//!
+//! A DFA can't say where a capture group starts and ends, as many paths through the pattern are merged into each of its
+//! states. Instead, the pattern is compiled to a set of instructions for a non-deterministic machine where captures are
+//! tags that record the current position. Every path through the machine is followed at once, each with its own copy of
+//! the tags, so the input is still only read once.
+//!
+//! The length of the match is the same as would be found by `matches()`. Where there's more than one way to match that
+//! length, repetitions are greedy and alternatives are tried in order. A capture group that is repeated reports the last
+//! range it matched, and one that isn't part of the match isn't reported at all.
+//!
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::regular_pattern::*;
+use super::symbol_reader::*;
+
+///
+/// An instruction for the capture matcher
+///
+#[derive(Clone, Debug)]
+enum Instruction<Symbol> {
+    /// Reads a symbol in a range, then moves to the next instruction
+    Read(Symbol, Symbol),
+
+    /// Continues at both of the specified instructions (with the first having priority)
+    Split(usize, usize),
+
+    /// Continues at the specified instruction
+    Jump(usize),
+
+    /// Records the current position in the specified tag
+    Tag(usize),
+
+    /// Moves to the next instruction, but only at the start of the input
+    StartOfInput,
+
+    /// Moves to the next instruction, but only once there are no more symbols in the input
+    EndOfInput,
+
+    /// This path can't match anything
+    Fail,
+
+    /// This path has matched the pattern
+    Match
+}
+
+///
+/// Matches a pattern, reporting the ranges matched by its capture groups
+///
+#[derive(Clone, Debug)]
+pub struct CaptureMatcher<Symbol> {
+    /// The instructions generated from the pattern
+    program: Vec<Instruction<Symbol>>,
+
+    /// The IDs of the capture groups. Each has a pair of tags: the first is at index*2 and the second is at index*2+1
+    capture_ids: Vec<usize>
+}
+
+impl<Symbol: Clone+Ord> CaptureMatcher<Symbol> {
+    ///
+    /// Creates a new capture matcher for a pattern
+    ///
+    pub fn new(pattern: &Pattern<Symbol>) -> CaptureMatcher<Symbol> {
+        let mut matcher = CaptureMatcher { program: vec![], capture_ids: vec![] };
+
+        matcher.compile(pattern);
+        matcher.program.push(Instruction::Match);
+
+        matcher
+    }
+
+    ///
+    /// Returns the index of the next instruction to be generated
+    ///
+    fn next_instruction(&self) -> usize {
+        self.program.len()
+    }
+
+    ///
+    /// Changes the target of a `Split` or `Jump` instruction that was generated before the instruction it goes to
+    ///
+    fn patch(&mut self, instruction: usize, target: usize) {
+        match self.program[instruction] {
+            Instruction::Split(_, ref mut second)   => *second = target,
+            Instruction::Jump(ref mut first)        => *first = target,
+            _                                       => panic!("Only split and jump instructions can be patched")
+        }
+    }
+
+    ///
+    /// Generates the instructions for a pattern
+    ///
+    fn compile(&mut self, pattern: &Pattern<Symbol>) {
+        match pattern {
+            Epsilon => { },
+
+            Match(symbols) => {
+                for symbol in symbols {
+                    self.program.push(Instruction::Read(symbol.clone(), symbol.clone()));
+                }
+            },
+
+            MatchRange(first, last) => {
+                self.program.push(Instruction::Read(first.clone(), last.clone()));
+            },
+
+            RepeatInfinite(count, pattern) => {
+                for _ in 0..*count {
+                    self.compile(pattern);
+                }
+
+                // Loop for as long as the pattern keeps matching
+                let loop_start = self.next_instruction();
+                self.program.push(Instruction::Split(loop_start+1, 0));
+                self.compile(pattern);
+                self.program.push(Instruction::Jump(loop_start));
+
+                let loop_end = self.next_instruction();
+                self.patch(loop_start, loop_end);
+            },
+
+            Repeat(range, pattern) => {
+                if range.start >= range.end {
+                    // Empty ranges can't be matched
+                    self.program.push(Instruction::Fail);
+                } else {
+                    for _ in 0..range.start {
+                        self.compile(pattern);
+                    }
+
+                    // Every repetition after the minimum is optional
+                    let mut splits = vec![];
+                    for _ in (range.start+1)..range.end {
+                        splits.push(self.next_instruction());
+                        self.program.push(Instruction::Split(self.next_instruction()+1, 0));
+                        self.compile(pattern);
+                    }
+
+                    let end = self.next_instruction();
+                    for split in splits {
+                        self.patch(split, end);
+                    }
+                }
+            },
+
+            MatchAll(patterns) => {
+                for pattern in patterns {
+                    self.compile(pattern);
+                }
+            },
+
+            MatchAny(patterns) => {
+                if patterns.is_empty() {
+                    self.program.push(Instruction::Fail);
+                } else {
+                    // Each alternative except the last splits off the remaining ones, then jumps to the end
+                    let mut jumps = vec![];
+
+                    for pattern in patterns.iter().take(patterns.len()-1) {
+                        let split = self.next_instruction();
+                        self.program.push(Instruction::Split(split+1, 0));
+                        self.compile(pattern);
+
+                        jumps.push(self.next_instruction());
+                        self.program.push(Instruction::Jump(0));
+
+                        let next_alternative = self.next_instruction();
+                        self.patch(split, next_alternative);
+                    }
+
+                    self.compile(&patterns[patterns.len()-1]);
+
+                    let end = self.next_instruction();
+                    for jump in jumps {
+                        self.patch(jump, end);
+                    }
+                }
+            },
+
+            Capture(id, pattern) => {
+                let index = match self.capture_ids.iter().position(|capture_id| capture_id == id) {
+                    Some(index) => index,
+                    None        => {
+                        self.capture_ids.push(*id);
+                        self.capture_ids.len()-1
+                    }
+                };
+
+                self.program.push(Instruction::Tag(index*2));
+                self.compile(pattern);
+                self.program.push(Instruction::Tag(index*2+1));
+            },
+
+            StartOfInput => {
+                self.program.push(Instruction::StartOfInput);
+            },
+
+            EndOfInput => {
+                self.program.push(Instruction::EndOfInput);
+            }
+        }
+    }
+
+    ///
+    /// Adds a path to the list of paths that are waiting for the next symbol, following any instructions that don't
+    /// need one
+    ///
+    /// Paths are added in priority order, and only the first path to reach each instruction is kept. If a path reaches
+    /// the end of the pattern and is longer than the current best match, it replaces it.
+    ///
+    fn add_path(&self, paths: &mut Vec<(usize, Vec<Option<usize>>)>, visited: &mut Vec<bool>, instruction: usize, mut tags: Vec<Option<usize>>, position: usize, best_match: &mut Option<(usize, Vec<Option<usize>>)>) {
+        if visited[instruction] {
+            return;
+        }
+
+        visited[instruction] = true;
+
+        match self.program[instruction] {
+            Instruction::Read(_, _)         => paths.push((instruction, tags)),
+            Instruction::EndOfInput         => paths.push((instruction, tags)),
+            Instruction::Fail               => { },
+            Instruction::Jump(target)       => self.add_path(paths, visited, target, tags, position, best_match),
+
+            Instruction::Split(first, second) => {
+                self.add_path(paths, visited, first, tags.clone(), position, best_match);
+                self.add_path(paths, visited, second, tags, position, best_match);
+            },
+
+            Instruction::StartOfInput => {
+                if position == 0 {
+                    self.add_path(paths, visited, instruction+1, tags, position, best_match);
+                }
+            },
+
+            Instruction::Tag(tag) => {
+                tags[tag] = Some(position);
+                self.add_path(paths, visited, instruction+1, tags, position, best_match);
+            },
+
+            Instruction::Match => {
+                let is_longer = best_match.as_ref().map(|(length, _)| position > *length).unwrap_or(true);
+
+                if is_longer {
+                    *best_match = Some((position, tags));
+                }
+            }
+        }
+    }
+
+    ///
+    /// Matches the start of a source stream, returning the length of the longest match and the range matched by each
+    /// capture group that was part of it
+    ///
+    pub fn match_captures<'a, Source: SymbolSource<'a, Symbol>>(&self, source: Source) -> Option<(usize, HashMap<usize, Range<usize>>)> {
+        let mut reader      = source.read_symbols();
+        let mut best_match  = None;
+        let mut position    = 0;
+        let mut paths       = vec![];
+
+        self.add_path(&mut paths, &mut vec![false; self.program.len()], 0, vec![None; self.capture_ids.len()*2], position, &mut best_match);
+
+        // Move every path on by a symbol until none of them are left
+        let mut visited = vec![];
+        let mut at_end  = false;
+
+        while !paths.is_empty() {
+            let symbol = if at_end { None } else { reader.next_symbol() };
+
+            match symbol {
+                Some(_) => {
+                    position    += 1;
+                    visited     = vec![false; self.program.len()];
+                },
+
+                None    => {
+                    // Once the input has run out, only the paths waiting at an end anchor can continue. The visited
+                    // instructions are kept between steps so that repeated anchors can't loop forever.
+                    if !at_end {
+                        visited = vec![false; self.program.len()];
+                    }
+
+                    at_end = true;
+                }
+            }
+
+            let mut next_paths = vec![];
+
+            for (instruction, tags) in paths {
+                match (&self.program[instruction], &symbol) {
+                    (Instruction::Read(first, last), Some(symbol)) if first <= symbol && symbol <= last => {
+                        self.add_path(&mut next_paths, &mut visited, instruction+1, tags, position, &mut best_match);
+                    },
+
+                    (Instruction::EndOfInput, None) => {
+                        self.add_path(&mut next_paths, &mut visited, instruction+1, tags, position, &mut best_match);
+                    },
+
+                    _ => { }
+                }
+            }
+
+            paths = next_paths;
+        }
+
+        // Report the captures that have both a start and an end
+        best_match.map(|(length, tags)| {
+            let captures = self.capture_ids.iter()
+                .enumerate()
+                .filter_map(|(index, id)| match (tags[index*2], tags[index*2+1]) {
+                    (Some(start), Some(end))    => Some((*id, start..end)),
+                    _                           => None
+                })
+                .collect();
+
+            (length, captures)
+        })
+    }
+}
+
+///
+/// Matches a source stream against a pattern, returning the length of the match and the range matched by each of its
+/// capture groups
+///
+/// ```
+/// # use concordance::*;
+/// let (length, captures) = matches_with_captures("ab12", MatchRange('a', 'z').repeat_forever(1).capture(1)).unwrap();
+///
+/// // length == 2, captures[&1] == 0..2
+/// # assert!(length == 2);
+/// # assert!(captures[&1] == (0..2));
+/// ```
+///
+pub fn matches_with_captures<'a, Symbol, TPattern, Source>(source: Source, pattern: TPattern) -> Option<(usize, HashMap<usize, Range<usize>>)>
+where   TPattern: ToPattern<Symbol>
+,       Source: SymbolSource<'a, Symbol>
+,       Symbol: Clone+Ord {
+    CaptureMatcher::new(&pattern.to_pattern()).match_captures(source)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn capture_value_of_key() {
+        let digits      = MatchRange('0', '9').repeat_forever(1).capture(1);
+        let key_value   = MatchRange('a', 'z').repeat_forever(1).capture(0).append("=").append(digits);
+
+        let (length, captures) = matches_with_captures("key=42", key_value).unwrap();
+
+        assert!(length == 6);
+        assert!(captures[&0] == (0..3));
+        assert!(captures[&1] == (4..6));
+    }
+
+    #[test]
+    fn captures_do_not_change_what_matches() {
+        let pattern = MatchRange('a', 'z').repeat_forever(1).capture(1).append(exactly("!").repeat(0..2));
+
+        assert!(matches("abc!", pattern.clone()) == Some(4));
+        assert!(matches_with_captures("abc!", pattern.clone()).map(|(length, _)| length) == Some(4));
+        assert!(matches_with_captures("123", pattern).is_none());
+    }
+
+    #[test]
+    fn only_matched_alternatives_are_captured() {
+        let pattern = exactly("a").capture(1).or(exactly("b").capture(2));
+
+        let (_, captures) = matches_with_captures("b", pattern).unwrap();
+
+        assert!(!captures.contains_key(&1));
+        assert!(captures[&2] == (0..1));
+    }
+
+    #[test]
+    fn repeated_captures_report_last_match() {
+        let pattern = MatchRange('a', 'z').capture(1).append(",").repeat_forever(1);
+
+        let (length, captures) = matches_with_captures("a,b,c,d", pattern).unwrap();
+
+        assert!(length == 6);
+        assert!(captures[&1] == (4..5));
+    }
+
+    #[test]
+    fn captures_use_longest_match() {
+        // The first alternative matches first but the second one matches more of the input
+        let pattern = exactly("ab").capture(1).or(exactly("abc").capture(2));
+
+        let (length, captures) = matches_with_captures("abcd", pattern).unwrap();
+
+        assert!(length == 3);
+        assert!(!captures.contains_key(&1));
+        assert!(captures[&2] == (0..3));
+    }
+
+    #[test]
+    fn captures_respect_start_anchor() {
+        let pattern = exactly("x").optional().append(start_anchor()).append(exactly("ab").capture(1));
+
+        assert!(matches_with_captures("ab", pattern.clone()).map(|(_, captures)| captures[&1].clone()) == Some(0..2));
+        assert!(matches_with_captures("xab", pattern.clone()).is_none());
+        assert!(matches("ab", pattern) == Some(2));
+    }
+
+    #[test]
+    fn captures_agree_with_dfa_for_optional_symbols_after_end_anchor() {
+        let pattern = exactly("x").capture(1).append(end_anchor()).append(exactly("y").optional());
+
+        assert!(matches_with_captures("x", pattern.clone()).map(|(length, _)| length) == Some(1));
+        assert!(matches("x", pattern) == Some(1));
+    }
+
+    #[test]
+    fn captures_respect_end_anchor() {
+        let pattern = exactly("ab").capture(1).append(end_anchor()).append(end_anchor().repeat_forever(0));
+
+        assert!(matches_with_captures("ab", pattern.clone()).map(|(_, captures)| captures[&1].clone()) == Some(0..2));
+        assert!(matches_with_captures("abc", pattern).is_none());
+    }
+}
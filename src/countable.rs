@@ -21,6 +21,28 @@
 //! Symbols used as input to the range-based DFAs must implement the `Countable` trait, which is needed to generate non-overlapping
 //! ranges.
 //!
+//! Countable types can optionally report the lowest and highest symbol in their alphabet. Types that don't know their bounds
+//! (for example, interned strings where new symbols can be created at any time) are said to have an 'open' alphabet. Operations
+//! that need to know about every symbol, such as negating a set of ranges, can still be performed on an open alphabet but their
+//! results can't be turned into a finite set of ranges.
+//!
+//! C-like enums can be used as symbols by implementing `CountableEnum`, which only needs a list of their variants:
+//!
+//! ```
+//! # use concordance::*;
+//! #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+//! enum Suit { Clubs, Diamonds, Hearts, Spades }
+//!
+//! impl CountableEnum for Suit {
+//!     const VARIANTS: &'static [Suit] = &[Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+//! }
+//!
+//! let red = MatchRange(Suit::Diamonds, Suit::Hearts).repeat_forever(1);
+//!
+//! matches(&[Suit::Hearts, Suit::Diamonds, Suit::Spades][..], red.clone());   // == Some(2)
+//! # assert!(matches(&[Suit::Hearts, Suit::Diamonds, Suit::Spades][..], red) == Some(2));
+//! ```
+//!
 
 // TODO: could make next/prev return Option<Self> which would let us deal with max/min values. However, we use this internally
 // where we can expect this not to matter.
@@ -33,66 +55,145 @@ use std::char;
 pub trait Countable {
     fn next(&self) -> Self;
     fn prev(&self) -> Self;
+
+    ///
+    /// The lowest symbol of this type, or None if the alphabet is open
+    ///
+    fn min_symbol() -> Option<Self> where Self: Sized { None }
+
+    ///
+    /// The highest symbol of this type, or None if the alphabet is open
+    ///
+    fn max_symbol() -> Option<Self> where Self: Sized { None }
 }
 
 impl Countable for usize { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(usize::MIN) }
+    fn max_symbol() -> Option<Self> { Some(usize::MAX) }
 }
 
 impl Countable for u8 { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(u8::MIN) }
+    fn max_symbol() -> Option<Self> { Some(u8::MAX) }
 }
 
 impl Countable for u16 { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(u16::MIN) }
+    fn max_symbol() -> Option<Self> { Some(u16::MAX) }
 }
 
 impl Countable for u32 { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(u32::MIN) }
+    fn max_symbol() -> Option<Self> { Some(u32::MAX) }
 }
 
 impl Countable for isize { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(isize::MIN) }
+    fn max_symbol() -> Option<Self> { Some(isize::MAX) }
 }
 
 impl Countable for i8 { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(i8::MIN) }
+    fn max_symbol() -> Option<Self> { Some(i8::MAX) }
 }
 
 impl Countable for i16 { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(i16::MIN) }
+    fn max_symbol() -> Option<Self> { Some(i16::MAX) }
 }
 
 impl Countable for i32 { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(i32::MIN) }
+    fn max_symbol() -> Option<Self> { Some(i32::MAX) }
 }
 
 impl Countable for u64 { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(u64::MIN) }
+    fn max_symbol() -> Option<Self> { Some(u64::MAX) }
 }
 
 impl Countable for i64 { 
     fn next(&self) -> Self { *self+1 }
     fn prev(&self) -> Self { *self-1 }
+
+    fn min_symbol() -> Option<Self> { Some(i64::MIN) }
+    fn max_symbol() -> Option<Self> { Some(i64::MAX) }
+}
+
+impl Countable for bool { 
+    fn next(&self) -> Self { !*self }
+    fn prev(&self) -> Self { !*self }
+
+    fn min_symbol() -> Option<Self> { Some(false) }
+    fn max_symbol() -> Option<Self> { Some(true) }
+}
+
+///
+/// Trait implemented by enums that can be used as symbols by listing their variants
+///
+/// Every type that implements this trait is also `Countable`. The variants must be listed in the same order as they're
+/// sorted by `Ord`, which is the order they're declared in if `Ord` is derived.
+///
+pub trait CountableEnum: Copy+PartialEq+'static {
+    ///
+    /// Every variant of this enum, from lowest to highest
+    ///
+    const VARIANTS: &'static [Self];
+}
+
+impl<Symbol: CountableEnum> Countable for Symbol {
+    fn next(&self) -> Self {
+        let index = Symbol::VARIANTS.iter().position(|variant| variant == self).unwrap();
+        Symbol::VARIANTS[(index+1) % Symbol::VARIANTS.len()]
+    }
+
+    fn prev(&self) -> Self {
+        let index = Symbol::VARIANTS.iter().position(|variant| variant == self).unwrap();
+        Symbol::VARIANTS[(index + Symbol::VARIANTS.len()-1) % Symbol::VARIANTS.len()]
+    }
+
+    fn min_symbol() -> Option<Self> { Symbol::VARIANTS.first().cloned() }
+    fn max_symbol() -> Option<Self> { Symbol::VARIANTS.last().cloned() }
 }
 
 impl Countable for char { 
     fn next(&self) -> Self { char::from_u32((*self as u32)+1).unwrap_or('\u{0000}') }
     fn prev(&self) -> Self { char::from_u32((*self as u32)-1).unwrap_or('\u{ffff}') }
+
+    fn min_symbol() -> Option<Self> { Some('\u{0000}') }
+    fn max_symbol() -> Option<Self> { Some(char::MAX) }
 }
 
 #[cfg(test)]
 mod test {
-    use super::*;
+    use super::super::*;
 
     #[test]
     fn can_get_next_prev_i8() {
@@ -165,4 +266,65 @@ mod test {
         assert!(val.next() == 'c');
         assert!(val.prev() == 'a');
     }
+
+    #[test]
+    fn integers_have_bounds() {
+        assert!(u8::min_symbol() == Some(0));
+        assert!(u8::max_symbol() == Some(255));
+        assert!(i32::min_symbol() == Some(i32::MIN));
+        assert!(char::max_symbol() == Some(char::MAX));
+    }
+
+    #[test]
+    fn full_byte_range_matches_any_byte() {
+        let any_byte = MatchRange(0u8, 255u8);
+
+        assert!(matches(&[0x7fu8][..], any_byte.clone()) == Some(1));
+        assert!(matches(&[255u8, 0u8][..], any_byte.clone().repeat_forever(1)) == Some(2));
+        assert!(matches(&[255u8][..], MatchRange(0u8, 127u8).or(MatchRange(128u8, 255u8))) == Some(1));
+    }
+
+    #[test]
+    fn complements_reach_the_ends_of_integer_alphabets() {
+        let not_digit = !MatchRange(b'0', b'9');
+
+        assert!(matches(&[0u8][..], not_digit.clone()) == Some(1));
+        assert!(matches(&[255u8][..], not_digit.clone()) == Some(1));
+        assert!(matches(&[u16::MAX][..], !MatchRange(0u16, 10u16)) == Some(1));
+        assert!(matches(&[i32::MIN][..], !MatchRange(0i32, 10i32)) == Some(1));
+        assert!(matches(&[usize::MAX][..], MatchRange(0usize, usize::MAX)) == Some(1));
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+    enum Light { Red, Amber, Green }
+
+    impl CountableEnum for Light {
+        const VARIANTS: &'static [Light] = &[Light::Red, Light::Amber, Light::Green];
+    }
+
+    #[test]
+    fn can_get_next_prev_bool() {
+        assert!(false.next());
+        assert!(!true.prev());
+        assert!(bool::min_symbol() == Some(false));
+        assert!(bool::max_symbol() == Some(true));
+    }
+
+    #[test]
+    fn can_get_next_prev_enum() {
+        assert!(Light::Red.next() == Light::Amber);
+        assert!(Light::Green.prev() == Light::Amber);
+        assert!(Light::min_symbol() == Some(Light::Red));
+        assert!(Light::max_symbol() == Some(Light::Green));
+    }
+
+    #[test]
+    fn enum_can_match_full_range() {
+        let any_light = MatchRange(Light::Red, Light::Green).repeat_forever(1);
+
+        assert!(matches(&[Light::Green, Light::Red, Light::Amber][..], any_light) == Some(3));
+        assert!(matches(&[Light::Amber, Light::Red][..], MatchRange(Light::Amber, Light::Green)) == Some(1));
+        assert!(matches(&[Light::Green][..], !MatchRange(Light::Red, Light::Amber)) == Some(1));
+        assert!(matches(&[true, false][..], MatchRange(false, true).repeat_forever(1)) == Some(2));
+    }
 }
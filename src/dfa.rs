@@ -0,0 +1,200 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! A `Dfa` is a `SymbolRangeDfa` that has been checked to be deterministic (no state has overlapping transitions) and
+//! complete (every state has a transition for every symbol in the alphabet). The only way to create one is by calling
+//! `Dfa::try_from()`, so algorithms that take a `Dfa` don't need to check these properties again.
+//!
+//! DFAs generated by the compiler usually aren't complete, as symbols that can't lead to a match have no transition.
+//!
+//! ```
+//! # use concordance::*;
+//! use std::convert::TryFrom;
+//!
+//! let dfa = exactly("abc").prepare_to_match();
+//!
+//! assert!(Dfa::try_from(dfa).is_err());
+//! ```
+//!
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use super::countable::*;
+use super::symbol_range::*;
+use super::state_machine::*;
+use super::symbol_range_dfa::*;
+
+///
+/// The reasons a `SymbolRangeDfa` can fail to be converted into a `Dfa`
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DfaError {
+    /// The symbol type does not have a minimum and maximum symbol, so the DFA can't be complete
+    OpenAlphabet,
+
+    /// A state has transitions that overlap or are out of order
+    NotDeterministic(StateId),
+
+    /// A state has no transition for some of the symbols in the alphabet
+    NotComplete(StateId),
+
+    /// A state has a transition to a state that doesn't exist
+    InvalidTarget(StateId)
+}
+
+///
+/// A DFA that is known to be deterministic and complete
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Dfa<InputSymbol: Ord, OutputSymbol> {
+    /// The checked DFA
+    dfa: SymbolRangeDfa<InputSymbol, OutputSymbol>
+}
+
+impl<InputSymbol: Ord, OutputSymbol> Dfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Returns the underlying symbol range DFA
+    ///
+    pub fn as_symbol_range_dfa(&self) -> &SymbolRangeDfa<InputSymbol, OutputSymbol> {
+        &self.dfa
+    }
+
+    ///
+    /// Converts this back into a symbol range DFA
+    ///
+    pub fn into_symbol_range_dfa(self) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+        self.dfa
+    }
+
+    ///
+    /// Returns the state that follows a particular state for a symbol
+    ///
+    /// As the DFA is complete, there is always exactly one next state.
+    ///
+    pub fn next_state(&self, state: StateId, symbol: &InputSymbol) -> StateId {
+        let transitions = self.dfa.transitions(state);
+        let index       = transitions.binary_search_by(|(range, _)| {
+            if range.highest < *symbol {
+                Ordering::Less
+            } else if range.lowest > *symbol {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        });
+
+        transitions[index.unwrap()].1
+    }
+}
+
+///
+/// Checks that the transitions for a state are ordered, don't overlap and cover the whole of an alphabet
+///
+fn check_transitions<InputSymbol: Ord+Countable>(transitions: &[(SymbolRange<InputSymbol>, StateId)], min_symbol: &InputSymbol, max_symbol: &InputSymbol, state: StateId) -> Result<(), DfaError> {
+    let first = transitions.first().ok_or(DfaError::NotComplete(state))?;
+    let last  = transitions.last().unwrap();
+
+    if first.0.lowest != *min_symbol || last.0.highest != *max_symbol {
+        return Err(DfaError::NotComplete(state));
+    }
+
+    for pair in transitions.windows(2) {
+        let (ref previous, _)   = pair[0];
+        let (ref next, _)       = pair[1];
+
+        if next.lowest <= previous.highest {
+            return Err(DfaError::NotDeterministic(state));
+        } else if next.lowest != previous.highest.next() {
+            return Err(DfaError::NotComplete(state));
+        }
+    }
+
+    Ok(())
+}
+
+impl<InputSymbol: Ord+Clone+Countable, OutputSymbol> TryFrom<SymbolRangeDfa<InputSymbol, OutputSymbol>> for Dfa<InputSymbol, OutputSymbol> {
+    type Error = DfaError;
+
+    fn try_from(dfa: SymbolRangeDfa<InputSymbol, OutputSymbol>) -> Result<Dfa<InputSymbol, OutputSymbol>, DfaError> {
+        let min_symbol = InputSymbol::min_symbol().ok_or(DfaError::OpenAlphabet)?;
+        let max_symbol = InputSymbol::max_symbol().ok_or(DfaError::OpenAlphabet)?;
+        let num_states = dfa.count_states();
+
+        for state in 0..num_states {
+            let transitions = dfa.get_transitions_for_state(state);
+
+            if transitions.iter().any(|(_, target)| *target >= num_states) {
+                return Err(DfaError::InvalidTarget(state));
+            }
+
+            check_transitions(&transitions, &min_symbol, &max_symbol, state)?;
+        }
+
+        Ok(Dfa { dfa })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn compiled_dfa_is_not_complete() {
+        let dfa = Match(vec![1u8, 2u8]).prepare_to_match();
+
+        assert!(Dfa::try_from(dfa) == Err(DfaError::NotComplete(0)));
+    }
+
+    #[test]
+    fn complete_dfa_is_accepted() {
+        let mut builder = SymbolRangeDfaBuilder::new();
+
+        // State 0: '0' moves to state 1, anything else to state 2
+        builder.start_state();
+        builder.transition(SymbolRange::new(0u8, 0u8), 1);
+        builder.transition(SymbolRange::new(1u8, 255u8), 2);
+
+        // State 1: accepts, anything moves to state 2
+        builder.start_state();
+        builder.transition(SymbolRange::new(0u8, 255u8), 2);
+        builder.accept("Zero");
+
+        // State 2: never accepts
+        builder.start_state();
+        builder.transition(SymbolRange::new(0u8, 255u8), 2);
+
+        let dfa = Dfa::try_from(builder.build()).unwrap();
+
+        assert!(dfa.next_state(0, &0) == 1);
+        assert!(dfa.next_state(0, &100) == 2);
+        assert!(dfa.next_state(1, &0) == 2);
+    }
+
+    #[test]
+    fn overlapping_transitions_are_rejected() {
+        let mut builder = SymbolRangeDfaBuilder::new();
+
+        builder.start_state();
+        builder.transition(SymbolRange::new(0u8, 10u8), 0);
+        builder.transition(SymbolRange::new(5u8, 255u8), 0);
+        builder.accept(());
+
+        assert!(Dfa::try_from(builder.build()) == Err(DfaError::NotDeterministic(0)));
+    }
+}
@@ -20,7 +20,8 @@
 //!
 //! The NDFA should not have any overlapping symbols, which is to say symbols that are not equal and yet could match the same
 //! input symbol. If the builder finds that two NDFA states have identical output symbols, then the builder will pick the symbol
-//! that compares as being lower as the final output symbol.
+//! that compares as being lower as the final output symbol. This can be changed by supplying a different `MergePolicy` to
+//! `build_with_policy`.
 //!
 //! Any NDFA can be converted into a DFA: if the NDFA can move to two states as the result of a particular input symbol, the DFA
 //! just needs a single new state representing both those possible states. In this way, the NDFA can be converted into a form where
@@ -46,6 +47,48 @@ use std::iter::*;
 use super::dfa_builder::*;
 use super::state_machine::*;
 
+///
+/// Decides on the output symbol for a DFA state that corresponds to more than one accepting NDFA state
+///
+pub trait MergePolicy<OutputSymbol> {
+    ///
+    /// Merges the output symbols for a DFA state into a single output symbol
+    ///
+    /// The outputs are distinct and sorted, and there's always at least one of them.
+    ///
+    fn merge(&self, outputs: &[OutputSymbol]) -> OutputSymbol;
+}
+
+///
+/// The default merge policy, where the output symbol that is ordered lowest is used
+///
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LowestOutput;
+
+impl<OutputSymbol: Clone> MergePolicy<OutputSymbol> for LowestOutput {
+    fn merge(&self, outputs: &[OutputSymbol]) -> OutputSymbol {
+        outputs[0].clone()
+    }
+}
+
+///
+/// Merge policy for NDFAs where each output is a list of symbols, which keeps every symbol that a DFA state can produce
+///
+/// The merged list is sorted, and has any duplicate symbols removed.
+///
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllOutputs;
+
+impl<OutputSymbol: Clone+Ord> MergePolicy<Vec<OutputSymbol>> for AllOutputs {
+    fn merge(&self, outputs: &[Vec<OutputSymbol>]) -> Vec<OutputSymbol> {
+        let mut all_outputs = outputs.concat();
+        all_outputs.sort();
+        all_outputs.dedup();
+
+        all_outputs
+    }
+}
+
 ///
 /// Builds a deterministic finite automaton from a NDFA
 ///
@@ -76,7 +119,7 @@ impl DfaState {
             set.insert(state);
         }
 
-        DfaState { source_states: Vec::from_iter(set.into_iter()) }
+        DfaState { source_states: Vec::from_iter(set) }
     }
 
     /// Removes any duplicate states from source_states
@@ -101,11 +144,11 @@ struct DfaTransitions<InputSymbol, OutputSymbol: Ord> {
 impl<InputSymbol: Ord+Clone, OutputSymbol: Ord> DfaTransitions<InputSymbol, OutputSymbol> {
     /// Goes through the transitions in the object and merges the states of any with the same symbol
     fn merge_states(&mut self) {
-        if self.transitions.len() > 0 {
+        if !self.transitions.is_empty() {
             // Order the transitions so that if two transitions have the same input symbol, they are neighbours
             self.transitions.sort_by(|a, b| {
-                let &(ref symbol_a, _) = a;
-                let &(ref symbol_b, _) = b;
+                let (symbol_a, _) = a;
+                let (symbol_b, _) = b;
 
                 symbol_a.cmp(symbol_b)
             });
@@ -140,12 +183,13 @@ impl<InputSymbol: Ord+Clone, OutputSymbol: Ord> DfaTransitions<InputSymbol, Outp
     ///
     /// Finds the output symbol that corresponds to this state
     ///
-    /// Rule is that if there is more than one output symbol then the symbol whose value is ordered lowest is the output for this state
+    /// If there is more than one output symbol then the merge policy decides what the output for this state is
     ///
-    fn output_symbol(&mut self) -> Option<&OutputSymbol> {
-        if self.output.len() > 0 {
+    fn output_symbol(&mut self, policy: &dyn MergePolicy<OutputSymbol>) -> Option<OutputSymbol> {
+        if !self.output.is_empty() {
             self.output.sort();
-            Some(&self.output[0])
+            self.output.dedup();
+            Some(policy.merge(&self.output))
         } else {
             None
         }
@@ -162,17 +206,56 @@ impl<InputSymbol: Ord+Clone, OutputSymbol: Ord+Clone, DfaType, Ndfa: StateMachin
         compiler.compile()
     }
 
+    ///
+    /// Builds a DFA using an NDFA and a builder, using a custom policy to decide the output symbol for states that
+    /// have more than one
+    ///
+    pub fn build_with_policy(ndfa: Ndfa, builder: Builder, policy: &dyn MergePolicy<OutputSymbol>) -> DfaType {
+        let compiler = DfaCompiler::new(ndfa, builder);
+        compiler.compile_with_policy(policy)
+    }
+
+    ///
+    /// Builds a DFA using an NDFA and a builder, also returning the NDFA states that each DFA state represents
+    ///
+    /// See `compile_with_state_map()` for details.
+    ///
+    pub fn build_with_state_map(ndfa: Ndfa, builder: Builder) -> (DfaType, Vec<Vec<StateId>>) {
+        let compiler = DfaCompiler::new(ndfa, builder);
+        compiler.compile_with_state_map(&LowestOutput)
+    }
+
     ///
     /// Creates a new DFA compiler using a particular builder and NDFA
     ///
     pub fn new(ndfa: Ndfa, builder: Builder) -> Self {
-        DfaCompiler { ndfa: ndfa, builder: builder, phantom: (PhantomData, PhantomData, PhantomData) }
+        DfaCompiler { ndfa, builder, phantom: (PhantomData, PhantomData, PhantomData) }
     }
 
     ///
     /// Compiles the NDFA into a DFA
     ///
     pub fn compile(self) -> DfaType {
+        self.compile_with_policy(&LowestOutput)
+    }
+
+    ///
+    /// Compiles the NDFA into a DFA, using the specified policy to merge output symbols
+    ///
+    pub fn compile_with_policy(self, policy: &dyn MergePolicy<OutputSymbol>) -> DfaType {
+        let (dfa, _) = self.compile_with_state_map(policy);
+        dfa
+    }
+
+    ///
+    /// Compiles the NDFA into a DFA, returning the DFA along with the NDFA states that each DFA state represents
+    ///
+    /// The state map is indexed by DFA state ID, and each entry is a sorted list of NDFA state IDs, including every state in
+    /// the `epsilon_closure()` of the states the compiler visited. This is useful for finding out why the DFA behaves as it
+    /// does: for example, a DFA state that represents states from two different patterns is one where both patterns can
+    /// still match.
+    ///
+    pub fn compile_with_state_map(self, policy: &dyn MergePolicy<OutputSymbol>) -> (DfaType, Vec<Vec<StateId>>) {
         // We assume that input symbols are non-overlapping, which is not automatically the case for symbol ranges
         // You can call Ndfa.fix_overlapping_ranges() to remove any overlapping ranges from an NDFA
 
@@ -205,11 +288,11 @@ impl<InputSymbol: Ord+Clone, OutputSymbol: Ord+Clone, DfaType, Ndfa: StateMachin
             }
 
             // Merge it so that we only have one transition per symbol
-            let mut dfa_transitions = DfaTransitions { state_id: states.len() as StateId, transitions: transitions, output: output };
+            let mut dfa_transitions = DfaTransitions { state_id: states.len() as StateId, transitions, output };
             dfa_transitions.merge_states();
 
             // Process any generated states that are not already in the DFA
-            for &(_, ref maybe_new_state) in &dfa_transitions.transitions {
+            for (_, maybe_new_state) in &dfa_transitions.transitions {
                 if !known_states.contains_key(maybe_new_state) {
                     to_process.push(maybe_new_state.clone());
                 }
@@ -220,14 +303,27 @@ impl<InputSymbol: Ord+Clone, OutputSymbol: Ord+Clone, DfaType, Ndfa: StateMachin
             states.push(dfa_transitions);
         }
 
+        // Work out which NDFA states each DFA state came from
+        let mut state_map = vec![vec![]; states.len()];
+
+        for (state, state_id) in known_states.iter() {
+            let mut source_states = state.source_states.iter()
+                .flat_map(|source_state| self.ndfa.epsilon_closure(*source_state))
+                .collect::<Vec<_>>();
+            source_states.sort();
+            source_states.dedup();
+
+            state_map[*state_id as usize] = source_states;
+        }
+
         // Build the DFA
         let mut builder = self.builder;
 
         for mut dfa_state in states {
             builder.start_state();
 
-            if let Some(output_symbol) = dfa_state.output_symbol() {
-                builder.accept(output_symbol.clone());
+            if let Some(output_symbol) = dfa_state.output_symbol(policy) {
+                builder.accept(output_symbol);
             }
 
             for (symbol, target_state) in dfa_state.transitions {
@@ -236,7 +332,7 @@ impl<InputSymbol: Ord+Clone, OutputSymbol: Ord+Clone, DfaType, Ndfa: StateMachin
         }
 
         // Generate the final DFA
-        builder.build()
+        (builder.build(), state_map)
     }
 }
 
@@ -244,10 +340,13 @@ impl<InputSymbol: Ord+Clone, OutputSymbol: Ord+Clone, DfaType, Ndfa: StateMachin
 mod test {
     use super::*;
     use super::super::regular_pattern::*;
-    use super::super::state_machine::*;
     use super::super::symbol_range_dfa::*;
     use super::super::pattern_matcher::*;
     use super::super::symbol_reader::*;
+    use super::super::matches::*;
+    use super::super::tokenizer::*;
+    use super::super::ndfa::*;
+    use super::super::symbol_range::*;
 
     #[test]
     fn can_create_compiler() {
@@ -343,4 +442,56 @@ mod test {
             assert!(false);
         }
     }
+
+    #[test]
+    fn can_merge_outputs_with_policy() {
+        struct ConcatenatePolicy;
+
+        impl MergePolicy<String> for ConcatenatePolicy {
+            fn merge(&self, outputs: &[String]) -> String {
+                outputs.concat()
+            }
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(exactly("ab"), String::from("A"));
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), String::from("B"));
+
+        let state_machine = DfaCompiler::build_with_policy(token_matcher.to_ndfa(), SymbolRangeDfaBuilder::new(), &ConcatenatePolicy);
+
+        match match_pattern(state_machine.start(), &mut "ab".read_symbols()) {
+            Accept(2, output)   => assert!(output == "AB"),
+            _                   => panic!()
+        }
+
+        match match_pattern(state_machine.start(), &mut "abc".read_symbols()) {
+            Accept(3, output)   => assert!(output == "B"),
+            _                   => panic!()
+        }
+    }
+
+    #[test]
+    fn start_state_maps_to_ndfa_start() {
+        // 'ab' or 'ac', with the start state reaching each alternative via an epsilon transition
+        let mut ndfa = Ndfa::new();
+        ndfa.add_epsilon(0, 1);
+        ndfa.add_epsilon(0, 2);
+        ndfa.add_transition(1, SymbolRange::single('a'), 3);
+        ndfa.add_transition(3, SymbolRange::single('b'), 5);
+        ndfa.add_transition(2, SymbolRange::single('a'), 4);
+        ndfa.add_transition(4, SymbolRange::single('c'), 6);
+        ndfa.set_output_symbol(5, String::from("A"));
+        ndfa.set_output_symbol(6, String::from("B"));
+
+        let (dfa, state_map)        = DfaCompiler::build_with_state_map(ndfa.clone(), SymbolRangeDfaBuilder::new());
+
+        // One entry per DFA state, with the start state representing the epsilon closure of the NDFA start state
+        assert!(state_map.len() == dfa.states().count());
+        assert!(state_map[0] == vec![0, 1, 2]);
+        assert!(state_map[0] == ndfa.epsilon_closure(0));
+
+        // After reading 'a', both patterns are still possible so the next state represents one NDFA state from each
+        let after_a                 = dfa.transitions(0).iter().find(|(range, _)| range.lowest <= 'a' && range.highest >= 'a').unwrap().1;
+        assert!(state_map[after_a as usize].len() == 2);
+    }
 }
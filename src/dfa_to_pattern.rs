@@ -0,0 +1,283 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! A `SymbolRangeDfa` can be turned back into a `Pattern` that matches the same strings. This makes it possible to perform
+//! operations that are easiest to do on a DFA (such as finding the complement) and then go back to working with patterns.
+//!
+//! The conversion works by state elimination: each state of the DFA is removed in turn, and the transitions that went through
+//! it are replaced by transitions labelled with patterns. Once every state is removed, only a single transition remains,
+//! from the start to the end of the DFA, which is the pattern for the whole DFA. Output symbols are not preserved: every
+//! accepting state is treated the same way.
+//!
+//! ```
+//! # use concordance::*;
+//! let dfa     = exactly("abc").repeat_forever(1).prepare_to_match();
+//! let pattern = dfa.to_pattern();
+//!
+//! matches("abcabc", pattern); // == Some(6)
+//! # assert!(matches("abcabc", dfa.to_pattern()) == Some(6));
+//! ```
+//!
+
+use std::collections::HashSet;
+
+use super::countable::*;
+use super::regular_pattern::*;
+use super::state_machine::*;
+use super::symbol_range_dfa::*;
+
+///
+/// Creates a pattern that matches either of two patterns
+///
+fn either<Symbol: Clone+PartialEq>(first: Option<Pattern<Symbol>>, second: Pattern<Symbol>) -> Pattern<Symbol> {
+    match first {
+        None                                => second,
+        Some(ref first) if *first == second => second,
+        Some(MatchAny(mut patterns))        => { patterns.push(second); MatchAny(patterns) },
+        Some(first)                         => MatchAny(vec![first, second])
+    }
+}
+
+///
+/// Creates a pattern that matches a series of patterns in order
+///
+fn sequence<Symbol: Clone>(patterns: Vec<Pattern<Symbol>>) -> Pattern<Symbol> {
+    let mut result = vec![];
+
+    for pattern in patterns {
+        match (result.last_mut(), pattern) {
+            (_, Epsilon)                                        => { },
+            (Some(&mut Match(ref mut last)), Match(symbols))    => last.extend(symbols),
+            (_, MatchAll(patterns))                             => result.extend(patterns),
+            (_, pattern)                                        => result.push(pattern)
+        }
+    }
+
+    match result.len() {
+        0 => Epsilon,
+        1 => result.pop().unwrap(),
+        _ => MatchAll(result)
+    }
+}
+
+impl<Symbol: Clone+Ord+Countable, OutputSymbol> SymbolRangeDfa<Symbol, OutputSymbol> {
+    ///
+    /// Returns a pattern that matches the strings accepted by this DFA when it begins in a particular state
+    ///
+    fn pattern_from_state(&self, initial_state: StateId) -> Pattern<Symbol> {
+        let num_states  = self.count_states() as usize;
+        let start       = num_states;
+        let end         = num_states+1;
+
+        // edges[x][y] is the pattern that moves from state x to state y. There are two extra states: one to start from, and
+        // one that every accepting state moves to
+        let mut edges: Vec<Vec<Option<Pattern<Symbol>>>> = vec![vec![None; num_states+2]; num_states+2];
+
+        edges[start][initial_state as usize] = Some(Epsilon);
+
+        for (state, state_edges) in edges.iter_mut().enumerate().take(num_states) {
+            for (range, target) in self.get_transitions_for_state(state as StateId) {
+                let symbol_pattern = if range.lowest == range.highest { Match(vec![range.lowest]) } else { MatchRange(range.lowest, range.highest) };
+                let existing       = state_edges[target as usize].take();
+
+                state_edges[target as usize] = Some(either(existing, symbol_pattern));
+            }
+
+            // States that only accept when the input has run out need an end anchor
+            if self.output_symbol_for_state(state as StateId).is_some() {
+                state_edges[end] = Some(Epsilon);
+            } else if self.output_at_end(state as StateId).is_some() {
+                state_edges[end] = Some(EndOfInput);
+            }
+        }
+
+        // Eliminate the states one at a time
+        let mut remaining: Vec<usize> = (0..num_states+2).collect();
+
+        for state in 0..num_states {
+            remaining.retain(|other| *other != state);
+
+            let self_loop = edges[state][state].take().map(|pattern| RepeatInfinite(0, Box::new(pattern)));
+
+            for from in remaining.iter() {
+                let into_state = if let Some(ref into_state) = edges[*from][state] { into_state.clone() } else { continue };
+
+                for to in remaining.iter() {
+                    let out_of_state = if let Some(ref out_of_state) = edges[state][*to] { out_of_state.clone() } else { continue };
+
+                    // from -> to can now also go via this state
+                    let via_state = match self_loop {
+                        Some(ref self_loop) => sequence(vec![into_state.clone(), self_loop.clone(), out_of_state]),
+                        None                => sequence(vec![into_state.clone(), out_of_state])
+                    };
+
+                    let existing = edges[*from][*to].take();
+                    edges[*from][*to] = Some(either(existing, via_state));
+                }
+            }
+
+            for other_edges in edges.iter_mut() {
+                other_edges[state] = None;
+            }
+
+            edges[state] = vec![None; num_states+2];
+        }
+
+        // An empty MatchAny will never match anything
+        edges[start][end].take().unwrap_or_else(|| MatchAny(vec![]))
+    }
+
+    ///
+    /// Returns true if every string that this DFA accepts when it begins in one state is also accepted when it begins in
+    /// another state
+    ///
+    fn accepts_subset(&self, state: StateId, of_state: StateId) -> bool {
+        // States are None once the DFA has no transition for the input
+        let mut visited     = HashSet::new();
+        let mut to_process  = vec![(Some(state), Some(of_state))];
+
+        visited.insert((Some(state), Some(of_state)));
+
+        while let Some((our_state, their_state)) = to_process.pop() {
+            let we_accept   = our_state.and_then(|state| self.output(state)).is_some();
+            let they_accept = their_state.and_then(|state| self.output(state)).is_some();
+
+            if we_accept && !they_accept {
+                return false;
+            }
+
+            let we_accept   = our_state.and_then(|state| self.output_at_end(state)).is_some();
+            let they_accept = their_state.and_then(|state| self.output_at_end(state)).is_some();
+
+            if we_accept && !they_accept {
+                return false;
+            }
+
+            for (_, next_state) in self.paired_transitions(self, (our_state, their_state)) {
+                if visited.insert(next_state) {
+                    to_process.push(next_state);
+                }
+            }
+        }
+
+        true
+    }
+}
+
+impl<Symbol: Clone+Ord+Countable, OutputSymbol> ToPattern<Symbol> for SymbolRangeDfa<Symbol, OutputSymbol> {
+    ///
+    /// Converts this DFA into a pattern
+    ///
+    /// A DFA with a separate state for matching at the start of the input produces a pattern containing a `start_anchor()`,
+    /// and states that only accept at the end of the input produce an `end_anchor()`. Anchors can only make a pattern match
+    /// more strings at the start or the end of the input, so this will panic if the DFA rejects something there that it
+    /// accepts elsewhere (as the DFA for the complement of an anchored pattern does).
+    ///
+    fn to_pattern(&self) -> Pattern<Symbol> {
+        if self.states().any(|state| self.output(state).is_some() && self.output_at_end(state).is_none()) {
+            panic!("A DFA that accepts fewer strings at the end of the input than elsewhere can't be converted to a pattern");
+        }
+
+        let from_anywhere = self.pattern_from_state(0);
+
+        if !self.has_start_of_input_state() {
+            return from_anywhere;
+        }
+
+        let start_of_input = self.initial_state(true);
+        if !self.accepts_subset(0, start_of_input) {
+            panic!("A DFA that accepts fewer strings at the start of the input than elsewhere can't be converted to a pattern");
+        }
+
+        let at_start = sequence(vec![StartOfInput, self.pattern_from_state(start_of_input)]);
+
+        match from_anywhere {
+            MatchAny(ref patterns) if patterns.is_empty()   => at_start,
+            from_anywhere                                   => either(Some(from_anywhere), at_start)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn literal_round_trip() {
+        let pattern = exactly("abc").prepare_to_match().to_pattern();
+
+        assert!(pattern == exactly("abc"));
+    }
+
+    #[test]
+    fn repeat_round_trip() {
+        let pattern = exactly("ab").repeat_forever(1).or("x").prepare_to_match().to_pattern();
+
+        assert!(matches("ababx", pattern.clone()) == Some(4));
+        assert!(matches("x", pattern.clone()) == Some(1));
+        assert!(matches("a", pattern.clone()).is_none());
+    }
+
+    #[test]
+    fn range_round_trip() {
+        let pattern = MatchRange('0', '9').repeat_forever(0).append("!").prepare_to_match().to_pattern();
+
+        assert!(matches("123!", pattern.clone()) == Some(4));
+        assert!(matches("!", pattern.clone()) == Some(1));
+        assert!(matches("12a!", pattern.clone()).is_none());
+    }
+
+    #[test]
+    fn start_anchor_round_trip() {
+        let pattern = start_anchor().append(exactly("ab")).prepare_to_match().to_pattern();
+
+        assert!(matches("ab", pattern.clone()) == Some(2));
+        assert!(pattern.prepare_to_match().match_at_slice(&['x', 'a', 'b'], 1).is_none());
+
+        let pattern = start_anchor().append(exactly("ab")).or(exactly("c")).prepare_to_match().to_pattern();
+
+        assert!(matches("ab", pattern.clone()) == Some(2));
+        assert!(matches("c", pattern.clone()) == Some(1));
+        assert!(pattern.clone().prepare_to_match().match_at_slice(&['x', 'a', 'b'], 1).is_none());
+        assert!(pattern.prepare_to_match().match_at_slice(&['x', 'c'], 1) == Some(1));
+    }
+
+    #[test]
+    fn end_anchor_round_trip() {
+        let pattern = exactly("ab").append(end_anchor()).prepare_to_match().to_pattern();
+
+        assert!(matches("ab", pattern.clone()) == Some(2));
+        assert!(matches("abc", pattern.clone()).is_none());
+
+        let pattern = exactly("ab").append(end_anchor()).or(exactly("a")).prepare_to_match().to_pattern();
+
+        assert!(matches("ab", pattern.clone()) == Some(2));
+        assert!(matches("abc", pattern.clone()) == Some(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn cannot_convert_dfa_that_rejects_more_at_end_of_input() {
+        exactly("ab").append(end_anchor()).prepare_to_match().complement(()).to_pattern();
+    }
+
+    #[test]
+    #[should_panic]
+    fn cannot_convert_dfa_that_rejects_more_at_start_of_input() {
+        start_anchor().append(exactly("ab")).prepare_to_match().complement(()).to_pattern();
+    }
+}
@@ -0,0 +1,271 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! State machines can be rendered in the Graphviz DOT format, which is useful for seeing what a pattern has been compiled
+//! into while debugging.
+//!
+//! ```
+//! # use concordance::*;
+//! let mut ndfa: Ndfa<SymbolRange<char>, &str> = Ndfa::new();
+//! ndfa.add_transition(0, SymbolRange::new('a', 'z'), 1);
+//! ndfa.set_output_symbol(1, "Word");
+//!
+//! let dot = ndfa.to_dot();
+//! # assert!(dot.contains("0 -> 1 [label=\"'a'-'z'\"];"));
+//! ```
+//!
+//! Compiled DFAs can be rendered in the same way, which shows the state machine that's actually run when matching:
+//!
+//! ```
+//! # use concordance::*;
+//! let dfa = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+//! let dot = dfa.to_dot();
+//! # assert!(dot.contains("[label=\"'a'-'z'\"]"));
+//! ```
+//!
+//! States are labelled with their IDs, and accepting states are drawn as double circles along with their output symbol.
+//! Symbols are written using their `Debug` representation, so characters that aren't printable are escaped.
+//!
+//! DFAs compiled from anchored patterns have some extra parts: a second arrow labelled `^` marks the state that's used
+//! when matching from the start of the input, and states that accept or reject differently when the input ends have a
+//! dotted `$` edge to what they do there.
+//!
+
+use std::fmt::Debug;
+
+use super::countable::*;
+use super::state_machine::*;
+use super::symbol_range::*;
+use super::ndfa::*;
+use super::symbol_range_dfa::*;
+
+///
+/// Escapes a string so it can be used as a quoted string in a DOT file
+///
+fn escape(text: &str) -> String {
+    let mut result = String::new();
+
+    for chr in text.chars() {
+        match chr {
+            '"'     => result.push_str("\\\""),
+            '\\'    => result.push_str("\\\\"),
+            '\n'    => result.push_str("\\n"),
+            chr     => result.push(chr)
+        }
+    }
+
+    result
+}
+
+///
+/// Returns the label for a transition on a range of symbols
+///
+fn range_label<Symbol: Ord+Debug>(range: &SymbolRange<Symbol>) -> String {
+    if range.lowest == range.highest {
+        escape(&format!("{:?}", range.lowest))
+    } else {
+        escape(&format!("{:?}-{:?}", range.lowest, range.highest))
+    }
+}
+
+///
+/// Writes the declaration for a state
+///
+fn write_state<OutputSymbol: Debug>(dot: &mut String, state: StateId, output: Option<&OutputSymbol>) {
+    match output {
+        Some(output)    => dot.push_str(&format!("    {} [shape=doublecircle, label=\"{}\\n{}\"];\n", state, state, escape(&format!("{:?}", output)))),
+        None            => dot.push_str(&format!("    {} [shape=circle];\n", state))
+    }
+}
+
+///
+/// Writes the start of a DOT graph, including the arrow that marks the start state
+///
+fn write_header(dot: &mut String, name: &str, start_state: StateId) {
+    dot.push_str(&format!("digraph {} {{\n", name));
+    dot.push_str("    rankdir=LR;\n");
+    dot.push_str("    start [shape=point];\n");
+    dot.push_str(&format!("    start -> {};\n", start_state));
+}
+
+impl<Symbol: Ord+Clone+Debug, OutputSymbol: Debug> Ndfa<SymbolRange<Symbol>, OutputSymbol> {
+    ///
+    /// Renders this NDFA in Graphviz DOT format
+    ///
+    /// Epsilon transitions (joined states) are drawn as dashed lines.
+    ///
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        write_header(&mut dot, "ndfa", 0);
+
+        for state in 0..self.count_states() {
+            write_state(&mut dot, state, self.own_output_symbol(state));
+        }
+
+        for state in 0..self.count_states() {
+            for (range, target) in self.own_transitions(state) {
+                dot.push_str(&format!("    {} -> {} [label=\"{}\"];\n", state, target, range_label(range)));
+            }
+
+            for target in self.epsilon_targets(state) {
+                dot.push_str(&format!("    {} -> {} [label=\"ε\", style=dashed];\n", state, target));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<Symbol: Ord+Clone+Countable+Debug, OutputSymbol: Debug> SymbolRangeDfa<Symbol, OutputSymbol> {
+    ///
+    /// Renders this DFA in Graphviz DOT format
+    ///
+    /// All of the transitions between a pair of states are drawn as a single edge, with neighbouring ranges joined together.
+    ///
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        write_header(&mut dot, "dfa", self.initial_state(false));
+
+        if self.has_start_of_input_state() {
+            dot.push_str("    start_of_input [shape=point];\n");
+            dot.push_str(&format!("    start_of_input -> {} [label=\"^\"];\n", self.initial_state(true)));
+        }
+
+        for state in 0..self.count_states() {
+            write_state(&mut dot, state, self.output_symbol_for_state(state));
+        }
+
+        // States that do something different when the input ends get an extra edge showing what that is
+        for state in 0..self.count_states() {
+            let output          = self.output_symbol_for_state(state).map(|output| format!("{:?}", output));
+            let output_at_end   = self.output_at_end(state).map(|output| format!("{:?}", output));
+
+            if output != output_at_end {
+                match output_at_end {
+                    Some(output_at_end) => dot.push_str(&format!("    end_{} [shape=doublecircle, label=\"$\\n{}\"];\n", state, escape(&output_at_end))),
+                    None                => dot.push_str(&format!("    end_{} [shape=box, label=\"reject\"];\n", state))
+                }
+
+                dot.push_str(&format!("    {} -> end_{} [label=\"$\", style=dotted];\n", state, state));
+            }
+        }
+
+        for state in 0..self.count_states() {
+            // Gather the ranges for each target state, in the order the targets are first seen
+            let mut edges: Vec<(StateId, Vec<SymbolRange<Symbol>>)> = vec![];
+
+            for (range, target) in self.get_transitions_for_state(state) {
+                match edges.iter_mut().find(|(edge_target, _)| *edge_target == target) {
+                    Some((_, ranges))   => ranges.push(range),
+                    None                => edges.push((target, vec![range]))
+                }
+            }
+
+            for (target, ranges) in edges {
+                let labels: Vec<_> = SymbolRange::join_all(ranges).iter().map(range_label).collect();
+
+                dot.push_str(&format!("    {} -> {} [label=\"{}\"];\n", state, target, labels.join(", ")));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn ndfa_dot_contains_states_and_transitions() {
+        let mut ndfa: Ndfa<SymbolRange<char>, &str> = Ndfa::new();
+
+        ndfa.add_transition(0, SymbolRange::new('a', 'a'), 1);
+        ndfa.add_transition(1, SymbolRange::new('\n', '\n'), 2);
+        ndfa.add_transition(1, SymbolRange::new('0', '9'), 2);
+        ndfa.add_epsilon(0, 1);
+        ndfa.set_output_symbol(2, "Line");
+
+        let dot = ndfa.to_dot();
+
+        assert!(dot.starts_with("digraph ndfa {\n"));
+        assert!(dot.contains("start -> 0;"));
+        assert!(dot.contains("0 [shape=circle];"));
+        assert!(dot.contains("2 [shape=doublecircle, label=\"2\\n\\\"Line\\\"\"];"));
+        assert!(dot.contains("0 -> 1 [label=\"'a'\"];"));
+        assert!(dot.contains("1 -> 2 [label=\"'\\\\n'\"];"));
+        assert!(dot.contains("1 -> 2 [label=\"'0'-'9'\"];"));
+        assert!(dot.contains("0 -> 1 [label=\"ε\", style=dashed];"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn dfa_dot_joins_ranges_on_each_edge() {
+        let mut builder = SymbolRangeDfaBuilder::new();
+
+        builder.start_state();
+        builder.transition(SymbolRange::new('a', 'c'), 1);
+        builder.transition(SymbolRange::new('d', 'f'), 1);
+        builder.transition(SymbolRange::new('x', 'x'), 1);
+
+        builder.start_state();
+        builder.accept(42);
+
+        let dot = builder.build().to_dot();
+
+        assert!(dot.starts_with("digraph dfa {\n"));
+        assert!(dot.contains("start -> 0;"));
+        assert!(dot.contains("0 [shape=circle];"));
+        assert!(dot.contains("1 [shape=doublecircle, label=\"1\\n42\"];"));
+        assert!(dot.contains("0 -> 1 [label=\"'a'-'f', 'x'\"];"));
+        assert!(!dot.contains("start_of_input"));
+        assert!(!dot.contains("end_"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn dfa_dot_shows_anchored_states() {
+        let mut builder = SymbolRangeDfaBuilder::new();
+
+        builder.start_state();
+        builder.transition(SymbolRange::new('a', 'a'), 2);
+
+        builder.start_state();
+        builder.transition(SymbolRange::new('a', 'a'), 2);
+        builder.accept_at_end(1);
+
+        builder.start_state();
+        builder.accept(2);
+        builder.reject_at_end();
+
+        builder.set_start_of_input(1);
+
+        let dot = builder.build().to_dot();
+
+        assert!(dot.contains("start -> 0;"));
+        assert!(dot.contains("start_of_input -> 1 [label=\"^\"];"));
+        assert!(dot.contains("end_1 [shape=doublecircle, label=\"$\\n1\"];"));
+        assert!(dot.contains("1 -> end_1 [label=\"$\", style=dotted];"));
+        assert!(dot.contains("end_2 [shape=box, label=\"reject\"];"));
+        assert!(dot.contains("2 -> end_2 [label=\"$\", style=dotted];"));
+        assert!(!dot.contains("end_0"));
+    }
+}
@@ -0,0 +1,167 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! Two DFAs are equivalent if they accept the same strings, even if their states are arranged differently. This is useful
+//! for checking that rewriting a pattern hasn't changed what it matches.
+//!
+//! ```
+//! # use concordance::*;
+//! let original    = Pattern::from_regex("(a|b)+").unwrap().prepare_to_match();
+//! let rewritten   = MatchRange('a', 'b').repeat_forever(1).prepare_to_match();
+//!
+//! assert!(original.is_equivalent(&rewritten));
+//! ```
+//!
+//! The check walks through the product of the two DFAs, visiting each pair of states that can be reached by the same
+//! string, and fails if it finds a pair where one state accepts and the other doesn't.
+//!
+
+use std::collections::HashSet;
+
+use super::countable::*;
+use super::state_machine::*;
+use super::symbol_range_dfa::*;
+
+impl<InputSymbol: Ord+Clone+Countable, OutputSymbol> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Returns true if this DFA accepts exactly the same strings as another DFA
+    ///
+    /// Output symbols are ignored, so DFAs that produce different outputs for the same string are still equivalent. Use
+    /// `is_equivalent_with_outputs()` to compare the outputs as well.
+    ///
+    pub fn is_equivalent<OtherOutputSymbol>(&self, other: &SymbolRangeDfa<InputSymbol, OtherOutputSymbol>) -> bool {
+        self.is_equivalent_by(other, |our_output, their_output| our_output.is_some() == their_output.is_some())
+    }
+
+    ///
+    /// Returns true if this DFA accepts exactly the same strings as another DFA, and produces the same output for each of them
+    ///
+    pub fn is_equivalent_with_outputs(&self, other: &SymbolRangeDfa<InputSymbol, OutputSymbol>) -> bool
+    where OutputSymbol: PartialEq {
+        self.is_equivalent_by(other, |our_output, their_output| our_output == their_output)
+    }
+
+    ///
+    /// Returns true if every pair of states reachable by the same string in both DFAs have outputs that match using the
+    /// specified function
+    ///
+    fn is_equivalent_by<OtherOutputSymbol, MatchFn>(&self, other: &SymbolRangeDfa<InputSymbol, OtherOutputSymbol>, outputs_match: MatchFn) -> bool
+    where MatchFn: Fn(Option<&OutputSymbol>, Option<&OtherOutputSymbol>) -> bool {
+        // States are None once a DFA has no transition for the input (so it can't accept anything any more)
+        let mut visited     = HashSet::new();
+        let mut to_process  = vec![];
+
+        // Both DFAs must match the same strings from the start of the input as well as from anywhere else
+        for at_start_of_input in [false, true].iter() {
+            let initial_states = (Some(self.initial_state(*at_start_of_input)), Some(other.initial_state(*at_start_of_input)));
+
+            if visited.insert(initial_states) {
+                to_process.push(initial_states);
+            }
+        }
+
+        while let Some((our_state, their_state)) = to_process.pop() {
+            let our_output      = our_state.and_then(|state| self.output_symbol_for_state(state));
+            let their_output    = their_state.and_then(|state| other.output_symbol_for_state(state));
+
+            if !outputs_match(our_output, their_output) {
+                return false;
+            }
+
+            // The outputs must also match if the input ends here
+            let our_output      = our_state.and_then(|state| self.output_at_end(state));
+            let their_output    = their_state.and_then(|state| other.output_at_end(state));
+
+            if !outputs_match(our_output, their_output) {
+                return false;
+            }
+
+            for (_, next_state) in self.paired_transitions(other, (our_state, their_state)) {
+                if !visited.contains(&next_state) {
+                    visited.insert(next_state);
+                    to_process.push(next_state);
+                }
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn alternatives_in_either_order_are_equivalent() {
+        let a_or_b = Pattern::from_regex("(a|b)").unwrap().prepare_to_match();
+        let b_or_a = Pattern::from_regex("(b|a)").unwrap().prepare_to_match();
+
+        assert!(a_or_b.is_equivalent(&b_or_a));
+        assert!(a_or_b.is_equivalent_with_outputs(&b_or_a));
+    }
+
+    #[test]
+    fn different_languages_are_not_equivalent() {
+        let one_or_more     = exactly("a").repeat_forever(1).prepare_to_match();
+        let zero_or_more    = exactly("a").repeat_forever(0).prepare_to_match();
+        let at_most_three   = exactly("a").repeat(1..4).prepare_to_match();
+
+        assert!(!one_or_more.is_equivalent(&zero_or_more));
+        assert!(!one_or_more.is_equivalent(&at_most_three));
+        assert!(one_or_more.is_equivalent(&one_or_more.clone().minimize()));
+    }
+
+    #[test]
+    fn start_anchors_are_compared() {
+        let anchored        = start_anchor().append(exactly("ab")).prepare_to_match();
+        let never_matches   = MatchAny(vec![]).prepare_to_match();
+        let unanchored      = exactly("ab").prepare_to_match();
+
+        assert!(!anchored.is_equivalent(&never_matches));
+        assert!(!anchored.is_equivalent(&unanchored));
+        assert!(anchored.is_equivalent(&anchored.clone().minimize()));
+    }
+
+    #[test]
+    fn end_anchors_are_compared() {
+        let anchored        = exactly("ab").append(end_anchor()).prepare_to_match();
+        let never_matches   = MatchAny(vec![]).prepare_to_match();
+        let unanchored      = exactly("ab").prepare_to_match();
+
+        assert!(!anchored.is_equivalent(&never_matches));
+        assert!(!anchored.is_equivalent(&unanchored));
+        assert!(anchored.is_equivalent(&anchored.clone().minimize()));
+    }
+
+    #[test]
+    fn outputs_are_only_compared_when_requested() {
+        let mut first = TokenMatcher::new();
+        first.add_pattern(exactly("if"), 1);
+        first.add_pattern(exactly("in"), 2);
+
+        let mut second = TokenMatcher::new();
+        second.add_pattern(exactly("if"), 2);
+        second.add_pattern(exactly("in"), 1);
+
+        let first   = first.prepare_to_match();
+        let second  = second.prepare_to_match();
+
+        assert!(first.is_equivalent(&second));
+        assert!(!first.is_equivalent_with_outputs(&second));
+    }
+}
@@ -0,0 +1,244 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! The intersection of two DFAs accepts only the strings that are accepted by both of them. It's generated by building the
+//! product automaton: each of its states represents a pair of states, one from each DFA, that are reached by the same
+//! input.
+//!
+//! ```
+//! # use concordance::*;
+//! let identifier  = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+//! let short       = MatchRange('a', 'c').repeat(1..3).prepare_to_match();
+//! let both        = identifier.intersect(&short);
+//!
+//! matches_prepared("ab", &both);      // == Some(2)
+//! matches_prepared("abz", &both);     // == Some(2)
+//! # assert!(matches_prepared("ab", &both) == Some(2));
+//! # assert!(matches_prepared("abz", &both) == Some(2));
+//! ```
+//!
+//! Patterns can be intersected too, by calling `and()`. This compiles both patterns, intersects the DFAs and converts the
+//! result back into a pattern.
+//!
+
+use std::collections::HashMap;
+
+use super::countable::*;
+use super::dfa_builder::*;
+use super::prepare::*;
+use super::regular_pattern::*;
+use super::state_machine::*;
+use super::symbol_range::*;
+use super::symbol_range_dfa::*;
+
+impl<InputSymbol: Ord+Clone+Countable, OutputSymbol: Clone> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Creates a DFA that accepts the strings that are accepted by both this DFA and another one
+    ///
+    /// A state of the new DFA is accepting if the states it represents are accepting in both of the original DFAs. Its
+    /// output symbol is a tuple of the two output symbols, so the outputs of the original DFAs can still be told apart.
+    ///
+    pub fn intersect<OtherOutputSymbol: Clone>(&self, other: &SymbolRangeDfa<InputSymbol, OtherOutputSymbol>) -> SymbolRangeDfa<InputSymbol, (OutputSymbol, OtherOutputSymbol)> {
+        let mut builder                                         = SymbolRangeDfaBuilder::new();
+        let mut state_ids: HashMap<(StateId, StateId), StateId> = HashMap::new();
+        let mut to_process                                      = vec![(0, 0)];
+        let mut next_index                                      = 0;
+
+        state_ids.insert((0, 0), 0);
+
+        // Matches at the start of the input begin in the pair of start of input states
+        let start_of_input = (self.initial_state(true), other.initial_state(true));
+
+        if start_of_input != (0, 0) {
+            state_ids.insert(start_of_input, 1);
+            to_process.push(start_of_input);
+            builder.set_start_of_input(1);
+        }
+
+        // States are generated in the order they're first found, which is also the order their IDs are assigned in
+        while next_index < to_process.len() {
+            let (our_state, their_state) = to_process[next_index];
+            next_index += 1;
+
+            builder.start_state();
+
+            // Both sets of transitions are sorted, so the overlapping ranges can be found by walking through them together
+            let our_transitions     = self.get_transitions_for_state(our_state);
+            let their_transitions   = other.get_transitions_for_state(their_state);
+            let mut our_index       = 0;
+            let mut their_index     = 0;
+
+            while our_index < our_transitions.len() && their_index < their_transitions.len() {
+                let (ref our_range, our_target)     = our_transitions[our_index];
+                let (ref their_range, their_target) = their_transitions[their_index];
+
+                if our_range.overlaps(their_range) {
+                    let lowest  = if our_range.lowest > their_range.lowest { &our_range.lowest } else { &their_range.lowest };
+                    let highest = if our_range.highest < their_range.highest { &our_range.highest } else { &their_range.highest };
+
+                    let target  = (our_target, their_target);
+                    let next_id = state_ids.len() as StateId;
+                    let target  = *state_ids.entry(target).or_insert_with(|| {
+                        to_process.push(target);
+                        next_id
+                    });
+
+                    builder.transition(SymbolRange::new(lowest.clone(), highest.clone()), target);
+                }
+
+                // Move past whichever range finishes first
+                if our_range.highest < their_range.highest {
+                    our_index += 1;
+                } else {
+                    their_index += 1;
+                }
+            }
+
+            // Accept if both states are accepting
+            if let (Some(our_output), Some(their_output)) = (self.output_symbol_for_state(our_state), other.output_symbol_for_state(their_state)) {
+                builder.accept((our_output.clone(), their_output.clone()));
+            }
+
+            // ... and in the same way when the input has run out
+            if self.has_end_of_input_outputs() || other.has_end_of_input_outputs() {
+                match (self.output_at_end(our_state), other.output_at_end(their_state)) {
+                    (Some(our_output), Some(their_output))  => builder.accept_at_end((our_output.clone(), their_output.clone())),
+                    _                                       => builder.reject_at_end()
+                }
+            }
+        }
+
+        builder.build()
+    }
+}
+
+impl<Symbol: Clone+Ord+Countable+'static> Pattern<Symbol> {
+    ///
+    /// Creates a pattern that matches the strings that are matched by both this pattern and another one
+    ///
+    /// Patterns don't have output symbols, so the result doesn't either: when it's added to a `TokenMatcher`, it produces
+    /// the output symbol it's added with, like any other pattern. Use `SymbolRangeDfa::intersect()` on compiled DFAs to
+    /// keep the outputs of both sides.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let lowercase_abc = MatchRange('a', 'z').repeat_forever(1).and(exactly("abc"));
+    ///
+    /// matches("abc", lowercase_abc.clone());  // == Some(3)
+    /// matches("abd", lowercase_abc.clone());  // == None
+    /// # assert!(matches("abc", lowercase_abc.clone()) == Some(3));
+    /// # assert!(matches("abd", lowercase_abc.clone()).is_none());
+    /// ```
+    ///
+    pub fn and<TPattern: IntoPattern<Symbol>>(self, pattern: TPattern) -> Pattern<Symbol> {
+        let ours    = self.prepare_to_match();
+        let theirs  = pattern.into_pattern().prepare_to_match();
+
+        ours.intersect(&theirs).to_pattern()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::char;
+
+    use super::super::*;
+
+    #[test]
+    fn intersect_accepts_strings_matching_both() {
+        let letters     = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+        let three       = MatchRange('\u{0000}', char::MAX).repeat(3..4).prepare_to_match();
+        let both        = letters.intersect(&three);
+
+        assert!(both.match_at_slice(&['a', 'b', 'c'], 0) == Some(3));
+        assert!(both.match_at_slice(&['a', 'b'], 0).is_none());
+        assert!(both.match_at_slice(&['a', '1', 'c'], 0).is_none());
+
+        // 'abcd' is accepted by both, but only its first three characters are accepted by the intersection
+        assert!(both.match_at_slice(&['a', 'b', 'c', 'd'], 0) == Some(3));
+        assert!(matches_prepared("abcd", &both) == Some(3));
+    }
+
+    #[test]
+    fn intersect_combines_output_symbols() {
+        let mut keywords = TokenMatcher::new();
+        keywords.add_pattern(exactly("if"), "If");
+        keywords.add_pattern(exactly("in"), "In");
+
+        let identifier  = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+        let keywords    = keywords.prepare_to_match();
+        let both        = keywords.intersect(&identifier);
+
+        let mut tokenizer = Tokenizer::new_prepared("if".read_symbols(), &both);
+
+        assert!(tokenizer.next_token() == Some((0..2, ("If", ()))));
+        assert!(matches_prepared("in", &both) == Some(2));
+        assert!(matches_prepared("ix", &both).is_none());
+    }
+
+    #[test]
+    fn intersect_of_disjoint_languages_accepts_nothing() {
+        let letters = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+        let digits  = MatchRange('0', '9').repeat_forever(1).prepare_to_match();
+        let both    = letters.intersect(&digits);
+
+        assert!(matches_prepared("abc", &both).is_none());
+        assert!(matches_prepared("123", &both).is_none());
+    }
+
+    #[test]
+    fn intersect_keeps_start_anchors() {
+        let anchored    = start_anchor().append(exactly("ab")).prepare_to_match();
+        let letters     = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+        let both        = anchored.intersect(&letters);
+
+        assert!(both.match_at_slice(&['a', 'b'], 0) == Some(2));
+        assert!(both.match_at_slice(&['x', 'a', 'b'], 1).is_none());
+
+        let pattern = start_anchor().append(exactly("ab")).and(MatchRange('a', 'z').repeat_forever(1));
+
+        assert!(matches("ab", pattern.clone()) == Some(2));
+        assert!(matches("abc", pattern.clone()) == Some(2));
+        assert!(matches("b", pattern.clone()).is_none());
+    }
+
+    #[test]
+    fn intersect_keeps_end_anchors() {
+        let anchored    = exactly("ab").append(end_anchor()).prepare_to_match();
+        let letters     = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+        let both        = anchored.intersect(&letters);
+
+        assert!(both.match_at_slice(&['a', 'b'], 0) == Some(2));
+        assert!(both.match_at_slice(&['a', 'b', 'c'], 0).is_none());
+
+        let pattern = exactly("ab").append(end_anchor()).and(MatchRange('a', 'z').repeat_forever(1));
+
+        assert!(matches("ab", pattern.clone()) == Some(2));
+        assert!(matches("abc", pattern.clone()).is_none());
+    }
+
+    #[test]
+    fn patterns_can_be_intersected() {
+        let pattern = MatchRange('a', 'z').repeat_forever(1).and(exactly("abc"));
+
+        assert!(matches("abc", pattern.clone()) == Some(3));
+        assert!(matches("abcd", pattern.clone()) == Some(3));
+        assert!(matches("ab", pattern.clone()).is_none());
+        assert!(matches("xyz", pattern.clone()).is_none());
+        assert!(matches("ABC", exactly("ABC").and(MatchRange('a', 'z').repeat_forever(1))).is_none());
+    }
+}
@@ -18,8 +18,12 @@
 //! A library for working with deterministic and non-deterministic finite-state automata.
 //!
 
-#[macro_use] extern crate serde;
+// The tests were written before clippy, and use some constructs that it warns about
+#![cfg_attr(test, allow(clippy::assertions_on_constants, clippy::partialeq_to_none, clippy::useless_vec))]
+
+extern crate serde;
 #[macro_use] extern crate serde_derive;
+#[cfg(test)] extern crate serde_json;
 
 pub use self::countable::*;
 pub use self::symbol_range::*;
@@ -38,6 +42,16 @@ pub use self::tape::*;
 pub use self::split_reader::*;
 pub use self::tokenizer::*;
 pub use self::tagged_stream::*;
+pub use self::same_symbol_run::*;
+pub use self::tokenizer_profiler::*;
+pub use self::annotated_stream::*;
+pub use self::negated_ranges::*;
+pub use self::pattern_complement::*;
+pub use self::dfa::*;
+pub use self::replace::*;
+pub use self::multi_literal::*;
+pub use self::streaming_tokenizer::*;
+pub use self::captures::*;
 
 pub mod countable;
 pub mod symbol_range;
@@ -57,3 +71,19 @@ pub mod tape;
 pub mod split_reader;
 pub mod tokenizer;
 pub mod tagged_stream;
+pub mod same_symbol_run;
+pub mod tokenizer_profiler;
+pub mod annotated_stream;
+pub mod negated_ranges;
+pub mod dfa_to_pattern;
+pub mod pattern_complement;
+pub mod dfa;
+pub mod replace;
+pub mod numbers;
+pub mod dot;
+pub mod multi_literal;
+pub mod minimize;
+pub mod intersect;
+pub mod equivalence;
+pub mod streaming_tokenizer;
+pub mod captures;
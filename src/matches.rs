@@ -40,10 +40,31 @@
 //! ```
 //!
 
+use std::fmt::Display;
+use std::iter;
+use std::ops::Range;
+
+use super::countable::*;
 use super::symbol_range_dfa::*;
 use super::symbol_reader::*;
 use super::pattern_matcher::*;
 use super::prepare::*;
+use super::tokenizer::*;
+
+///
+/// The result of matching a pattern against the whole of a source stream, as returned by `match_outcome()`
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchOutcome {
+    /// The pattern matched this many symbols from the start of the input
+    Accepted(usize),
+
+    /// The pattern can't match the input, even if more symbols are added to the end of it
+    Rejected,
+
+    /// The input ran out part way through a match: the pattern might match if more symbols are added to the end of it
+    Incomplete
+}
 
 ///
 /// Runs a DFA against a symbol stream and returns its final state
@@ -64,7 +85,7 @@ use super::prepare::*;
 /// # assert!(match match_result { Accept(count, val) => count == 6 && val == &(), _ => false });
 /// ```
 ///
-pub fn match_pattern<'a, InputSymbol: Ord, OutputSymbol, State>(start_state: MatchAction<'a, OutputSymbol, State>, symbol_reader: &mut SymbolReader<InputSymbol>) -> MatchAction<'a, OutputSymbol, State>
+pub fn match_pattern<'a, InputSymbol: Ord, OutputSymbol, State>(start_state: MatchAction<'a, OutputSymbol, State>, symbol_reader: &mut dyn SymbolReader<InputSymbol>) -> MatchAction<'a, OutputSymbol, State>
 where State: MatchingState<'a, InputSymbol, OutputSymbol> {
     let mut current_state = start_state;
 
@@ -85,7 +106,7 @@ where State: MatchingState<'a, InputSymbol, OutputSymbol> {
 ///
 /// Runs a pattern matcher against a stream, and returns the number of characters matching if it accepted the stream
 ///
-fn matches_symbol_range<InputSymbol: Ord, OutputSymbol: 'static>(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, symbol_reader: &mut SymbolReader<InputSymbol>) -> Option<usize> {
+fn matches_symbol_range<InputSymbol: Ord, OutputSymbol: 'static>(dfa: &SymbolRangeDfa<InputSymbol, OutputSymbol>, symbol_reader: &mut dyn SymbolReader<InputSymbol>) -> Option<usize> {
     // Run the DFA
     let final_state = match_pattern(dfa.start(), symbol_reader);
 
@@ -100,8 +121,9 @@ fn matches_symbol_range<InputSymbol: Ord, OutputSymbol: 'static>(dfa: &SymbolRan
 /// Matches a source stream against a pattern
 ///
 /// This is the basic pattern matcher. It matches against the left-hand side of the source, and if there is a string of any
-/// length that can match the passed in pattern it will return the length of that string. Pattern matchers should be greedy,
-/// so this will return the length of the longest string that can match the given pattern.
+/// length that can match the passed in pattern it will return the length of that string. Matching is always greedy: the
+/// result is the length of the longest prefix of the source that matches the pattern, so parsing can continue from the
+/// symbol after it.
 ///
 /// ```
 /// # use concordance::*;
@@ -131,6 +153,28 @@ where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
     matches_symbol_range(&matcher, &mut reader)
 }
 
+///
+/// Matches a source stream against a pattern, returning the length of the longest prefix of the source that matches
+///
+/// This is the same as `matches()`, which always finds the longest match, but makes that explicit where it matters: for
+/// instance, when the result is used as the position to continue parsing from.
+///
+/// ```
+/// # use concordance::*;
+/// matches_longest("aaab", exactly("a").repeat_forever(1));  // == Some(3)
+/// # assert!(matches_longest("aaab", exactly("a").repeat_forever(1)) == Some(3));
+/// ```
+///
+#[inline]
+pub fn matches_longest<'a, Symbol, OutputSymbol, Prepare, Reader, Source>(source: Source, pattern: Prepare) -> Option<usize>
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Reader: SymbolReader<Symbol>+'a
+,       Source: SymbolSource<'a, Symbol, SymbolReader=Reader>
+,       Symbol: Ord
+,       OutputSymbol: 'static {
+    matches(source, pattern)
+}
+
 ///
 /// Matches a source stream against a prepared pattern
 ///
@@ -153,13 +197,324 @@ where   Reader: SymbolReader<Symbol>+'a
 ,       OutputSymbol: 'static {
     let mut reader = source.read_symbols();
 
-    matches_symbol_range(&matcher, &mut reader)
+    matches_symbol_range(matcher, &mut reader)
+}
+
+///
+/// Matches a source stream against a prepared pattern, returning the length of the match along with a reference to the
+/// output symbol of the accepting state
+///
+/// The output symbol is borrowed from the DFA, so this doesn't need to clone it.
+///
+/// ```
+/// # use concordance::*;
+/// let mut matcher = TokenMatcher::new();
+/// matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), "Number");
+/// let prepared = matcher.prepare_to_match();
+///
+/// matches_ref(&prepared, "123+4"); // == Some((3, &"Number"))
+/// # assert!(matches_ref(&prepared, "123+4") == Some((3, &"Number")));
+/// ```
+///
+pub fn matches_ref<'a, 'b, Symbol, OutputSymbol, Reader, Source>(matcher: &'a SymbolRangeDfa<Symbol, OutputSymbol>, source: Source) -> Option<(usize, &'a OutputSymbol)>
+where   Reader: SymbolReader<Symbol>+'b
+,       Source: SymbolSource<'b, Symbol, SymbolReader=Reader>
+,       Symbol: Ord
+,       OutputSymbol: 'static {
+    let mut reader = source.read_symbols();
+
+    if let Accept(count, output) = match_pattern(matcher.start(), &mut reader) {
+        Some((count, output))
+    } else {
+        None
+    }
+}
+
+///
+/// Matches a source stream against a pattern, returning the length of the match along with the output symbol of the
+/// accepting state
+///
+/// This is useful for classifying the start of a stream with a `TokenMatcher`, without needing to set up a `Tokenizer`.
+///
+/// ```
+/// # use concordance::*;
+/// let mut matcher = TokenMatcher::new();
+/// matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), "Number");
+/// matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), "Word");
+///
+/// matches_with_output("abc123", &matcher); // == Some((3, "Word"))
+/// # assert!(matches_with_output("abc123", &matcher) == Some((3, "Word")));
+/// ```
+///
+pub fn matches_with_output<'a, Symbol, OutputSymbol, Prepare, Reader, Source>(source: Source, pattern: Prepare) -> Option<(usize, OutputSymbol)>
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Reader: SymbolReader<Symbol>+'a
+,       Source: SymbolSource<'a, Symbol, SymbolReader=Reader>
+,       Symbol: Ord
+,       OutputSymbol: Clone+'static {
+    let matcher = pattern.prepare_to_match();
+
+    matches_ref(&matcher, source).map(|(count, output)| (count, output.clone()))
+}
+
+///
+/// Matches a source stream against a pattern, reporting whether or not more input could change the result
+///
+/// The result is `Incomplete` if the input runs out in a state that doesn't accept, but which could still reach an
+/// accepting state if there were more symbols. This is useful for streaming parsers, which can wait for more input
+/// rather than treating this as a failure. Otherwise, the result is the same as for `matches()`.
+///
+/// ```
+/// # use concordance::*;
+/// match_outcome("abc", "abc");    // == MatchOutcome::Accepted(3)
+/// match_outcome("ab", "abc");     // == MatchOutcome::Incomplete
+/// match_outcome("ax", "abc");     // == MatchOutcome::Rejected
+/// # assert!(match_outcome("abc", "abc") == MatchOutcome::Accepted(3));
+/// # assert!(match_outcome("ab", "abc") == MatchOutcome::Incomplete);
+/// # assert!(match_outcome("ax", "abc") == MatchOutcome::Rejected);
+/// ```
+///
+pub fn match_outcome<'a, Symbol, OutputSymbol, Prepare, Reader, Source>(source: Source, pattern: Prepare) -> MatchOutcome
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Reader: SymbolReader<Symbol>+'a
+,       Source: SymbolSource<'a, Symbol, SymbolReader=Reader>
+,       Symbol: Ord
+,       OutputSymbol: 'static {
+    let matcher     = pattern.prepare_to_match();
+    let mut reader  = source.read_symbols();
+    let mut action  = matcher.start();
+    let mut length  = 0;
+
+    // Run the DFA until it stops or the input runs out
+    while let More(state) = action {
+        if let Some(symbol) = reader.next_symbol() {
+            action  = state.next(symbol);
+            length  += 1;
+        } else {
+            // The match is only complete if it accepts the whole of the input, or if more input can't make it any longer
+            let can_continue = state.can_accept_more_input();
+
+            match state.finish() {
+                Accept(count, _) if count == length => return MatchOutcome::Accepted(count),
+                _ if can_continue                   => return MatchOutcome::Incomplete,
+                finished                            => action = finished
+            }
+        }
+    }
+
+    match action {
+        Accept(count, _)    => MatchOutcome::Accepted(count),
+        _                   => MatchOutcome::Rejected
+    }
+}
+
+///
+/// Matches the formatted form of a value against a pattern
+///
+/// This formats the value using its `Display` implementation and then matches the resulting string in the same way as
+/// `matches`.
+///
+/// ```
+/// # use concordance::*;
+/// matches_display(&1234, MatchRange('0', '9').repeat_forever(1)); // == Some(4)
+/// # assert!(matches_display(&1234, MatchRange('0', '9').repeat_forever(1)) == Some(4));
+/// ```
+///
+pub fn matches_display<Value, OutputSymbol, Prepare>(value: &Value, pattern: Prepare) -> Option<usize>
+where   Value: Display+?Sized
+,       Prepare: PrepareToMatch<SymbolRangeDfa<char, OutputSymbol>>
+,       OutputSymbol: 'static {
+    let formatted = value.to_string();
+
+    matches(&*formatted, pattern)
+}
+
+///
+/// Matches a source stream against a pattern, returning the length of the match along with the number of symbols that
+/// were left over after the end of the match
+///
+/// If the pattern does not match, the whole of the input is left over. A full match is one where the number of remaining
+/// symbols is 0.
+///
+/// ```
+/// # use concordance::*;
+/// matches_with_remainder("abc", "ab");    // == (Some(2), 1)
+/// matches_with_remainder("ab", "ab");     // == (Some(2), 0)
+/// matches_with_remainder("xyz", "ab");    // == (None, 3)
+/// # assert!(matches_with_remainder("abc", "ab") == (Some(2), 1));
+/// # assert!(matches_with_remainder("ab", "ab") == (Some(2), 0));
+/// # assert!(matches_with_remainder("xyz", "ab") == (None, 3));
+/// ```
+///
+pub fn matches_with_remainder<'a, Symbol, OutputSymbol, Prepare, Reader, Source>(source: Source, pattern: Prepare) -> (Option<usize>, usize)
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Reader: SymbolReader<Symbol>+'a
+,       Source: SymbolSource<'a, Symbol, SymbolReader=Reader>
+,       Symbol: Ord+Clone
+,       OutputSymbol: 'static {
+    // The matcher can read past the end of the match, so we need to read the whole input to know how much is left over
+    let matcher = pattern.prepare_to_match();
+    let input   = source.read_symbols().to_vec();
+    let matched = matches_symbol_range(&matcher, &mut input.read_symbols());
+
+    let remaining = input.len() - matched.unwrap_or(0);
+
+    (matched, remaining)
+}
+
+///
+/// Finds the location of the nth (0-based) non-overlapping match of a pattern in a source stream
+///
+/// Matches are found in the same way as a `Tokenizer` finds tokens: each match is the longest one starting at the earliest
+/// position after the end of the previous match. Searching stops as soon as the nth match has been found.
+///
+/// ```
+/// # use concordance::*;
+/// nth_match("a1b2c3", MatchRange('0', '9').repeat_forever(1), 1); // == Some(3..4)
+/// # assert!(nth_match("a1b2c3", MatchRange('0', '9').repeat_forever(1), 1) == Some(3..4));
+/// ```
+///
+pub fn nth_match<'a, Symbol, OutputSymbol, Prepare, Reader, Source>(source: Source, pattern: Prepare, n: usize) -> Option<Range<usize>>
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Reader: SymbolReader<Symbol>+'a
+,       Source: SymbolSource<'a, Symbol, SymbolReader=Reader>
+,       Symbol: Clone+Ord+Countable
+,       OutputSymbol: Clone+Ord+'static {
+    let mut tokenizer = Tokenizer::new(source.read_symbols(), pattern);
+
+    tokenizer.nth(n).map(|(location, _)| location)
+}
+
+///
+/// Finds the first match of a pattern that starts anywhere in a source stream
+///
+/// Unlike `matches()`, the match doesn't have to start at the beginning of the input. The compiled DFA is reused at each
+/// position, and the input is only read as far as the end of the match that's found.
+///
+/// ```
+/// # use concordance::*;
+/// find("abcde", exactly("cd"));   // == Some(2..4)
+/// # assert!(find("abcde", exactly("cd")) == Some(2..4));
+/// ```
+///
+pub fn find<'a, Symbol, OutputSymbol, Prepare, Reader, Source>(source: Source, pattern: Prepare) -> Option<Range<usize>>
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Reader: SymbolReader<Symbol>+'a
+,       Source: SymbolSource<'a, Symbol, SymbolReader=Reader>
+,       Symbol: Clone+Ord+Countable
+,       OutputSymbol: Clone+Ord+'static {
+    let mut tokenizer = Tokenizer::new(source.read_symbols(), pattern);
+
+    tokenizer.next().map(|(location, _)| location)
+}
+
+///
+/// Finds every non-overlapping match of a pattern in a source stream
+///
+/// This searches the whole of the input rather than only matching at the start of it. Matches are found in the same way as
+/// by `nth_match()`: the search continues after the end of each match, and moves on by one symbol from any position where
+/// there's no match or only an empty one, so patterns that can match nothing can't stop the search from making progress.
+/// For input that's already in a slice, `find_all_in_slice()` finds the same matches more quickly.
+///
+/// ```
+/// # use concordance::*;
+/// let digits = find_all("a1b2c33", MatchRange('0', '9').repeat_forever(1)).collect::<Vec<_>>();
+///
+/// // digits == vec![1..2, 3..4, 5..7]
+/// # assert!(digits == vec![1..2, 3..4, 5..7]);
+/// ```
+///
+pub fn find_all<'a, Symbol, OutputSymbol, Prepare, Reader, Source>(source: Source, pattern: Prepare) -> impl Iterator<Item=Range<usize>>+'a
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Reader: SymbolReader<Symbol>+'a
+,       Source: SymbolSource<'a, Symbol, SymbolReader=Reader>
+,       Symbol: Clone+Ord+Countable+'a
+,       OutputSymbol: Clone+Ord+'static {
+    Tokenizer::new(source.read_symbols(), pattern).map(|(location, _)| location)
+}
+
+///
+/// Finds every non-overlapping match of a pattern in a slice
+///
+/// The matches are the same as those found by `find_all()`, but the DFA is run directly over the slice with
+/// `SymbolRangeDfa::match_at_slice()` at each position, so no reader needs to be created for the input. This is the faster
+/// way to search input that's already in memory. If every match must begin with the same symbols (see
+/// `SymbolRangeDfa::required_prefix()`), positions that don't begin with them are skipped without running the DFA.
+///
+/// ```
+/// # use concordance::*;
+/// let input   = "a1b2c33".chars().collect::<Vec<_>>();
+/// let digits  = find_all_in_slice(&input, MatchRange('0', '9').repeat_forever(1)).collect::<Vec<_>>();
+///
+/// // digits == vec![1..2, 3..4, 5..7]
+/// # assert!(digits == vec![1..2, 3..4, 5..7]);
+/// ```
+///
+pub fn find_all_in_slice<'a, Symbol, OutputSymbol, Prepare>(input: &'a [Symbol], pattern: Prepare) -> impl Iterator<Item=Range<usize>>+'a
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Symbol: Clone+Ord+Countable+'a
+,       OutputSymbol: Clone+Ord+'static {
+    let dfa             = pattern.prepare_to_match();
+    let prefix          = dfa.required_prefix();
+    let mut position    = 0;
+
+    iter::from_fn(move || {
+        while position < input.len() {
+            // The prefix is for matches away from the start of the input, which may be different if there are start anchors
+            if !prefix.is_empty() && (position > 0 || dfa.initial_state(true) == 0) {
+                match input[position..].windows(prefix.len()).position(|window| window == &prefix[..]) {
+                    Some(offset)    => position += offset,
+                    None            => { position = input.len(); break; }
+                }
+            }
+
+            // Empty matches are skipped in the same way as by the tokenizer, so the search always makes progress
+            match dfa.match_at_slice(input, position) {
+                Some(length) if length > 0 => {
+                    let start = position;
+                    position += length;
+
+                    return Some(start..position);
+                },
+
+                _ => position += 1
+            }
+        }
+
+        None
+    })
 }
 
 #[cfg(test)]
 mod test {
     use super::super::*;
 
+    #[test]
+    fn matches_longest_prefix() {
+        assert!(matches_longest("aaab", exactly("a").repeat_forever(1)) == Some(3));
+        assert!(matches_longest("abcccXYZ", exactly("ab").append(exactly("c").repeat_forever(1))) == Some(5));
+        assert!(matches_longest("ab", exactly("a").or("ab").or("abc")) == Some(2));
+        assert!(matches_longest("b", exactly("a").repeat_forever(1)).is_none());
+    }
+
+    #[test]
+    fn match_outcome_distinguishes_incomplete_input() {
+        assert!(match_outcome("ab", "abc") == MatchOutcome::Incomplete);
+        assert!(match_outcome("ax", "abc") == MatchOutcome::Rejected);
+        assert!(match_outcome("abcd", "abc") == MatchOutcome::Accepted(3));
+        assert!(match_outcome("", "abc") == MatchOutcome::Incomplete);
+    }
+
+    #[test]
+    fn match_outcome_prefers_longer_matches_that_are_incomplete() {
+        let pattern = exactly("a").or("abc");
+
+        assert!(match_outcome("ab", pattern.clone()) == MatchOutcome::Incomplete);
+        assert!(match_outcome("abx", pattern.clone()) == MatchOutcome::Accepted(1));
+        assert!(match_outcome("aa", MatchRange('a', 'z').repeat_forever(1)) == MatchOutcome::Accepted(2));
+        assert!(match_outcome("ab", exactly("ab").append(end_anchor())) == MatchOutcome::Accepted(2));
+    }
+
     #[test]
     fn match_multiple_repeats() {
         assert!(matches("abcabc", exactly("abc").repeat_forever(1)).is_some());
@@ -225,4 +580,169 @@ mod test {
     fn match_zero_repeats() {
         assert!(matches("", exactly("abc").repeat_forever(0)).is_some());
     }
+
+    #[test]
+    fn match_with_remainder() {
+        assert!(matches_with_remainder("abc", "ab") == (Some(2), 1));
+        assert!(matches_with_remainder("ab", "ab") == (Some(2), 0));
+        assert!(matches_with_remainder("abab", exactly("ab").repeat_forever(1)) == (Some(4), 0));
+        assert!(matches_with_remainder("def", "ab") == (None, 3));
+    }
+
+    #[test]
+    fn match_borrowing_output() {
+        // Output symbol that can't be cloned
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+        struct Output(Vec<u32>);
+
+        let mut builder = SymbolRangeDfaBuilder::new();
+        builder.start_state();
+        builder.transition(SymbolRange::new('a', 'z'), 1);
+        builder.start_state();
+        builder.transition(SymbolRange::new('a', 'z'), 1);
+        builder.accept(Output(vec![1, 2, 3]));
+        let dfa = builder.build();
+
+        let (length, output) = matches_ref(&dfa, "abc1").unwrap();
+
+        assert!(length == 3);
+        assert!(output.0 == vec![1, 2, 3]);
+        assert!(matches_ref(&dfa, "1abc").is_none());
+    }
+
+    #[test]
+    fn match_display() {
+        let digits = MatchRange('0', '9').repeat_forever(1);
+
+        assert!(matches_display(&42, digits.clone()) == Some(2));
+        assert!(matches_display(&-42, digits.clone()).is_none());
+        assert!(matches_display(&1.5, digits.clone()) == Some(1));
+    }
+
+    #[test]
+    fn match_iterator() {
+        let evens = (0u32..10).map(|num| num*2).into_symbol_reader();
+
+        assert!(matches(evens, MatchRange(0, 8).repeat_forever(1)) == Some(5));
+    }
+
+    #[test]
+    fn match_lowercase_reader() {
+        let prepared = exactly("abc").prepare_to_match();
+
+        assert!(match_pattern(prepared.start(), &mut LowercaseCharReader::new("ABC".read_symbols())).is_accepted(&()));
+        assert!(match_pattern(prepared.start(), &mut LowercaseCharReader::new("aBc".read_symbols())).is_accepted(&()));
+    }
+
+    #[test]
+    fn find_nth_match() {
+        let digits = MatchRange('0', '9').repeat_forever(1);
+
+        assert!(nth_match("a1b2c3", digits.clone(), 0) == Some(1..2));
+        assert!(nth_match("a1b2c3", digits.clone(), 1) == Some(3..4));
+        assert!(nth_match("a12b345", digits.clone(), 1) == Some(4..7));
+        assert!(nth_match("a1b2c3", digits.clone(), 3).is_none());
+    }
+
+    #[test]
+    fn find_all_digits() {
+        let found = find_all("a1b2c3", MatchRange('0', '9')).collect::<Vec<_>>();
+
+        assert!(found == vec![1..2, 3..4, 5..6]);
+    }
+
+    #[test]
+    fn find_all_in_slice_is_same_as_find_all() {
+        let input = "ab12 $foo 3 foo!".chars().collect::<Vec<_>>();
+
+        let patterns = vec![
+            MatchRange('0', '9').repeat_forever(1),
+            exactly("foo").or(MatchRange('a', 'z').repeat_forever(0)),
+            start_anchor().append(exactly("ab")),
+            exactly("foo").append(end_anchor()).or("!"),
+            exactly("$").repeat_forever(0)
+        ];
+
+        for pattern in patterns {
+            let from_slice  = find_all_in_slice(&input, pattern.clone()).collect::<Vec<_>>();
+            let from_reader = find_all(&input, pattern).collect::<Vec<_>>();
+
+            assert!(from_slice == from_reader);
+        }
+    }
+
+    #[test]
+    fn find_all_in_slice_skips_to_required_prefix() {
+        let input = "x foo1 fo2 foofoo34 foo".chars().collect::<Vec<_>>();
+
+        let patterns = vec![
+            exactly("foo").append(MatchRange('0', '9').repeat_forever(1)),
+            start_anchor().append(exactly("x")).or("foo"),
+            exactly("foo").append(end_anchor())
+        ];
+
+        for pattern in patterns {
+            let from_slice  = find_all_in_slice(&input, pattern.clone()).collect::<Vec<_>>();
+            let from_reader = find_all(&input, pattern).collect::<Vec<_>>();
+
+            assert!(from_slice == from_reader);
+        }
+
+        assert!(find_all_in_slice(&input, exactly("foo").append(MatchRange('0', '9').repeat_forever(1))).collect::<Vec<_>>() == vec![2..6, 14..19]);
+        assert!(find_all_in_slice(&input, start_anchor().append(exactly("x")).or("foo")).collect::<Vec<_>>() == vec![0..1, 2..5, 11..14, 14..17, 20..23]);
+    }
+
+    ///
+    /// Compares the time taken by `find_all()` and `find_all_in_slice()` over a large input
+    ///
+    /// `find_all_in_slice()` reads the symbols directly rather than going through a tape, so it shouldn't be any slower. This
+    /// is ignored by default as the timings are only meaningful in a release build. Run it with
+    /// `cargo test --release find_all_timing -- --ignored`.
+    ///
+    #[test]
+    #[ignore]
+    fn find_all_timing() {
+        use std::time::Instant;
+
+        let input   = "abc 123 def, 4567 ghi ".chars().cycle().take(1_000_000).collect::<Vec<_>>();
+        let dfa     = MatchRange('0', '9').repeat_forever(1).prepare_to_match();
+
+        let start       = Instant::now();
+        let from_reader = find_all(&input, dfa.clone()).count();
+        let reader_time = start.elapsed();
+
+        let start       = Instant::now();
+        let from_slice  = find_all_in_slice(&input, dfa).count();
+        let slice_time  = start.elapsed();
+
+        assert!(from_reader == from_slice);
+        assert!(slice_time <= reader_time);
+    }
+
+    #[test]
+    fn find_all_skips_empty_matches() {
+        let found = find_all("a1b22", MatchRange('0', '9').repeat_forever(0)).collect::<Vec<_>>();
+
+        assert!(found == vec![1..2, 3..5]);
+    }
+
+    #[test]
+    fn find_in_middle_of_input() {
+        assert!(find("abcde", exactly("cd")) == Some(2..4));
+        assert!(find("abcde", exactly("ab")) == Some(0..2));
+        assert!(find("abcde", exactly("ce")).is_none());
+    }
+
+    #[test]
+    fn matches_with_output_returns_the_matching_pattern() {
+        let mut matcher = TokenMatcher::new();
+        matcher.add_pattern(exactly("if"), 1);
+        matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), 2);
+        matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), 3);
+
+        assert!(matches_with_output("if", &matcher) == Some((2, 1)));
+        assert!(matches_with_output("iffy", &matcher) == Some((4, 2)));
+        assert!(matches_with_output("42 ", &matcher) == Some((2, 3)));
+        assert!(matches_with_output("+", &matcher).is_none());
+    }
 }
@@ -0,0 +1,295 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! The DFA compiler generates a new state for every set of NDFA states it finds, so its output often contains states that
+//! behave identically. Minimizing a DFA merges these states using Hopcroft's partition refinement algorithm.
+//!
+//! ```
+//! # use concordance::*;
+//! let dfa     = exactly("abc").or("abd").prepare_to_match();
+//! let minimal = dfa.clone().minimize();
+//!
+//! // The states after 'c' and 'd' are merged
+//! # assert!(minimal.count_states() == dfa.count_states()-1);
+//! # assert!(matches_prepared("abd", &minimal) == Some(3));
+//! ```
+//!
+//! States start off in separate partitions if they have different output symbols, so the minimized DFA produces the same
+//! output for the longest match as the original one. This includes the output symbols used at the end of the input, and
+//! the start of input state is kept, so anchored patterns still match in the same way. States that can never reach an
+//! accepting state are removed.
+//!
+
+use std::collections::HashMap;
+
+use super::countable::*;
+use super::dfa_builder::*;
+use super::overlapping_symbols::*;
+use super::state_machine::*;
+use super::symbol_range_dfa::*;
+
+impl<InputSymbol: Ord+Clone+Countable, OutputSymbol: Clone+PartialEq> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Returns a DFA with the smallest number of states that matches the same strings with the same outputs as this one
+    ///
+    /// The states of the minimized DFA are numbered in the same way as by `canonicalize()`.
+    ///
+    pub fn minimize(self) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+        let num_states  = self.count_states() as usize;
+        let dead_state  = num_states;
+
+        // Divide the symbols into classes so that every transition covers a whole number of classes
+        let mut symbol_map = SymbolMap::new();
+        for state in 0..num_states {
+            for (range, _) in self.get_transitions_for_state(state as StateId) {
+                symbol_map.add_range(&range);
+            }
+        }
+
+        let classes         = symbol_map.to_non_overlapping_map();
+        let classes         = classes.ranges();
+
+        // Work out the states that move to each state for each class. Missing transitions go to an extra 'dead' state.
+        let mut sources: Vec<Vec<(usize, usize)>> = vec![vec![]; num_states+1];
+
+        for state in 0..num_states {
+            let mut covered = vec![false; classes.len()];
+
+            for (range, target) in self.get_transitions_for_state(state as StateId) {
+                let mut class = classes.binary_search_by(|class| class.lowest.cmp(&range.lowest)).unwrap_or_else(|pos| pos);
+
+                while class < classes.len() && classes[class].highest <= range.highest {
+                    covered[class] = true;
+                    sources[target as usize].push((class, state));
+                    class += 1;
+                }
+            }
+
+            for (class, _) in covered.iter().enumerate().filter(|(_, covered)| !**covered) {
+                sources[dead_state].push((class, state));
+            }
+        }
+
+        for class in 0..classes.len() {
+            sources[dead_state].push((class, dead_state));
+        }
+
+        // Initially, states are partitioned by their output symbols (both when there's more input and at the end of the input)
+        let mut blocks: Vec<Vec<usize>>                                             = vec![];
+        let mut block_for_state: Vec<usize>                                         = vec![];
+        let mut block_outputs: Vec<(Option<&OutputSymbol>, Option<&OutputSymbol>)>  = vec![];
+
+        for state in 0..=num_states {
+            let output = if state == dead_state { (None, None) } else { (self.output(state as StateId), self.output_at_end(state as StateId)) };
+
+            let block = match block_outputs.iter().position(|block_output| *block_output == output) {
+                Some(block) => block,
+                None        => {
+                    block_outputs.push(output);
+                    blocks.push(vec![]);
+                    blocks.len()-1
+                }
+            };
+
+            blocks[block].push(state);
+            block_for_state.push(block);
+        }
+
+        // Split the blocks until every state in a block moves to the same block for every class of symbol
+        let mut waiting: Vec<usize> = (0..blocks.len()).collect();
+        let mut is_waiting          = vec![true; blocks.len()];
+
+        while let Some(splitter) = waiting.pop() {
+            is_waiting[splitter] = false;
+
+            // Find the states that move into the splitter block for each class
+            let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+
+            for &state in blocks[splitter].iter() {
+                for &(class, source) in sources[state].iter() {
+                    predecessors.entry(class).or_default().push(source);
+                }
+            }
+
+            for (_, class_predecessors) in predecessors {
+                // Group the predecessors by the block they're currently in
+                let mut moving: HashMap<usize, Vec<usize>> = HashMap::new();
+
+                for source in class_predecessors {
+                    moving.entry(block_for_state[source]).or_default().push(source);
+                }
+
+                for (block, moving_states) in moving {
+                    // Nothing to do if every state in the block moves to the splitter
+                    if moving_states.len() == blocks[block].len() {
+                        continue;
+                    }
+
+                    // Split the moving states into a new block
+                    let new_block = blocks.len();
+
+                    for &state in moving_states.iter() {
+                        block_for_state[state] = new_block;
+                    }
+
+                    blocks[block].retain(|state| block_for_state[*state] == block);
+                    blocks.push(moving_states);
+
+                    // Both halves need to be used as splitters if the original block was, otherwise only the smaller one does
+                    if is_waiting[block] {
+                        is_waiting.push(true);
+                        waiting.push(new_block);
+                    } else {
+                        let smaller = if blocks[block].len() <= blocks[new_block].len() { block } else { new_block };
+
+                        is_waiting.push(false);
+                        is_waiting[smaller] = true;
+                        waiting.push(smaller);
+                    }
+                }
+            }
+        }
+
+        // The block containing the start state becomes state 0
+        let start_block = block_for_state[0];
+        let dead_block  = block_for_state[dead_state];
+        let mut order   = vec![start_block];
+        order.extend((0..blocks.len()).filter(|block| *block != start_block));
+
+        let mut new_ids = vec![0; blocks.len()];
+        for (new_id, block) in order.iter().enumerate() {
+            new_ids[*block] = new_id as StateId;
+        }
+
+        // Generate a state for each block, using the transitions of any of its states
+        let mut builder = SymbolRangeDfaBuilder::new();
+
+        // Matches at the start of the input begin in whichever block the original start of input state ended up in
+        if self.has_start_of_input_state() {
+            builder.set_start_of_input(new_ids[block_for_state[self.initial_state(true) as usize]]);
+        }
+
+        for block in order {
+            builder.start_state();
+
+            let representative = blocks[block].iter().cloned().find(|state| *state != dead_state);
+
+            if let Some(representative) = representative {
+                for (range, target) in self.get_transitions_for_state(representative as StateId) {
+                    let target_block = block_for_state[target as usize];
+
+                    // Transitions to the dead state are left out, so the dead state becomes unreachable
+                    if target_block != dead_block {
+                        builder.transition(range, new_ids[target_block]);
+                    }
+                }
+
+                if let Some(output) = self.output_symbol_for_state(representative as StateId) {
+                    builder.accept(output.clone());
+                }
+
+                if self.has_end_of_input_outputs() {
+                    match self.output_at_end(representative as StateId) {
+                        Some(output)    => builder.accept_at_end(output.clone()),
+                        None            => builder.reject_at_end()
+                    }
+                }
+            }
+        }
+
+        // Canonicalizing removes the unreachable states
+        builder.build().canonicalize()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    fn same_matches(first: &SymbolRangeDfa<char, u32>, second: &SymbolRangeDfa<char, u32>, inputs: &[&str]) -> bool {
+        inputs.iter().all(|input| matches_ref(first, *input) == matches_ref(second, *input))
+    }
+
+    #[test]
+    fn merges_equivalent_states() {
+        let mut matcher = TokenMatcher::new();
+        matcher.add_pattern(exactly("abc").or("xbc"), 1);
+
+        let dfa     = matcher.prepare_to_match();
+        let minimal = dfa.clone().minimize();
+
+        assert!(minimal.count_states() < dfa.count_states());
+        assert!(minimal.count_states() == 4);
+        assert!(same_matches(&dfa, &minimal, &["abc", "abd", "xbc", "xbd", "ab", "abcd", "", "q"]));
+    }
+
+    #[test]
+    fn keeps_states_with_different_outputs() {
+        let mut matcher = TokenMatcher::new();
+        matcher.add_pattern(exactly("abc"), 1);
+        matcher.add_pattern(exactly("abd"), 2);
+
+        let dfa     = matcher.prepare_to_match();
+        let minimal = dfa.clone().minimize();
+
+        assert!(minimal.count_states() == 5);
+        assert!(same_matches(&dfa, &minimal, &["abc", "abd", "abe", "ab"]));
+    }
+
+    #[test]
+    fn minimizing_repetitions_keeps_longest_match() {
+        let mut matcher = TokenMatcher::new();
+        matcher.add_pattern(MatchRange('a', 'b').repeat_forever(0).append(exactly("c")).or(exactly("bb")), 1);
+        matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), 2);
+
+        let dfa     = matcher.prepare_to_match();
+        let minimal = dfa.clone().minimize();
+
+        assert!(minimal.count_states() <= dfa.count_states());
+        assert!(same_matches(&dfa, &minimal, &["c", "abac", "bb", "bbb", "bbc", "123", "12a", "ab", "bbabc"]));
+    }
+
+    #[test]
+    fn minimizing_keeps_start_anchors() {
+        let dfa     = start_anchor().append(exactly("ab")).prepare_to_match();
+        let minimal = dfa.clone().minimize();
+
+        assert!(minimal.has_start_of_input_state());
+        assert!(matches_prepared("ab", &minimal) == Some(2));
+        assert!(minimal.match_at_slice(&['x', 'a', 'b'], 1).is_none());
+        assert!(minimal.is_equivalent_with_outputs(&dfa));
+    }
+
+    #[test]
+    fn minimizing_keeps_end_anchors() {
+        let dfa     = exactly("ab").append(end_anchor()).prepare_to_match();
+        let minimal = dfa.clone().minimize();
+
+        assert!(minimal.has_end_of_input_outputs());
+        assert!(matches_prepared("ab", &minimal) == Some(2));
+        assert!(matches_prepared("abc", &minimal).is_none());
+        assert!(minimal.is_equivalent_with_outputs(&dfa));
+    }
+
+    #[test]
+    fn minimizing_is_canonical() {
+        let first   = exactly("abc").or("abd").prepare_to_match().minimize();
+        let second  = exactly("ab").append(MatchRange('c', 'd')).prepare_to_match().minimize();
+
+        assert!(first == second);
+    }
+}
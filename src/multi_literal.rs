@@ -0,0 +1,234 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! A `MultiLiteralMatcher` finds every occurrence of a large set of literal strings in a text, in a single pass.
+//!
+//! A DFA built from an alternation of literals can only report one match at each position: this uses the Aho-Corasick
+//! algorithm instead, which builds a trie of the words and adds 'failure' links that say where to continue from when
+//! the next character doesn't extend the current match. Every occurrence of every word is reported, including ones that
+//! overlap or that are contained within other words.
+//!
+//! ```
+//! # use concordance::*;
+//! let matcher = MultiLiteralMatcher::new(&[("he", 1), ("she", 2)]);
+//! let found   = matcher.find_all("ushe");
+//!
+//! // found == vec![(1..4, &2), (2..4, &1)]
+//! # assert!(found == vec![(1..4, &2), (2..4, &1)]);
+//! ```
+//!
+
+use std::ops::Range;
+
+use super::symbol_reader::*;
+use super::state_machine::*;
+
+///
+/// Matches many literal strings at once, reporting every occurrence of each one
+///
+#[derive(Clone, Debug)]
+pub struct MultiLiteralMatcher<OutputSymbol> {
+    /// The trie transitions for each state, sorted by character
+    transitions: Vec<Vec<(char, StateId)>>,
+
+    /// The state to continue from when a state has no transition for the next character
+    failure: Vec<StateId>,
+
+    /// The words (as indexes into `words`) that end at each state, longest first
+    outputs: Vec<Vec<usize>>,
+
+    /// The length in characters and the output symbol of each word
+    words: Vec<(usize, OutputSymbol)>
+}
+
+impl<OutputSymbol: Clone> MultiLiteralMatcher<OutputSymbol> {
+    ///
+    /// Creates a matcher that finds the specified words, each of which is reported along with its output symbol
+    ///
+    /// Empty words are ignored, as they would match at every position.
+    ///
+    pub fn new(words: &[(&str, OutputSymbol)]) -> MultiLiteralMatcher<OutputSymbol> {
+        let mut matcher = MultiLiteralMatcher {
+            transitions:    vec![vec![]],
+            failure:        vec![0],
+            outputs:        vec![vec![]],
+            words:          vec![]
+        };
+
+        for &(word, ref output) in words {
+            if !word.is_empty() {
+                matcher.add_word(word, output.clone());
+            }
+        }
+
+        matcher.build_failure_links();
+        matcher
+    }
+}
+
+impl<OutputSymbol> MultiLiteralMatcher<OutputSymbol> {
+    ///
+    /// Returns the state reached from a state on a particular character in the trie
+    ///
+    fn goto(&self, state: StateId, chr: char) -> Option<StateId> {
+        let transitions = &self.transitions[state as usize];
+
+        transitions.binary_search_by_key(&chr, |&(symbol, _)| symbol)
+            .ok()
+            .map(|index| transitions[index].1)
+    }
+
+    ///
+    /// Adds a word to the trie
+    ///
+    fn add_word(&mut self, word: &str, output: OutputSymbol) {
+        let mut state = 0;
+
+        for chr in word.chars() {
+            let transitions = &mut self.transitions[state as usize];
+
+            state = match transitions.binary_search_by_key(&chr, |&(symbol, _)| symbol) {
+                Ok(index)   => transitions[index].1,
+                Err(index)  => {
+                    let new_state = self.failure.len() as StateId;
+                    transitions.insert(index, (chr, new_state));
+
+                    self.transitions.push(vec![]);
+                    self.failure.push(0);
+                    self.outputs.push(vec![]);
+
+                    new_state
+                }
+            };
+        }
+
+        self.outputs[state as usize].push(self.words.len());
+        self.words.push((word.chars().count(), output));
+    }
+
+    ///
+    /// Fills in the failure links for every state in the trie
+    ///
+    /// The states are visited breadth-first, so the failure state for a state (which is always shallower in the trie) has
+    /// been finished by the time it's needed.
+    ///
+    fn build_failure_links(&mut self) {
+        let mut queue: Vec<StateId> = self.transitions[0].iter().map(|&(_, target)| target).collect();
+        let mut next_index          = 0;
+
+        while next_index < queue.len() {
+            let state = queue[next_index];
+            next_index += 1;
+
+            for (chr, target) in self.transitions[state as usize].clone() {
+                // Follow the failure links from this state until one of them can be extended by this character
+                let mut failure = self.failure[state as usize];
+                while failure != 0 && self.goto(failure, chr).is_none() {
+                    failure = self.failure[failure as usize];
+                }
+
+                let failure = match self.goto(failure, chr) {
+                    Some(failure_target) if failure_target != target    => failure_target,
+                    _                                                   => 0
+                };
+
+                // Any word that ends at the failure state also ends at this one
+                let inherited = self.outputs[failure as usize].clone();
+
+                self.failure[target as usize] = failure;
+                self.outputs[target as usize].extend(inherited);
+
+                queue.push(target);
+            }
+        }
+    }
+
+    ///
+    /// Returns the number of words that this matcher will find
+    ///
+    pub fn count_words(&self) -> usize {
+        self.words.len()
+    }
+
+    ///
+    /// Finds every occurrence of every word in a text
+    ///
+    /// Matches are returned as ranges of character positions, ordered by where they end. Matches that end at the same
+    /// position are returned longest first.
+    ///
+    pub fn find_all<'a, 'b, Source: SymbolSource<'b, char>>(&'a self, text: Source) -> Vec<(Range<usize>, &'a OutputSymbol)> {
+        let mut reader  = text.read_symbols();
+        let mut result  = vec![];
+        let mut state   = 0;
+        let mut end     = 0;
+
+        while let Some(chr) = reader.next_symbol() {
+            end += 1;
+
+            while state != 0 && self.goto(state, chr).is_none() {
+                state = self.failure[state as usize];
+            }
+
+            state = self.goto(state, chr).unwrap_or(0);
+
+            for &word in self.outputs[state as usize].iter() {
+                let (length, ref output) = self.words[word];
+                result.push(((end-length)..end, output));
+            }
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn finds_overlapping_keywords() {
+        let matcher = MultiLiteralMatcher::new(&[("he", "he"), ("she", "she"), ("his", "his"), ("hers", "hers")]);
+        let found   = matcher.find_all("ushers");
+
+        assert!(found == vec![(1..4, &"she"), (2..4, &"he"), (2..6, &"hers")]);
+    }
+
+    #[test]
+    fn follows_failure_links_between_words() {
+        let matcher = MultiLiteralMatcher::new(&[("abcd", 1), ("bce", 2), ("c", 3)]);
+        let found   = matcher.find_all("abce");
+
+        assert!(found == vec![(2..3, &3), (1..4, &2)]);
+    }
+
+    #[test]
+    fn reports_repeated_and_duplicate_words() {
+        let matcher = MultiLiteralMatcher::new(&[("aa", 1), ("aa", 2), ("", 3)]);
+        let found   = matcher.find_all("aaa");
+
+        assert!(matcher.count_words() == 2);
+        assert!(found == vec![(0..2, &1), (0..2, &2), (1..3, &1), (1..3, &2)]);
+    }
+
+    #[test]
+    fn positions_are_counted_in_characters() {
+        let matcher = MultiLiteralMatcher::new(&[("é", ())]);
+        let found   = matcher.find_all("café!");
+
+        assert!(found == vec![(3..4, &())]);
+    }
+}
@@ -45,7 +45,10 @@ use super::symbol_range::*;
 ///
 /// Represents a non-deterministic finite-state automata
 ///
-#[derive(Clone)]
+/// NDFAs can be serialized with serde (provided their input and output symbols can be), which makes it possible to save an
+/// intermediate state machine, inspect or edit it and load it again later on.
+///
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Ndfa<InputSymbol, OutputSymbol> where InputSymbol : Clone {
     /// Highest known state ID
     max_state: StateId,
@@ -60,6 +63,12 @@ pub struct Ndfa<InputSymbol, OutputSymbol> where InputSymbol : Clone {
     output_symbols: HashMap<StateId, OutputSymbol>
 }
 
+impl<InputSymbol: Clone, OutputSymbol> Default for Ndfa<InputSymbol, OutputSymbol> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<InputSymbol: Clone, OutputSymbol> Ndfa<InputSymbol, OutputSymbol> {
     ///
     /// Creates a new non-deterministic finite Automaton
@@ -71,6 +80,140 @@ impl<InputSymbol: Clone, OutputSymbol> Ndfa<InputSymbol, OutputSymbol> {
         Ndfa { max_state: 0, transitions: vec![], joined_with: vec![], output_symbols: HashMap::new() }
     }
 
+    ///
+    /// Adds an epsilon transition, which moves from one state to another without reading a symbol
+    ///
+    /// This is the same as joining the states: the `from` state gains all of the transitions and the output symbol of the
+    /// `to` state, so the DFA compiler follows epsilon transitions without needing to know about them.
+    ///
+    pub fn add_epsilon(&mut self, from: StateId, to: StateId) {
+        self.join_states(from, to);
+    }
+
+    ///
+    /// Returns the transitions that were added to a state, without following any epsilon transitions
+    ///
+    pub fn own_transitions(&self, state: StateId) -> &[(InputSymbol, StateId)] {
+        self.transitions.get(state as usize).map(|transitions| &transitions[..]).unwrap_or(&[])
+    }
+
+    ///
+    /// Returns the states that a state has epsilon transitions to
+    ///
+    pub fn epsilon_targets(&self, state: StateId) -> &[StateId] {
+        self.joined_with.get(state as usize).map(|targets| &targets[..]).unwrap_or(&[])
+    }
+
+    ///
+    /// Returns the output symbol that was set for a state, without following any epsilon transitions
+    ///
+    pub fn own_output_symbol(&self, state: StateId) -> Option<&OutputSymbol> {
+        self.output_symbols.get(&state)
+    }
+
+    ///
+    /// Creates an NDFA that accepts anything that's accepted by either this NDFA or another one
+    ///
+    /// The states of both NDFAs are renumbered, and a new start state is added with epsilon transitions to the start states
+    /// of the two original NDFAs. Output symbols are kept, so a string accepted by both machines may have either output.
+    ///
+    /// The ranges used by the two machines may overlap, so `fix_overlapping_ranges()` should be called on the result before
+    /// compiling an NDFA that uses symbol ranges.
+    ///
+    pub fn union(self, other: Ndfa<InputSymbol, OutputSymbol>) -> Ndfa<InputSymbol, OutputSymbol> {
+        let mut result      = Ndfa::new();
+        let other_start     = self.count_states() + 1;
+
+        result.add_epsilon(0, 1);
+        result.add_epsilon(0, other_start);
+
+        result.add_renumbered(self, 1);
+        result.add_renumbered(other, other_start);
+
+        result
+    }
+
+    ///
+    /// Creates an NDFA that accepts strings made up of a string accepted by this NDFA followed by a string accepted by
+    /// another one
+    ///
+    /// Every accepting state of this NDFA gets an epsilon transition to the start of the other NDFA, whose states are
+    /// renumbered to follow on from the states of this one. The accepting states of this NDFA lose their output symbols,
+    /// so only the output symbols of the other NDFA are produced.
+    ///
+    pub fn concat(self, other: Ndfa<InputSymbol, OutputSymbol>) -> Ndfa<InputSymbol, OutputSymbol> {
+        let mut result          = self;
+        let other_start         = result.count_states();
+        let accepting: Vec<_> = result.output_symbols.drain().map(|(state, _)| state).collect();
+
+        for state in accepting {
+            result.add_epsilon(state, other_start);
+        }
+
+        result.add_renumbered(other, other_start);
+
+        result
+    }
+
+    ///
+    /// Creates an NDFA that accepts the reverse of every string accepted by this one
+    ///
+    /// Every transition (including epsilon transitions) is flipped around. A new start state is added with epsilon
+    /// transitions to each state that was accepting, and the old start state becomes the only accepting state. The states
+    /// of this NDFA are renumbered by adding one to their IDs, as the new start state is state 0.
+    ///
+    /// Output symbols can't be kept: the old start state is reached by the reverse of every accepted string, whatever its
+    /// output was, so it can only have a single output symbol. The reversed NDFA produces `()` for every match.
+    ///
+    pub fn reverse(&self) -> Ndfa<InputSymbol, ()> {
+        let mut result = Ndfa::new();
+
+        result.create_state(self.max_state + 1);
+
+        for (state, transitions) in self.transitions.iter().enumerate() {
+            for (symbol, target) in transitions.iter() {
+                result.add_transition(target + 1, symbol.clone(), state as StateId + 1);
+            }
+        }
+
+        for (state, joined_with) in self.joined_with.iter().enumerate() {
+            for target in joined_with.iter() {
+                result.add_epsilon(target + 1, state as StateId + 1);
+            }
+        }
+
+        for state in self.output_symbols.keys() {
+            result.add_epsilon(0, state + 1);
+        }
+
+        result.set_output_symbol(1, ());
+
+        result
+    }
+
+    ///
+    /// Adds the states from another NDFA to this one, adding an offset to every state ID
+    ///
+    fn add_renumbered(&mut self, ndfa: Ndfa<InputSymbol, OutputSymbol>, offset: StateId) {
+        self.create_state(ndfa.max_state + offset);
+
+        for (state, transitions) in ndfa.transitions.into_iter().enumerate() {
+            for (symbol, target) in transitions {
+                self.add_transition(state as StateId + offset, symbol, target + offset);
+            }
+        }
+
+        for (state, joined_with) in ndfa.joined_with.into_iter().enumerate() {
+            for target in joined_with {
+                self.join_states(state as StateId + offset, target + offset);
+            }
+        }
+
+        for (state, output_symbol) in ndfa.output_symbols {
+            self.set_output_symbol(state + offset, output_symbol);
+        }
+    }
+
     ///
     /// Retrieves the complete set of states whose transitions should be returned due to joining for a given state
     ///
@@ -92,7 +235,7 @@ impl<InputSymbol: Clone, OutputSymbol> Ndfa<InputSymbol, OutputSymbol> {
 
                 // Process any states that are joined to this one
                 if (next_state as usize) < self.joined_with.len() {
-                    let ref join_states = self.joined_with[next_state as usize];
+                    let join_states = &self.joined_with[next_state as usize];
 
                     for join_to in join_states {
                         stack.push(*join_to);
@@ -117,7 +260,7 @@ impl<Symbol: Ord+Clone+Countable, OutputSymbol> Ndfa<SymbolRange<Symbol>, Output
         let mut symbol_map = SymbolMap::new();
 
         for transit in &self.transitions {
-            for &(ref range, _) in transit {
+            for (range, _) in transit {
                 symbol_map.add_range(range);
             }
         }
@@ -157,7 +300,7 @@ impl<InputSymbol: Clone, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> f
     /// Retrieves the transitions for a particular state
     ///
     fn get_transitions_for_state(&self, state: StateId) -> Vec<(InputSymbol, StateId)> {
-        let empty           = vec![];
+        let empty           = [];
 
         // The transitions are all the transitions for this state, plus all the transitions in the states that are joined to it
         let joined_states   = self.get_join_closure(state);
@@ -169,7 +312,7 @@ impl<InputSymbol: Clone, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> f
             }
         });
 
-        merged.map(|item| item.clone()).collect()
+        merged.cloned().collect()
     }
 
     ///
@@ -195,6 +338,16 @@ impl<InputSymbol: Clone, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> f
             _ => result
         }
     }
+
+    ///
+    /// Retrieves the states that are joined to a particular state, including the state itself
+    ///
+    fn epsilon_closure(&self, state: StateId) -> Vec<StateId> {
+        let mut closure = self.get_join_closure(state).into_iter().collect::<Vec<_>>();
+        closure.sort();
+
+        closure
+    }
 }
 
 impl<InputSymbol : Clone, OutputSymbol> MutableStateMachine<InputSymbol, OutputSymbol> for Ndfa<InputSymbol, OutputSymbol> {
@@ -422,4 +575,135 @@ mod test {
         assert!(ndfa.get_transitions_for_state(1).contains(&(42, 1)));
         assert!(ndfa.get_transitions_for_state(1).contains(&(43, 2)));
     }
+
+    #[test]
+    fn dfa_follows_epsilon_transitions() {
+        use super::super::*;
+
+        // Matches 'a?b+' by skipping the 'a' with an epsilon transition, and looping back with another
+        let mut ndfa: Ndfa<SymbolRange<char>, ()> = Ndfa::new();
+
+        ndfa.add_transition(0, SymbolRange::new('a', 'a'), 1);
+        ndfa.add_epsilon(0, 1);
+        ndfa.add_transition(1, SymbolRange::new('b', 'b'), 2);
+        ndfa.add_epsilon(2, 1);
+        ndfa.set_output_symbol(2, ());
+        ndfa.fix_overlapping_ranges();
+
+        let dfa = DfaCompiler::build(ndfa, SymbolRangeDfaBuilder::new());
+
+        assert!(matches_prepared("ab", &dfa) == Some(2));
+        assert!(matches_prepared("b", &dfa) == Some(1));
+        assert!(matches_prepared("abbb", &dfa) == Some(4));
+        assert!(matches_prepared("bbb", &dfa) == Some(3));
+        assert!(matches_prepared("a", &dfa).is_none());
+        assert!(matches_prepared("aab", &dfa).is_none());
+    }
+
+    #[test]
+    fn union_matches_either_machine() {
+        use super::super::*;
+
+        // 'a+' with output 1
+        let mut a_ndfa: Ndfa<SymbolRange<char>, u32> = Ndfa::new();
+        a_ndfa.add_transition(0, SymbolRange::new('a', 'a'), 1);
+        a_ndfa.add_transition(1, SymbolRange::new('a', 'a'), 1);
+        a_ndfa.set_output_symbol(1, 1);
+
+        // 'b+' with output 2
+        let mut b_ndfa: Ndfa<SymbolRange<char>, u32> = Ndfa::new();
+        b_ndfa.add_transition(0, SymbolRange::new('b', 'b'), 1);
+        b_ndfa.add_transition(1, SymbolRange::new('b', 'b'), 1);
+        b_ndfa.set_output_symbol(1, 2);
+
+        let mut union = a_ndfa.union(b_ndfa);
+        union.fix_overlapping_ranges();
+        assert!(union.count_states() == 5);
+
+        let dfa = DfaCompiler::build(union, SymbolRangeDfaBuilder::new());
+
+        assert!(matches_prepared("aaa", &dfa) == Some(3));
+        assert!(matches_prepared("bb", &dfa) == Some(2));
+        assert!(matches_prepared("ab", &dfa) == Some(1));
+        assert!(matches_prepared("c", &dfa).is_none());
+
+        assert!(matches_ref(&dfa, "aa").map(|(_, output)| *output) == Some(1));
+        assert!(matches_ref(&dfa, "bb").map(|(_, output)| *output) == Some(2));
+    }
+
+    #[test]
+    fn concat_matches_both_machines_in_order() {
+        use super::super::*;
+
+        let mut a_ndfa: Ndfa<SymbolRange<char>, u32> = Ndfa::new();
+        a_ndfa.add_transition(0, SymbolRange::new('a', 'a'), 1);
+        a_ndfa.set_output_symbol(1, 1);
+
+        let mut b_ndfa: Ndfa<SymbolRange<char>, u32> = Ndfa::new();
+        b_ndfa.add_transition(0, SymbolRange::new('b', 'b'), 1);
+        b_ndfa.set_output_symbol(1, 2);
+
+        let mut concat = a_ndfa.concat(b_ndfa);
+        concat.fix_overlapping_ranges();
+
+        let dfa = DfaCompiler::build(concat, SymbolRangeDfaBuilder::new());
+
+        assert!(matches_ref(&dfa, "ab") == Some((2, &2)));
+        assert!(matches_prepared("abb", &dfa) == Some(2));
+        assert!(matches_prepared("a", &dfa).is_none());
+        assert!(matches_prepared("b", &dfa).is_none());
+        assert!(matches_prepared("ba", &dfa).is_none());
+    }
+
+    #[test]
+    fn ndfa_survives_json_round_trip() {
+        use super::super::*;
+
+        let mut ndfa: Ndfa<SymbolRange<char>, u32> = Ndfa::new();
+
+        let number_end  = MatchRange('0', '9').repeat_forever(1).compile(&mut ndfa, 0);
+        let word_end    = MatchRange('a', 'z').repeat_forever(1).compile(&mut ndfa, 0);
+        ndfa.set_output_symbol(number_end, 1);
+        ndfa.set_output_symbol(word_end, 2);
+        ndfa.fix_overlapping_ranges();
+
+        let json                                        = ::serde_json::to_string(&ndfa).unwrap();
+        let loaded: Ndfa<SymbolRange<char>, u32>        = ::serde_json::from_str(&json).unwrap();
+
+        assert!(loaded.count_states() == ndfa.count_states());
+
+        let original_dfa    = DfaCompiler::build(ndfa, SymbolRangeDfaBuilder::new());
+        let loaded_dfa      = DfaCompiler::build(loaded, SymbolRangeDfaBuilder::new());
+
+        let original: Vec<_>    = Tokenizer::new_prepared("12 abc 3".read_symbols(), &original_dfa).collect();
+        let reloaded: Vec<_>    = Tokenizer::new_prepared("12 abc 3".read_symbols(), &loaded_dfa).collect();
+
+        assert!(original.len() == 3);
+        assert!(reloaded == original);
+    }
+
+    #[test]
+    fn reverse_accepts_reversed_strings() {
+        use super::super::*;
+
+        // 'ab+c' or 'xy'
+        let mut ndfa: Ndfa<SymbolRange<char>, u32> = Ndfa::new();
+
+        let abc_end = exactly("a").append(exactly("b").repeat_forever(1)).append(exactly("c")).compile(&mut ndfa, 0);
+        let xy_end  = exactly("xy").compile(&mut ndfa, 0);
+        ndfa.set_output_symbol(abc_end, 1);
+        ndfa.set_output_symbol(xy_end, 2);
+
+        let mut reversed = ndfa.reverse();
+        reversed.fix_overlapping_ranges();
+
+        let dfa = DfaCompiler::build(reversed, SymbolRangeDfaBuilder::new());
+
+        assert!(matches_prepared("cba", &dfa) == Some(3));
+        assert!(matches_prepared("cbbba", &dfa) == Some(5));
+        assert!(matches_prepared("yx", &dfa) == Some(2));
+        assert!(matches_prepared("abc", &dfa).is_none());
+        assert!(matches_prepared("ca", &dfa).is_none());
+        assert!(matches_prepared("xy", &dfa).is_none());
+    }
 }
@@ -0,0 +1,267 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! A negated set of ranges matches every symbol that is not in any of the ranges it was created from. It's stored
+//! symbolically, as the list of ranges that are excluded, so it can be created for any countable type, including those
+//! with very large alphabets (like `u32`) or open alphabets where the full set of symbols is not known in advance.
+//!
+//! The negation is only expanded into the ranges that it matches when it's turned into a pattern. This is done by
+//! finding the gaps between the excluded ranges, so it never needs to enumerate the individual symbols in the alphabet.
+//!
+//! DFAs only have transitions for ranges of symbols, and have no 'anything else' transition, so a pattern for a negated
+//! set needs to know the lowest and highest symbols in the alphabet. For closed alphabets (such as `u8`, `u32` or `char`)
+//! these come from `Countable::min_symbol()` and `max_symbol()`. For open alphabets, where these are `None`,
+//! `matching_pattern_in()` expands the negation within the range of symbols that are known so far: as the alphabet
+//! grows (for instance, as more strings are interned), the pattern needs to be created again to match the new symbols.
+//! `includes()` works for any symbol without needing to know the alphabet.
+//!
+//! ```
+//! # use concordance::*;
+//! let not_digits  = NegatedRanges::new(vec![SymbolRange::new('0', '9')]);
+//! let pattern     = not_digits.matching_pattern().unwrap();
+//!
+//! matches("a", pattern.clone());   // == Some(1)
+//! matches("5", pattern.clone());   // == None
+//! # assert!(matches("a", pattern.clone()) == Some(1));
+//! # assert!(matches("5", pattern.clone()) == None);
+//! ```
+//!
+
+use std::cmp::Ordering;
+
+use super::countable::*;
+use super::symbol_range::*;
+use super::regular_pattern::*;
+
+///
+/// Represents every symbol that is not in a set of ranges
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct NegatedRanges<Symbol: Ord> {
+    /// The excluded ranges, in order and with no overlaps
+    excluded: Vec<SymbolRange<Symbol>>
+}
+
+impl<Symbol: Ord+Clone> NegatedRanges<Symbol> {
+    ///
+    /// Creates a new set of symbols containing everything not in the specified ranges
+    ///
+    pub fn new(excluded: Vec<SymbolRange<Symbol>>) -> NegatedRanges<Symbol> {
+        let mut excluded = excluded;
+        excluded.sort();
+
+        // Join any overlapping ranges so the list is ordered and distinct
+        let mut merged: Vec<SymbolRange<Symbol>> = vec![];
+
+        for range in excluded {
+            let overlaps_last = merged.last().map(|last| last.overlaps(&range)).unwrap_or(false);
+
+            if overlaps_last {
+                let last = merged.pop().unwrap();
+                merged.push(last.join(&range));
+            } else {
+                merged.push(range);
+            }
+        }
+
+        NegatedRanges { excluded: merged }
+    }
+
+    ///
+    /// True if the specified symbol is matched by this set (ie, is not in any of the excluded ranges)
+    ///
+    /// This works for open alphabets as no knowledge of the full set of symbols is required.
+    ///
+    pub fn includes(&self, symbol: &Symbol) -> bool {
+        let position = self.excluded.binary_search_by(|range| {
+            if range.highest < *symbol {
+                Ordering::Less
+            } else if range.lowest > *symbol {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        });
+
+        position.is_err()
+    }
+
+    ///
+    /// Returns the ranges that have been excluded from this set
+    ///
+    pub fn excluded_ranges(&self) -> &Vec<SymbolRange<Symbol>> {
+        &self.excluded
+    }
+}
+
+impl<Symbol: Ord+Clone+Countable> NegatedRanges<Symbol> {
+    ///
+    /// Returns the ranges of symbols matched by this set, or None if the symbol type has an open alphabet
+    ///
+    /// The ranges are found from the gaps between the excluded ranges, so the number of ranges returned is at most one
+    /// more than the number of excluded ranges.
+    ///
+    pub fn matching_ranges(&self) -> Option<Vec<SymbolRange<Symbol>>> {
+        let alphabet = SymbolRange::new(Symbol::min_symbol()?, Symbol::max_symbol()?);
+
+        Some(self.matching_ranges_in(&alphabet))
+    }
+
+    ///
+    /// Returns the ranges of symbols matched by this set that are part of an alphabet
+    ///
+    /// This works for open alphabets, where `alphabet` is the range of symbols that are known so far.
+    ///
+    pub fn matching_ranges_in(&self, alphabet: &SymbolRange<Symbol>) -> Vec<SymbolRange<Symbol>> {
+        let mut result  = vec![];
+        let mut lowest  = Some(alphabet.lowest.clone());
+
+        for range in self.excluded.iter() {
+            // Ranges outside of the alphabet don't make any gaps in it
+            if range.highest < alphabet.lowest {
+                continue;
+            } else if range.lowest > alphabet.highest {
+                break;
+            }
+
+            // The gap is from the symbol after the last range to the symbol before this one
+            if let Some(gap_start) = lowest {
+                if gap_start < range.lowest {
+                    result.push(SymbolRange::new(gap_start, range.lowest.prev()));
+                }
+            }
+
+            // The next gap starts after this range (there's no next gap if this range reaches the end of the alphabet)
+            lowest = if range.highest < alphabet.highest { Some(range.highest.next()) } else { None };
+        }
+
+        if let Some(gap_start) = lowest {
+            result.push(SymbolRange::new(gap_start, alphabet.highest.clone()));
+        }
+
+        result
+    }
+
+    ///
+    /// Returns a pattern that matches a single symbol from this set, or None if the symbol type has an open alphabet
+    ///
+    pub fn matching_pattern(&self) -> Option<Pattern<Symbol>> {
+        let alphabet = SymbolRange::new(Symbol::min_symbol()?, Symbol::max_symbol()?);
+
+        Some(self.matching_pattern_in(&alphabet))
+    }
+
+    ///
+    /// Returns a pattern that matches a single symbol from this set that's part of an alphabet
+    ///
+    /// For open alphabets, the pattern only matches symbols in the range that was passed in, so it needs to be created
+    /// again if new symbols are added to the alphabet.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let not_five    = NegatedRanges::new(vec![SymbolRange::new(5u32, 5u32)]);
+    /// let pattern     = not_five.matching_pattern_in(&SymbolRange::new(0, 9));
+    ///
+    /// matches(&vec![4u32], pattern.clone());  // == Some(1)
+    /// # assert!(matches(&vec![4u32], pattern.clone()) == Some(1));
+    /// # assert!(matches(&vec![5u32], pattern.clone()) == None);
+    /// # assert!(matches(&vec![10u32], pattern.clone()) == None);
+    /// ```
+    ///
+    pub fn matching_pattern_in(&self, alphabet: &SymbolRange<Symbol>) -> Pattern<Symbol> {
+        let mut ranges = self.matching_ranges_in(alphabet);
+
+        if ranges.len() == 1 {
+            let range = ranges.pop().unwrap();
+            MatchRange(range.lowest, range.highest)
+        } else {
+            MatchAny(ranges.into_iter().map(|range| MatchRange(range.lowest, range.highest)).collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct Interned(u32);
+
+    impl Countable for Interned {
+        fn next(&self) -> Self { Interned(self.0+1) }
+        fn prev(&self) -> Self { Interned(self.0-1) }
+    }
+
+    #[test]
+    fn can_negate_u32_ranges() {
+        let negated = NegatedRanges::new(vec![SymbolRange::new(5u32, 10u32), SymbolRange::new(100, 100)]);
+
+        assert!(negated.matching_ranges() == Some(vec![SymbolRange::new(0, 4), SymbolRange::new(11, 99), SymbolRange::new(101, u32::MAX)]));
+    }
+
+    #[test]
+    fn negated_u32_pattern_matches_samples() {
+        let negated = NegatedRanges::new(vec![SymbolRange::new(5u32, 10u32), SymbolRange::new(100, 100)]);
+        let matcher = negated.matching_pattern().unwrap().prepare_to_match();
+
+        for sample in [0u32, 4, 11, 99, 101, 65536, u32::MAX-1, u32::MAX].iter() {
+            assert!(matches_prepared(&vec![*sample], &matcher) == Some(1));
+            assert!(negated.includes(sample));
+        }
+
+        for sample in [5u32, 7, 10, 100].iter() {
+            assert!(matches_prepared(&vec![*sample], &matcher).is_none());
+            assert!(!negated.includes(sample));
+        }
+    }
+
+    #[test]
+    fn merges_overlapping_ranges() {
+        let negated = NegatedRanges::new(vec![SymbolRange::new(8u8, 20u8), SymbolRange::new(0, 10), SymbolRange::new(250, 255)]);
+
+        assert!(negated.excluded_ranges() == &vec![SymbolRange::new(0, 20), SymbolRange::new(250, 255)]);
+        assert!(negated.matching_ranges() == Some(vec![SymbolRange::new(21, 249)]));
+    }
+
+    #[test]
+    fn open_alphabet_is_symbolic() {
+        let negated = NegatedRanges::new(vec![SymbolRange::new(Interned(3), Interned(4))]);
+
+        assert!(negated.includes(&Interned(2)));
+        assert!(!negated.includes(&Interned(3)));
+        assert!(negated.includes(&Interned(1000)));
+        assert!(negated.matching_ranges().is_none());
+    }
+
+    #[test]
+    fn open_alphabet_can_be_matched_within_known_symbols() {
+        let negated = NegatedRanges::new(vec![SymbolRange::new(Interned(3), Interned(4)), SymbolRange::new(Interned(20), Interned(30))]);
+
+        assert!(negated.matching_ranges_in(&SymbolRange::new(Interned(0), Interned(10))) == vec![SymbolRange::new(Interned(0), Interned(2)), SymbolRange::new(Interned(5), Interned(10))]);
+        assert!(negated.matching_ranges_in(&SymbolRange::new(Interned(4), Interned(25))) == vec![SymbolRange::new(Interned(5), Interned(19))]);
+
+        // The pattern only knows about the alphabet it was created with, so it's created again when the alphabet grows
+        let known       = negated.matching_pattern_in(&SymbolRange::new(Interned(0), Interned(10))).prepare_to_match();
+        let grown       = negated.matching_pattern_in(&SymbolRange::new(Interned(0), Interned(100))).prepare_to_match();
+
+        assert!(matches_prepared(&vec![Interned(7)], &known) == Some(1));
+        assert!(matches_prepared(&vec![Interned(3)], &known).is_none());
+        assert!(matches_prepared(&vec![Interned(50)], &known).is_none());
+        assert!(matches_prepared(&vec![Interned(50)], &grown) == Some(1));
+        assert!(matches_prepared(&vec![Interned(25)], &grown).is_none());
+    }
+}
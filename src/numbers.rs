@@ -0,0 +1,134 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! Ready-made patterns for matching numbers written in the usual formats.
+//!
+//! ```
+//! # use concordance::*;
+//! use concordance::numbers::*;
+//!
+//! matches("-12", integer());      // == Some(3)
+//! matches("3.14", decimal());     // == Some(4)
+//! matches("0x1F", hex_literal()); // == Some(4)
+//! # assert!(matches("-12", integer()) == Some(3));
+//! # assert!(matches("3.14", decimal()) == Some(4));
+//! # assert!(matches("0x1F", hex_literal()) == Some(4));
+//! ```
+//!
+//! Like every pattern, these match as much of the start of the input as they can, so `matches("007", integer())` is
+//! `Some(1)`: check that the whole input was matched when validating a string.
+//!
+
+use super::regular_pattern::*;
+
+///
+/// Matches one or more decimal digits
+///
+fn digits() -> Pattern<char> {
+    MatchRange('0', '9').repeat_forever(1)
+}
+
+///
+/// Matches an optional '+' or '-' sign
+///
+fn sign() -> Pattern<char> {
+    exactly("+").or("-").repeat(0..2)
+}
+
+///
+/// Matches a whole number with no sign and no leading zeros (a single '0' is allowed)
+///
+fn unsigned_integer() -> Pattern<char> {
+    exactly("0").or(MatchRange('1', '9').append(MatchRange('0', '9').repeat_forever(0)))
+}
+
+///
+/// Matches an integer, with an optional sign. Leading zeros are not allowed, except for the number '0' itself.
+///
+pub fn integer() -> Pattern<char> {
+    sign().append(unsigned_integer())
+}
+
+///
+/// Matches a decimal number, with an optional sign, fractional part and exponent
+///
+/// Either side of the decimal point may be left out (but not both), so "1.", ".5" and "1.5" are all matched. The exponent
+/// is an 'e' or 'E' followed by an integer, which may have a sign and leading zeros.
+///
+pub fn decimal() -> Pattern<char> {
+    let whole_part      = unsigned_integer().append(exactly(".").append(MatchRange('0', '9').repeat_forever(0)).repeat(0..2));
+    let fraction_only   = exactly(".").append(digits());
+    let exponent        = exactly("e").or("E").append(sign()).append(digits());
+
+    sign().append(whole_part.or(fraction_only)).append(exponent.repeat(0..2))
+}
+
+///
+/// Matches a hexadecimal literal, such as '0x1F'
+///
+pub fn hex_literal() -> Pattern<char> {
+    let hex_digit = MatchRange('0', '9').or(MatchRange('a', 'f')).or(MatchRange('A', 'F'));
+
+    exactly("0").append(exactly("x").or("X")).append(hex_digit.repeat_forever(1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::matches::*;
+
+    fn matches_all(input: &str, pattern: Pattern<char>) -> bool {
+        matches(input, pattern) == Some(input.chars().count())
+    }
+
+    #[test]
+    fn integer_matches_signed_numbers() {
+        assert!(matches_all("0", integer()));
+        assert!(matches_all("42", integer()));
+        assert!(matches_all("-42", integer()));
+        assert!(matches_all("+7", integer()));
+        assert!(!matches_all("007", integer()));
+        assert!(!matches_all("-", integer()));
+    }
+
+    #[test]
+    fn decimal_matches_common_formats() {
+        assert!(matches_all("3.14", decimal()));
+        assert!(matches_all("-0.5", decimal()));
+        assert!(matches_all("1e10", decimal()));
+        assert!(matches_all("1.", decimal()));
+        assert!(matches_all(".5", decimal()));
+        assert!(matches_all("6.02E-23", decimal()));
+        assert!(matches_all("12", decimal()));
+    }
+
+    #[test]
+    fn decimal_rejects_malformed_numbers() {
+        assert!(!matches_all("1.2.3", decimal()));
+        assert!(!matches_all(".", decimal()));
+        assert!(!matches_all("1e", decimal()));
+        assert!(!matches_all("01.5", decimal()));
+    }
+
+    #[test]
+    fn hex_literal_matches_either_case() {
+        assert!(matches_all("0x1F", hex_literal()));
+        assert!(matches_all("0Xdeadbeef", hex_literal()));
+        assert!(!matches_all("0x", hex_literal()));
+        assert!(!matches_all("1F", hex_literal()));
+    }
+}
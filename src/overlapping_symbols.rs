@@ -51,6 +51,12 @@ pub struct SymbolMap<Symbol: Ord+Clone+Countable> {
     ranges: Vec<SymbolRange<Symbol>>,
 }
 
+impl<Symbol: Ord+Clone+Countable> Default for SymbolMap<Symbol> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Symbol: Ord+Clone+Countable> SymbolMap<Symbol> {
     ///
     /// Creates a new symbol map
@@ -93,6 +99,13 @@ impl<Symbol: Ord+Clone+Countable> SymbolMap<Symbol> {
         }
     }
 
+    ///
+    /// Returns the ranges in this map, in order
+    ///
+    pub fn ranges(&self) -> &[SymbolRange<Symbol>] {
+        &self.ranges
+    }
+
     ///
     /// Finds the ranges in this map that overlap the target ranges
     ///
@@ -185,7 +198,6 @@ impl<Symbol: Ord+Clone+Countable> SymbolMap<Symbol> {
 #[cfg(test)]
 mod test {
     use super::*;
-    use super::super::symbol_range::*;
 
     #[test]
     fn can_lookup_overlapping_ranges() {
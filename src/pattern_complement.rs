@@ -0,0 +1,222 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! The complement of a pattern matches every string that the pattern does not match. It's generated by compiling the pattern
+//! to a DFA, adding a 'dead' state for every symbol that the DFA doesn't have a transition for, swapping the accepting and
+//! non-accepting states and then converting the result back into a pattern.
+//!
+//! ```
+//! # use concordance::*;
+//! let not_abc = exactly("abc").complement();
+//!
+//! matches("abd", not_abc.clone());    // == Some(3)
+//! matches("abc", not_abc.clone());    // == Some(2) ("ab" is not "abc", but "abc" is)
+//! # assert!(matches("abd", not_abc.clone()) == Some(3));
+//! # assert!(matches("abc", not_abc.clone()) == Some(2));
+//! ```
+//!
+//! Patterns also implement `Not`, so `!pattern` is the same as `pattern.complement()`. There's a free `not()` function as
+//! well, so `not(exactly("if"))` can be used in the same way as `exactly()`.
+//!
+//! A compiled DFA can also be complemented directly by calling `complement()` with the output symbol that its accepting
+//! states should produce.
+//!
+
+use std::ops::Not;
+
+use super::countable::*;
+use super::dfa_builder::*;
+use super::negated_ranges::*;
+use super::prepare::*;
+use super::regular_pattern::*;
+use super::state_machine::*;
+use super::symbol_range::*;
+use super::symbol_range_dfa::*;
+
+impl<Symbol: Clone+Ord+Countable, OutputSymbol> SymbolRangeDfa<Symbol, OutputSymbol> {
+    ///
+    /// Creates a DFA that accepts every string rejected by this DFA
+    ///
+    /// The DFA is completed with a 'dead' state that every missing transition moves to, then its accepting and
+    /// non-accepting states are swapped. The states that were not accepting have no output symbol to keep, so every
+    /// accepting state of the complement produces the supplied output symbol.
+    ///
+    /// The symbol type must report its minimum and maximum symbols so that the dead state can be reached on every symbol
+    /// in the alphabet. This will panic if used with a symbol type with an open alphabet.
+    ///
+    pub fn complement<NewOutputSymbol: Clone>(&self, output: NewOutputSymbol) -> SymbolRangeDfa<Symbol, NewOutputSymbol> {
+        let min_symbol  = Symbol::min_symbol().expect("DFAs can only be complemented if their symbols have a bounded alphabet");
+        let max_symbol  = Symbol::max_symbol().expect("DFAs can only be complemented if their symbols have a bounded alphabet");
+
+        let num_states  = self.count_states();
+        let dead_state  = num_states;
+        let mut builder = SymbolRangeDfaBuilder::new();
+
+        // The states keep their IDs, so matches at the start of the input begin in the same state as before
+        if self.has_start_of_input_state() {
+            builder.set_start_of_input(self.initial_state(true));
+        }
+
+        for state in 0..num_states {
+            builder.start_state();
+
+            // Symbols that don't have a transition move to the dead state
+            let mut transitions = self.get_transitions_for_state(state);
+            let missing         = NegatedRanges::new(transitions.iter().map(|(range, _)| range.clone()).collect());
+
+            transitions.extend(missing.matching_ranges().unwrap().into_iter().map(|range| (range, dead_state)));
+            transitions.sort();
+
+            for (range, target) in transitions {
+                builder.transition(range, target);
+            }
+
+            if self.output_symbol_for_state(state).is_none() {
+                builder.accept(output.clone());
+            }
+
+            // States that accept at the end of the input in this DFA reject there in the complement, and vice versa
+            if self.has_end_of_input_outputs() {
+                match self.output_at_end(state) {
+                    None    => builder.accept_at_end(output.clone()),
+                    Some(_) => builder.reject_at_end()
+                }
+            }
+        }
+
+        // Once in the dead state, the DFA stays there and accepts everything
+        builder.start_state();
+        builder.transition(SymbolRange::new(min_symbol, max_symbol), dead_state);
+        builder.accept(output);
+
+        builder.build()
+    }
+}
+
+impl<Symbol: Clone+Ord+Countable+'static> Pattern<Symbol> {
+    ///
+    /// Creates a pattern that matches every string that this pattern does not match
+    ///
+    /// The symbol type must be `Countable`, and must report its minimum and maximum symbols so that the complement can
+    /// include every symbol that this pattern does not match. This will panic if used with a symbol type with an open
+    /// alphabet.
+    ///
+    /// Patterns containing anchors usually can't be complemented: the complement of `^ab` would need to match "ab"
+    /// everywhere except at the start of the input, and the complement of `ab$` would need to match "ab" everywhere except
+    /// at the end of the input, neither of which can be written as a pattern. This panics in these cases; use
+    /// `SymbolRangeDfa::complement()` on the compiled pattern instead.
+    ///
+    pub fn complement(&self) -> Pattern<Symbol> {
+        let dfa = self.clone().prepare_to_match();
+
+        dfa.complement(()).to_pattern()
+    }
+}
+
+impl<Symbol: Clone+Ord+Countable+'static> Not for Pattern<Symbol> {
+    type Output = Pattern<Symbol>;
+
+    ///
+    /// Creates a pattern that matches every string that this pattern does not match (see `complement()`)
+    ///
+    fn not(self) -> Pattern<Symbol> {
+        self.complement()
+    }
+}
+
+///
+/// Creates a pattern that matches every string that a pattern does not match
+///
+/// ```
+/// # use concordance::*;
+/// let not_if = not(exactly("if"));
+///
+/// matches("in", not_if.clone());  // == Some(2)
+/// matches("if", not_if.clone());  // == Some(1)
+/// # assert!(matches("in", not_if.clone()) == Some(2));
+/// # assert!(matches("if", not_if.clone()) == Some(1));
+/// ```
+///
+pub fn not<Symbol: Clone+Ord+Countable+'static, TPattern: IntoPattern<Symbol>>(pattern: TPattern) -> Pattern<Symbol> {
+    pattern.into_pattern().complement()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn complement_rejects_original_string() {
+        let not_abc = exactly("abc").complement().prepare_to_match();
+
+        assert!(matches_prepared("abc", &not_abc) != Some(3));
+        assert!(matches_prepared("abd", &not_abc) == Some(3));
+        assert!(matches_prepared("", &not_abc) == Some(0));
+        assert!(matches_prepared("abcd", &not_abc) == Some(4));
+    }
+
+    #[test]
+    fn complement_of_bytes() {
+        let not_digit = MatchRange(b'0', b'9').complement().prepare_to_match();
+
+        assert!(matches_prepared(&vec![b'5'], &not_digit) == Some(0));
+        assert!(matches_prepared(&vec![b'a'], &not_digit) == Some(1));
+        assert!(matches_prepared(&vec![b'5', b'5'], &not_digit) == Some(2));
+    }
+
+    #[test]
+    fn dfa_complement_uses_supplied_output() {
+        let not_ab = exactly("ab").prepare_to_match().complement("NotAb");
+
+        assert!(matches_ref(&not_ab, "ac") == Some((2, &"NotAb")));
+        assert!(matches_ref(&not_ab, "") == Some((0, &"NotAb")));
+        assert!(matches_ref(&not_ab, "ab") == Some((1, &"NotAb")));
+        assert!(matches_ref(&not_ab, "abc") == Some((3, &"NotAb")));
+    }
+
+    #[test]
+    fn dfa_complement_keeps_start_anchors() {
+        let not_ab = start_anchor().append(exactly("ab")).prepare_to_match().complement(());
+
+        assert!(not_ab.match_at_slice(&['a', 'b'], 0) == Some(1));
+        assert!(not_ab.match_at_slice(&['a', 'c'], 0) == Some(2));
+        assert!(not_ab.match_at_slice(&['x', 'a', 'b'], 1) == Some(2));
+    }
+
+    #[test]
+    fn dfa_complement_keeps_end_anchors() {
+        let not_ab = exactly("ab").append(end_anchor()).prepare_to_match().complement(());
+
+        // "ab" is only rejected when it's at the end of the input, so the longest match is "a"
+        assert!(not_ab.match_at_slice(&['a', 'b'], 0) == Some(1));
+        assert!(matches_prepared("ab", &not_ab) == Some(1));
+
+        assert!(not_ab.match_at_slice(&['a', 'b', 'c'], 0) == Some(3));
+        assert!(matches_prepared("abc", &not_ab) == Some(3));
+        assert!(matches_prepared("ac", &not_ab) == Some(2));
+    }
+
+    #[test]
+    fn not_matches_everything_else() {
+        let not_ab = not(exactly("ab"));
+
+        assert!(matches("ac", not_ab.clone()) == Some(2));
+        assert!(matches("", not_ab.clone()) == Some(0));
+        assert!(matches("ab", not_ab.clone()) != Some(2));
+        assert!(!exactly("ab") == not_ab);
+    }
+}
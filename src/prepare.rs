@@ -43,13 +43,12 @@ pub trait PrepareToMatch<As> where As: Sized {
 }
 
 impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Ord+Clone> PrepareToMatch<SymbolRangeDfa<InputSymbol, OutputSymbol>> 
-for Box<StateMachine<SymbolRange<InputSymbol>, OutputSymbol>+'a> {
+for Box<dyn StateMachine<SymbolRange<InputSymbol>, OutputSymbol>+'a> {
     #[inline]
     fn prepare_to_match(self) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
-        let builder       = SymbolRangeDfaBuilder::new();
-        let state_machine = DfaCompiler::build(self, builder);
+        let builder = SymbolRangeDfaBuilder::new();
 
-        state_machine
+        DfaCompiler::build(self, builder)
     }
 }
 
@@ -57,14 +56,33 @@ impl<InputSymbol: Clone+Ord+Countable+'static> PrepareToMatch<SymbolRangeDfa<Inp
 for Pattern<InputSymbol> {
     #[inline]
     fn prepare_to_match(self) -> SymbolRangeDfa<InputSymbol, ()> {
-        let ndfa = self.to_ndfa(());
+        let dfa = compile_with_end_of_input(&self);
 
-        ndfa.prepare_to_match()
+        // Patterns with start anchors need a separate DFA for matching at the start of the input
+        if self.has_start_anchor() {
+            dfa.with_start_of_input(compile_with_end_of_input(&self.at_start_of_input()))
+        } else {
+            dfa
+        }
     }
 }
 
-impl<'a, InputSymbol: Clone+Ord+Countable+'static> PrepareToMatch<SymbolRangeDfa<InputSymbol, ()>> 
-for &'a ToPattern<InputSymbol> {
+///
+/// Compiles a pattern to a DFA, including the outputs for when the input runs out if it has any end anchors
+///
+fn compile_with_end_of_input<InputSymbol: Clone+Ord+Countable+'static>(pattern: &Pattern<InputSymbol>) -> SymbolRangeDfa<InputSymbol, ()> {
+    let dfa: SymbolRangeDfa<InputSymbol, ()> = pattern.to_ndfa(()).prepare_to_match();
+
+    if pattern.has_end_anchor() {
+        let at_end: SymbolRangeDfa<InputSymbol, ()> = pattern.at_end_of_input().to_ndfa(()).prepare_to_match();
+        dfa.with_end_of_input(&at_end)
+    } else {
+        dfa
+    }
+}
+
+impl<InputSymbol: Clone+Ord+Countable+'static> PrepareToMatch<SymbolRangeDfa<InputSymbol, ()>> 
+for &dyn ToPattern<InputSymbol> {
     #[inline]
     fn prepare_to_match(self) -> SymbolRangeDfa<InputSymbol, ()> {
         let pattern = self.to_pattern();
@@ -80,8 +98,8 @@ impl<InputSymbol: Clone+Ord+Countable, OutputSymbol> PrepareToMatch<SymbolRangeD
     }
 }
 
-impl<'a> PrepareToMatch<SymbolRangeDfa<char, ()>> 
-for &'a str {
+impl PrepareToMatch<SymbolRangeDfa<char, ()>> 
+for &str {
     #[inline]
     fn prepare_to_match(self) -> SymbolRangeDfa<char, ()> {
         let pattern = self.to_pattern();
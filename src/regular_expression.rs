@@ -17,14 +17,1023 @@
 //!
 //! Regular expressions are a way to expression patterns in a regular language. They're only useful for character streams.
 //!
+//! ```
+//! # use concordance::*;
+//! let pattern = Pattern::from_regex("abc").unwrap();
+//!
+//! matches("abc", pattern); // == Some(3)
+//! # assert!(matches("abc", Pattern::from_regex("abc").unwrap()) == Some(3));
+//! ```
+//!
+
+use std::fmt;
+use std::error::Error;
+use std::iter::Peekable;
+use std::str::Chars;
 
+use super::symbol_range::*;
+use super::negated_ranges::*;
 use super::regular_pattern::*;
 
+///
+/// Describes why a regular expression could not be parsed
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RegexErrorKind {
+    /// The regular expression contains a character that is not supported yet
+    UnsupportedCharacter(char),
+
+    /// A character class was started with '[' but had no closing ']'
+    UnterminatedClass,
+
+    /// A group was started with '(' but had no closing ')'
+    UnterminatedGroup,
+
+    /// A ')' was found that doesn't close a group
+    UnmatchedParenthesis,
+
+    /// A quantifier such as '*' was found with nothing before it to repeat
+    DanglingQuantifier(char),
+
+    /// A counted repetition such as `{4,2}` has a maximum that's less than its minimum
+    InvalidRepetition(u32, u32),
+
+    /// The regular expression ends with a '\\'
+    UnterminatedEscape,
+
+    /// A '\\' is followed by a character that doesn't have a meaning as an escape sequence
+    UnknownEscape(char),
+
+    /// A character class does not contain any characters
+    EmptyClass,
+
+    /// A range in a character class has its first character after its last character
+    InvalidRange(char, char)
+}
+
+///
+/// Error generated when a regular expression could not be parsed
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RegexError {
+    /// The offset, in characters, into the regular expression where the problem was found
+    pub position: usize,
+
+    /// The reason the regular expression could not be parsed
+    pub kind: RegexErrorKind
+}
+
+impl RegexError {
+    ///
+    /// Creates a new error at a particular position in a regular expression
+    ///
+    pub fn new(position: usize, kind: RegexErrorKind) -> RegexError {
+        RegexError { position, kind }
+    }
+}
+
+impl fmt::Display for RegexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RegexErrorKind::UnsupportedCharacter(chr)       => write!(f, "'{}' is not supported", chr),
+            RegexErrorKind::UnterminatedClass               => write!(f, "character class is missing a closing ']'"),
+            RegexErrorKind::UnterminatedGroup               => write!(f, "group is missing a closing ')'"),
+            RegexErrorKind::UnmatchedParenthesis            => write!(f, "')' does not close a group"),
+            RegexErrorKind::DanglingQuantifier(chr)         => write!(f, "'{}' has nothing to repeat", chr),
+            RegexErrorKind::InvalidRepetition(min, max)     => write!(f, "repetition maximum {} is less than its minimum {}", max, min),
+            RegexErrorKind::UnterminatedEscape              => write!(f, "escape sequence is missing a character"),
+            RegexErrorKind::UnknownEscape(chr)              => write!(f, "'\\{}' is not a valid escape sequence", chr),
+            RegexErrorKind::EmptyClass                      => write!(f, "character class is empty"),
+            RegexErrorKind::InvalidRange(first, last)       => write!(f, "range '{}-{}' is out of order", first, last)
+        }
+    }
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.position)
+    }
+}
+
+impl Error for RegexError {
+}
+
+///
+/// Flags that change how a regular expression is interpreted
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct RegexFlags {
+    /// Letters match both their upper and lower case forms
+    pub case_insensitive: bool
+}
+
+impl RegexFlags {
+    ///
+    /// Creates a set of flags for a case-insensitive regular expression
+    ///
+    pub fn case_insensitive() -> RegexFlags {
+        RegexFlags { case_insensitive: true }
+    }
+}
+
+///
+/// Parser that turns a regular expression into a pattern
+///
+#[derive(Clone)]
+struct RegexParser<'a> {
+    /// The characters that are still to be parsed
+    chars: Peekable<Chars<'a>>,
+
+    /// The offset, in characters, of the next character to be parsed
+    position: usize,
+
+    /// Flags that affect how the expression is parsed
+    flags: RegexFlags
+}
+
+impl<'a> RegexParser<'a> {
+    ///
+    /// Creates a new parser for a regular expression
+    ///
+    fn new(regex: &'a str, flags: RegexFlags) -> RegexParser<'a> {
+        RegexParser { chars: regex.chars().peekable(), position: 0, flags }
+    }
+
+    ///
+    /// The offset, in characters, of the next character to be parsed
+    ///
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    ///
+    /// Reads the next character from the expression
+    ///
+    fn next(&mut self) -> Option<char> {
+        let next = self.chars.next();
+        if next.is_some() { self.position += 1; }
+
+        next
+    }
+
+    ///
+    /// Returns the next character from the expression without reading it
+    ///
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    ///
+    /// Reads a decimal number from the expression
+    ///
+    fn read_number(&mut self) -> Option<u32> {
+        let mut digits = String::new();
+
+        while let Some(digit) = self.peek().cloned().filter(|chr| chr.is_ascii_digit()) {
+            digits.push(digit);
+            self.next();
+        }
+
+        digits.parse().ok()
+    }
+
+    ///
+    /// Parses a set of alternative sequences, separated by '|'
+    ///
+    fn parse_alternatives(&mut self) -> Result<Pattern<char>, RegexError> {
+        let mut result = self.parse_sequence()?;
+
+        while self.peek() == Some(&'|') {
+            self.next();
+
+            let alternative = self.parse_sequence()?;
+            result = result.or(alternative);
+        }
+
+        Ok(result)
+    }
+
+    ///
+    /// Parses a sequence of atoms that should be matched in order
+    ///
+    fn parse_sequence(&mut self) -> Result<Pattern<char>, RegexError> {
+        let mut atoms = vec![];
+
+        loop {
+            // Sequences end at the end of the expression, an alternative or the end of a group
+            match self.peek() {
+                None | Some('|') | Some(')')    => break,
+                _                               => { }
+            }
+
+            let atom = self.parse_atom()?;
+            let atom = self.parse_quantifiers(atom)?;
+
+            atoms.push(atom);
+        }
+
+        Ok(sequence(atoms))
+    }
+
+    ///
+    /// Applies any quantifiers that follow an atom
+    ///
+    fn parse_quantifiers(&mut self, atom: Pattern<char>) -> Result<Pattern<char>, RegexError> {
+        let mut result = atom;
+
+        loop {
+            if self.peek() == Some(&'{') {
+                let position = self.position();
+
+                // Counters are only quantifiers if they're valid, otherwise the '{' is treated as a literal character
+                match self.parse_counter() {
+                    Some((min, max))    => { result = repeat_counted(result, min, max).map_err(|kind| RegexError::new(position, kind))?; continue; },
+                    None                => return Ok(result)
+                }
+            }
+
+            result = match self.peek() {
+                Some('*')   => result.repeat_forever(0),
+                Some('+')   => result.repeat_forever(1),
+                Some('?')   => result.repeat(0..2),
+                _           => return Ok(result)
+            };
+
+            self.next();
+        }
+    }
+
+    ///
+    /// If the next characters form a counter (`{n}`, `{n,}` or `{n,m}`), reads it and returns the minimum and maximum number
+    /// of repetitions. Returns None and leaves the parser where it was if they don't.
+    ///
+    fn parse_counter(&mut self) -> Option<(u32, Option<u32>)> {
+        let mut lookahead = self.clone();
+
+        if lookahead.next() != Some('{') {
+            return None;
+        }
+
+        let min = lookahead.read_number()?;
+
+        let max = match lookahead.next() {
+            Some('}')   => Some(min),
+            Some(',')   => {
+                if lookahead.peek() == Some(&'}') {
+                    lookahead.next();
+                    None
+                } else {
+                    let max = lookahead.read_number()?;
+                    if lookahead.next() != Some('}') { return None; }
+                    Some(max)
+                }
+            },
+            _           => return None
+        };
+
+        *self = lookahead;
+        Some((min, max))
+    }
+
+    ///
+    /// Parses a single item from the regular expression
+    ///
+    fn parse_atom(&mut self) -> Result<Pattern<char>, RegexError> {
+        let position = self.position();
+
+        // A valid counter needs something before it to repeat (but an invalid one is just a literal '{')
+        if self.parse_counter().is_some() {
+            return Err(RegexError::new(position, RegexErrorKind::DanglingQuantifier('{')));
+        }
+
+        match self.next() {
+            None                          => Ok(Epsilon),
+            Some('[')                     => self.parse_class(position),
+            Some('(')                     => self.parse_group(position),
+            Some('\\')                    => self.parse_escape(position),
+            Some(chr @ ('*' | '+' | '?')) => Err(RegexError::new(position, RegexErrorKind::DanglingQuantifier(chr))),
+            Some(chr)                     => {
+                if is_special(chr) {
+                    Err(RegexError::new(position, RegexErrorKind::UnsupportedCharacter(chr)))
+                } else {
+                    Ok(self.literal(chr))
+                }
+            }
+        }
+    }
+
+    ///
+    /// Parses a group (the opening '(', at `start`, has already been read)
+    ///
+    fn parse_group(&mut self, start: usize) -> Result<Pattern<char>, RegexError> {
+        let group = self.parse_alternatives()?;
+
+        if self.next() == Some(')') {
+            Ok(group)
+        } else {
+            Err(RegexError::new(start, RegexErrorKind::UnterminatedGroup))
+        }
+    }
+
+    ///
+    /// Parses a character class (the opening '[', at `start`, has already been read)
+    ///
+    fn parse_class(&mut self, start: usize) -> Result<Pattern<char>, RegexError> {
+        let unterminated    = RegexError::new(start, RegexErrorKind::UnterminatedClass);
+        let negated         = self.peek() == Some(&'^');
+        if negated { self.next(); }
+
+        let mut ranges = vec![];
+
+        loop {
+            // Read the first character of the next item
+            let item_position = self.position();
+
+            let first = match self.next() {
+                None        => return Err(unterminated),
+                Some(']')   => break,
+                Some('\\')  => {
+                    let escaped = self.next().ok_or_else(|| unterminated.clone())?;
+
+                    // Shorthand classes like '\d' add their ranges to this class (the negated forms can't be combined with other ranges)
+                    match shorthand_class(escaped) {
+                        Some((_, true))                 => return Err(RegexError::new(item_position, RegexErrorKind::UnknownEscape(escaped))),
+                        Some((shorthand_ranges, false)) => { ranges.extend(shorthand_ranges); continue; },
+                        None                            => escaped_char(escaped).map_err(|kind| RegexError::new(item_position, kind))?
+                    }
+                },
+                Some(chr)   => chr
+            };
+
+            // A '-' followed by anything other than the end of the class makes this a range
+            let mut lookahead = self.chars.clone();
+            let is_range = lookahead.next() == Some('-') && lookahead.peek().is_some() && lookahead.peek() != Some(&']');
+
+            if is_range {
+                self.next();
+
+                let last_position = self.position();
+
+                let last = match self.next() {
+                    Some('\\')  => {
+                        let escaped = self.next().ok_or_else(|| unterminated.clone())?;
+                        escaped_char(escaped).map_err(|kind| RegexError::new(last_position, kind))?
+                    },
+                    Some(chr)   => chr,
+                    None        => return Err(unterminated)
+                };
+
+                if last < first {
+                    return Err(RegexError::new(item_position, RegexErrorKind::InvalidRange(first, last)));
+                }
+
+                ranges.push(SymbolRange::new(first, last));
+            } else {
+                ranges.push(SymbolRange::new(first, first));
+            }
+        }
+
+        if ranges.is_empty() {
+            return Err(RegexError::new(start, RegexErrorKind::EmptyClass));
+        }
+
+        Ok(class_pattern(self.fold_case(ranges), negated))
+    }
+
+    ///
+    /// Parses an escape sequence (the '\\', at `start`, has already been read)
+    ///
+    fn parse_escape(&mut self, start: usize) -> Result<Pattern<char>, RegexError> {
+        let escaped = self.next().ok_or_else(|| RegexError::new(start, RegexErrorKind::UnterminatedEscape))?;
+
+        match shorthand_class(escaped) {
+            Some((ranges, negated)) => Ok(class_pattern(ranges, negated)),
+            None                    => Ok(self.literal(escaped_char(escaped).map_err(|kind| RegexError::new(start, kind))?))
+        }
+    }
+
+    ///
+    /// Creates the pattern that matches a literal character
+    ///
+    fn literal(&self, chr: char) -> Pattern<char> {
+        let ranges = self.fold_case(vec![SymbolRange::new(chr, chr)]);
+
+        if ranges.len() == 1 && ranges[0].lowest == ranges[0].highest {
+            Match(vec![chr])
+        } else {
+            class_pattern(ranges, false)
+        }
+    }
+
+    ///
+    /// If the expression is case-insensitive, adds the other case of any letters to a set of ranges
+    ///
+    /// This uses the same case conversions as `Pattern::case_insensitive()`.
+    ///
+    fn fold_case(&self, ranges: Vec<SymbolRange<char>>) -> Vec<SymbolRange<char>> {
+        if !self.flags.case_insensitive {
+            return ranges;
+        }
+
+        let mut folded = ranges.clone();
+
+        for range in ranges {
+            folded.extend(other_case(&range));
+        }
+
+        SymbolRange::join_all(folded)
+    }
+}
+
+///
+/// Returns the ranges containing the other case of any ASCII letters in a range
+///
+fn ascii_other_case(range: &SymbolRange<char>) -> Vec<SymbolRange<char>> {
+    let mut result = vec![];
+
+    for (letters, offset) in [(SymbolRange::new('a', 'z'), -32i32), (SymbolRange::new('A', 'Z'), 32i32)].iter() {
+        if range.overlaps(letters) {
+            let lowest  = range.lowest.max(letters.lowest) as i32 + offset;
+            let highest = range.highest.min(letters.highest) as i32 + offset;
+
+            result.push(SymbolRange::new(lowest as u8 as char, highest as u8 as char));
+        }
+    }
+
+    result
+}
+
+///
+/// Returns the character in a case conversion, if it converts to exactly one character
+///
+fn single_char<Chars: Iterator<Item=char>>(mut chars: Chars) -> Option<char> {
+    let first = chars.next();
+
+    if chars.next().is_some() { None } else { first }
+}
+
+///
+/// Returns every character that's the same as a character apart from its case, including the character itself
+///
+/// Case conversions that produce more than one character (such as 'ß' to 'SS') are ignored.
+///
+fn case_variants(chr: char) -> Vec<char> {
+    let mut variants    = vec![chr];
+    let mut index       = 0;
+
+    // Converting the variants can find more variants (for example, the Kelvin sign converts to 'k', which converts to 'K')
+    while index < variants.len() {
+        let current = variants[index];
+        index += 1;
+
+        for other in [single_char(current.to_lowercase()), single_char(current.to_uppercase())].iter().flatten() {
+            if !variants.contains(other) {
+                variants.push(*other);
+            }
+        }
+    }
+
+    variants
+}
+
+///
+/// Returns the ranges of characters that are in a different case to the characters in a range but aren't in the range
+/// themselves
+///
+fn other_case(range: &SymbolRange<char>) -> Vec<SymbolRange<char>> {
+    if range.highest <= '\u{7f}' {
+        ascii_other_case(range)
+    } else {
+        let mut result = vec![];
+
+        for chr in (range.lowest as u32..=range.highest as u32).filter_map(char::from_u32) {
+            result.extend(case_variants(chr).into_iter()
+                .filter(|variant| !range.includes(variant))
+                .map(|variant| SymbolRange::new(variant, variant)));
+        }
+
+        SymbolRange::join_all(result)
+    }
+}
+
+///
+/// Repeats a pattern between `min` and `max` times (or at least `min` times if there's no maximum)
+///
+fn repeat_counted(pattern: Pattern<char>, min: u32, max: Option<u32>) -> Result<Pattern<char>, RegexErrorKind> {
+    // Required copies are matched in order
+    let mut copies = vec![pattern.clone(); min as usize];
+
+    match max {
+        // No maximum: can repeat as many extra times as we like
+        None                        => copies.push(pattern.repeat_forever(0)),
+
+        // Maximum before the minimum is an error
+        Some(max) if max < min      => return Err(RegexErrorKind::InvalidRepetition(min, max)),
+
+        // Remaining copies are optional
+        Some(max)                   => copies.extend((min..max).map(|_| pattern.clone().repeat(0..2)))
+    }
+
+    Ok(sequence(copies))
+}
+
+///
+/// Creates a pattern that matches a list of patterns in order
+///
+fn sequence(patterns: Vec<Pattern<char>>) -> Pattern<char> {
+    let mut patterns    = patterns.into_iter();
+    let first           = patterns.next();
+
+    // An empty sequence matches the empty string
+    match first {
+        None        => Match(vec![]),
+        Some(first) => patterns.fold(first, |result, pattern| result.append(pattern))
+    }
+}
+
+///
+/// Creates the pattern for a character class
+///
+fn class_pattern(ranges: Vec<SymbolRange<char>>, negated: bool) -> Pattern<char> {
+    if negated {
+        // char has a bounded alphabet so the negated ranges can always be turned into a pattern
+        NegatedRanges::new(ranges).matching_pattern().unwrap()
+    } else {
+        let mut ranges  = ranges.into_iter().map(|range| MatchRange(range.lowest, range.highest));
+        let first       = ranges.next().unwrap();
+
+        ranges.fold(first, |result, range| result.or(range))
+    }
+}
+
+///
+/// If a character is the name of a shorthand class (such as the 'd' in '\d'), returns the ranges in the class and whether
+/// or not it's negated
+///
+fn shorthand_class(chr: char) -> Option<(Vec<SymbolRange<char>>, bool)> {
+    let ranges = match chr.to_ascii_lowercase() {
+        'd' => vec![SymbolRange::new('0', '9')],
+        'w' => vec![SymbolRange::new('a', 'z'), SymbolRange::new('A', 'Z'), SymbolRange::new('0', '9'), SymbolRange::new('_', '_')],
+        's' => vec![SymbolRange::new('\t', '\r'), SymbolRange::new(' ', ' ')],
+        _   => return None
+    };
+
+    // The upper case versions are negated
+    Some((ranges, chr.is_ascii_uppercase()))
+}
+
+///
+/// Returns the character matched by an escape sequence (such as '\n' or '\.')
+///
+fn escaped_char(chr: char) -> Result<char, RegexErrorKind> {
+    match chr {
+        'n'                         => Ok('\n'),
+        'r'                         => Ok('\r'),
+        't'                         => Ok('\t'),
+        chr if chr.is_alphanumeric() => Err(RegexErrorKind::UnknownEscape(chr)),
+        chr                         => Ok(chr)
+    }
+}
+
+///
+/// True if a character has a special meaning in a regular expression
+///
+fn is_special(chr: char) -> bool {
+    matches!(chr, '\\' | '(' | ')' | '[' | ']' | '*' | '+' | '?' | '|' | '.' | '^' | '$')
+}
+
 impl Pattern<char> {
     ///
     /// Creates a new pattern from a regular expression
     ///
-    pub fn from_regex(pattern: &str) -> Pattern<char> {
-        unimplemented!()
+    /// Literal characters, character classes (such as `[a-z]` or `[^0-9]`), groups, alternatives (`a|b`), the `*`, `+`
+    /// and `?` quantifiers, counted repetitions (`{n}`, `{n,}` and `{n,m}`) and escape sequences (including the `\d`, `\w`
+    /// and `\s` classes and their negated forms) are supported at the moment: any other character with a special meaning
+    /// will result in an error.
+    ///
+    /// Errors report the character offset into the expression where the problem was found.
+    ///
+    pub fn from_regex(pattern: &str) -> Result<Pattern<char>, RegexError> {
+        Self::from_regex_with_flags(pattern, RegexFlags::default())
+    }
+
+    ///
+    /// Creates a new pattern from a regular expression, using a set of flags to change how it's interpreted
+    ///
+    /// With the `case_insensitive` flag, literal characters and the characters in classes also match their other case
+    /// (so `[a-c]` will match `B`, for example).
+    ///
+    pub fn from_regex_with_flags(pattern: &str, flags: RegexFlags) -> Result<Pattern<char>, RegexError> {
+        let mut parser  = RegexParser::new(pattern, flags);
+        let result      = parser.parse_alternatives()?;
+
+        // The only thing that can stop the parser before the end of the expression is a ')'
+        if parser.chars.peek().is_some() {
+            Err(RegexError::new(parser.position(), RegexErrorKind::UnmatchedParenthesis))
+        } else {
+            Ok(result)
+        }
+    }
+
+    ///
+    /// Returns a pattern that matches the same strings as this one, except that ASCII letters match in either case
+    ///
+    /// Only the letters 'a' to 'z' and 'A' to 'Z' are affected: other characters (such as accented letters) still need to
+    /// match exactly.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let pattern = exactly("café").ascii_case_insensitive();
+    ///
+    /// matches("CAFé", pattern.clone());   // == Some(4)
+    /// matches("CAFÉ", pattern.clone());   // == None
+    /// # assert!(matches("CAFé", pattern.clone()) == Some(4));
+    /// # assert!(matches("CAFÉ", pattern.clone()).is_none());
+    /// ```
+    ///
+    pub fn ascii_case_insensitive(&self) -> Pattern<char> {
+        match self {
+            Epsilon                         => Epsilon,
+            RepeatInfinite(count, pattern)  => RepeatInfinite(*count, Box::new(pattern.ascii_case_insensitive())),
+            Repeat(range, pattern)          => Repeat(range.clone(), Box::new(pattern.ascii_case_insensitive())),
+            MatchAll(patterns)              => MatchAll(patterns.iter().map(|pattern| pattern.ascii_case_insensitive()).collect()),
+            MatchAny(patterns)              => MatchAny(patterns.iter().map(|pattern| pattern.ascii_case_insensitive()).collect()),
+            Capture(id, pattern)            => Capture(*id, Box::new(pattern.ascii_case_insensitive())),
+            StartOfInput                    => StartOfInput,
+            EndOfInput                      => EndOfInput,
+
+            MatchRange(lowest, highest) => {
+                let range = SymbolRange::new(*lowest, *highest);
+                let mut ranges = ascii_other_case(&range);
+                ranges.push(range);
+
+                class_pattern(SymbolRange::join_all(ranges), false)
+            },
+
+            Match(symbols) => {
+                // Runs of characters that aren't letters can stay as a literal
+                let mut sequence    = vec![];
+                let mut literal     = vec![];
+
+                for chr in symbols {
+                    if chr.is_ascii_alphabetic() {
+                        if !literal.is_empty() { sequence.push(Match(literal.split_off(0))); }
+                        sequence.push(MatchAny(vec![Match(vec![chr.to_ascii_lowercase()]), Match(vec![chr.to_ascii_uppercase()])]));
+                    } else {
+                        literal.push(*chr);
+                    }
+                }
+
+                if !literal.is_empty() { sequence.push(Match(literal)); }
+
+                match sequence.len() {
+                    0 => Match(vec![]),
+                    1 => sequence.pop().unwrap(),
+                    _ => MatchAll(sequence)
+                }
+            }
+        }
+    }
+
+    ///
+    /// Returns a pattern that matches the same strings as this one, except that letters match in any case
+    ///
+    /// Unlike `ascii_case_insensitive()`, this uses the Unicode case conversions, so accented and non-Latin letters are
+    /// affected too. Characters that don't have a case are left alone. Case conversions that change the number of
+    /// characters (such as 'ß' to 'SS') aren't used, so these characters still only match themselves.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let pattern = exactly("café").case_insensitive();
+    ///
+    /// matches("CAFÉ", pattern.clone());   // == Some(4)
+    /// # assert!(matches("CAFÉ", pattern.clone()) == Some(4));
+    /// ```
+    ///
+    pub fn case_insensitive(&self) -> Pattern<char> {
+        match self {
+            Epsilon                         => Epsilon,
+            RepeatInfinite(count, pattern)  => RepeatInfinite(*count, Box::new(pattern.case_insensitive())),
+            Repeat(range, pattern)          => Repeat(range.clone(), Box::new(pattern.case_insensitive())),
+            MatchAll(patterns)              => MatchAll(patterns.iter().map(|pattern| pattern.case_insensitive()).collect()),
+            MatchAny(patterns)              => MatchAny(patterns.iter().map(|pattern| pattern.case_insensitive()).collect()),
+            Capture(id, pattern)            => Capture(*id, Box::new(pattern.case_insensitive())),
+            StartOfInput                    => StartOfInput,
+            EndOfInput                      => EndOfInput,
+
+            MatchRange(lowest, highest) => {
+                let range = SymbolRange::new(*lowest, *highest);
+                let mut ranges = other_case(&range);
+                ranges.push(range);
+
+                class_pattern(SymbolRange::join_all(ranges), false)
+            },
+
+            Match(symbols) => {
+                // Runs of characters that don't have a case can stay as a literal
+                let mut sequence    = vec![];
+                let mut literal     = vec![];
+
+                for chr in symbols {
+                    let variants = case_variants(*chr);
+
+                    if variants.len() > 1 {
+                        if !literal.is_empty() { sequence.push(Match(literal.split_off(0))); }
+                        sequence.push(MatchAny(variants.into_iter().map(|variant| Match(vec![variant])).collect()));
+                    } else {
+                        literal.push(*chr);
+                    }
+                }
+
+                if !literal.is_empty() { sequence.push(Match(literal)); }
+
+                match sequence.len() {
+                    0 => Match(vec![]),
+                    1 => sequence.pop().unwrap(),
+                    _ => MatchAll(sequence)
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+    use std::error::Error;
+
+    #[test]
+    fn literal_is_same_as_exactly() {
+        assert!(Pattern::from_regex("abc") == Ok(exactly("abc")));
+    }
+
+    #[test]
+    fn can_match_literal() {
+        let matcher = Pattern::from_regex("abc").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("abc", &matcher) == Some(3));
+        assert!(matches_prepared("abd", &matcher).is_none());
+    }
+
+    #[test]
+    fn special_characters_are_errors() {
+        assert!(Pattern::from_regex("a.").map_err(|err| err.kind) == Err(RegexErrorKind::UnsupportedCharacter('.')));
+    }
+
+    #[test]
+    fn can_match_class() {
+        let matcher = Pattern::from_regex("[a-f]").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("c", &matcher) == Some(1));
+        assert!(matches_prepared("g", &matcher).is_none());
+    }
+
+    #[test]
+    fn class_is_union_of_ranges() {
+        assert!(Pattern::from_regex("[a-zA-Z_]") == Ok(MatchRange('a', 'z').or(MatchRange('A', 'Z')).or(MatchRange('_', '_'))));
+    }
+
+    #[test]
+    fn can_match_negated_class() {
+        let matcher = Pattern::from_regex("x[^0-9]").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("xa", &matcher) == Some(2));
+        assert!(matches_prepared("x\u{10ffff}", &matcher) == Some(2));
+        assert!(matches_prepared("x5", &matcher).is_none());
+    }
+
+    #[test]
+    fn class_with_escapes_and_literal_dashes() {
+        let matcher = Pattern::from_regex(r"[-a\]\-]").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("-", &matcher) == Some(1));
+        assert!(matches_prepared("a", &matcher) == Some(1));
+        assert!(matches_prepared("]", &matcher) == Some(1));
+        assert!(matches_prepared("b", &matcher).is_none());
+
+        let trailing_dash = Pattern::from_regex("[a-]").unwrap().prepare_to_match();
+        assert!(matches_prepared("-", &trailing_dash) == Some(1));
+    }
+
+    #[test]
+    fn bad_classes_are_errors() {
+        assert!(Pattern::from_regex("[abc").map_err(|err| err.kind) == Err(RegexErrorKind::UnterminatedClass));
+        assert!(Pattern::from_regex("[z-a]").map_err(|err| err.kind) == Err(RegexErrorKind::InvalidRange('z', 'a')));
+    }
+
+    #[test]
+    fn can_match_zero_or_more() {
+        let matcher = Pattern::from_regex("ab*c").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("ac", &matcher) == Some(2));
+        assert!(matches_prepared("abbbc", &matcher) == Some(5));
+        assert!(matches_prepared("abxc", &matcher).is_none());
+    }
+
+    #[test]
+    fn can_match_one_or_more_and_optional() {
+        let matcher = Pattern::from_regex("[0-9]+x?").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("123x", &matcher) == Some(4));
+        assert!(matches_prepared("1", &matcher) == Some(1));
+        assert!(matches_prepared("x", &matcher).is_none());
+    }
+
+    #[test]
+    fn can_quantify_group() {
+        let matcher = Pattern::from_regex("(ab|c)+d").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("abcabd", &matcher) == Some(6));
+        assert!(matches_prepared("cd", &matcher) == Some(2));
+        assert!(matches_prepared("d", &matcher).is_none());
+    }
+
+    #[test]
+    fn quantifier_needs_something_to_repeat() {
+        assert!(Pattern::from_regex("*a").map_err(|err| err.kind) == Err(RegexErrorKind::DanglingQuantifier('*')));
+        assert!(Pattern::from_regex("a|+").map_err(|err| err.kind) == Err(RegexErrorKind::DanglingQuantifier('+')));
+    }
+
+    #[test]
+    fn bad_groups_are_errors() {
+        assert!(Pattern::from_regex("a(b") == Err(RegexError::new(1, RegexErrorKind::UnterminatedGroup)));
+        assert!(Pattern::from_regex("a)b").map_err(|err| err.kind) == Err(RegexErrorKind::UnmatchedParenthesis));
+    }
+
+    #[test]
+    fn can_match_counted_range() {
+        let matcher = Pattern::from_regex("a{2,3}").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("aa", &matcher) == Some(2));
+        assert!(matches_prepared("aaa", &matcher) == Some(3));
+        assert!(matches_prepared("a", &matcher).is_none());
+        assert!(matches_prepared("aaaa", &matcher) == Some(3));
+    }
+
+    #[test]
+    fn can_match_fixed_and_open_counts() {
+        let fixed = Pattern::from_regex("a{3}").unwrap().prepare_to_match();
+        let open  = Pattern::from_regex("a{2,}").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("aaaa", &fixed) == Some(3));
+        assert!(matches_prepared("aa", &fixed).is_none());
+        assert!(matches_prepared("aaaaa", &open) == Some(5));
+        assert!(matches_prepared("a", &open).is_none());
+    }
+
+    #[test]
+    fn invalid_counters_are_literals() {
+        assert!(Pattern::from_regex("a{foo}") == Ok(exactly("a{foo}")));
+        assert!(Pattern::from_regex("a{2") == Ok(exactly("a{2")));
+    }
+
+    #[test]
+    fn bad_counters_are_errors() {
+        assert!(Pattern::from_regex("a{4,2}").map_err(|err| err.kind) == Err(RegexErrorKind::InvalidRepetition(4, 2)));
+        assert!(Pattern::from_regex("{2}").map_err(|err| err.kind) == Err(RegexErrorKind::DanglingQuantifier('{')));
+    }
+
+    #[test]
+    fn can_match_digit_escape() {
+        let matcher = Pattern::from_regex(r"\d+").unwrap().prepare_to_match();
+
+        assert!(matches_prepared("2024", &matcher) == Some(4));
+        assert!(matches_prepared("20a4", &matcher) == Some(2));
+        assert!(matches_prepared("a", &matcher).is_none());
+    }
+
+    #[test]
+    fn shorthand_is_same_as_class() {
+        assert!(Pattern::from_regex(r"\d") == Pattern::from_regex("[0-9]"));
+        assert!(Pattern::from_regex(r"\w") == Pattern::from_regex("[a-zA-Z0-9_]"));
+        assert!(Pattern::from_regex(r"\W") == Pattern::from_regex("[^a-zA-Z0-9_]"));
+        assert!(Pattern::from_regex(r"[\d_]") == Pattern::from_regex("[0-9_]"));
+    }
+
+    #[test]
+    fn can_match_whitespace_escapes() {
+        let space       = Pattern::from_regex(r"\s+").unwrap().prepare_to_match();
+        let not_space   = Pattern::from_regex(r"\S+").unwrap().prepare_to_match();
+
+        assert!(matches_prepared(" \t\n x", &space) == Some(4));
+        assert!(matches_prepared("ab c", &not_space) == Some(2));
+        assert!(matches_prepared(" ", &not_space).is_none());
+    }
+
+    #[test]
+    fn can_escape_special_characters() {
+        assert!(Pattern::from_regex(r"\.\*\(\)\\") == Ok(exactly(".*()\\")));
+        assert!(Pattern::from_regex(r"a\nb") == Ok(exactly("a\nb")));
+    }
+
+    #[test]
+    fn bad_escapes_are_errors() {
+        assert!(Pattern::from_regex("a\\").map_err(|err| err.kind) == Err(RegexErrorKind::UnterminatedEscape));
+        assert!(Pattern::from_regex(r"\q").map_err(|err| err.kind) == Err(RegexErrorKind::UnknownEscape('q')));
+    }
+
+    #[test]
+    fn errors_report_position() {
+        assert!(Pattern::from_regex("ab)c").unwrap_err().position == 2);
+        assert!(Pattern::from_regex("a[bc").unwrap_err().position == 1);
+        assert!(Pattern::from_regex("ab[z-a]").unwrap_err() == RegexError::new(3, RegexErrorKind::InvalidRange('z', 'a')));
+        assert!(Pattern::from_regex("a{4,2}").unwrap_err().position == 1);
+        assert!(Pattern::from_regex(r"ab\q").unwrap_err().position == 2);
+
+        // Positions are still counted correctly after reading counters and classes
+        assert!(Pattern::from_regex("a{12}[b-c]{3,1}").unwrap_err().position == 10);
+    }
+
+    #[test]
+    fn errors_can_be_displayed() {
+        let error: Box<dyn Error> = Box::new(Pattern::from_regex("a(b").unwrap_err());
+
+        assert!(error.to_string() == "group is missing a closing ')' at position 1");
+    }
+
+    #[test]
+    fn can_match_case_insensitive_literal() {
+        let matcher = Pattern::from_regex_with_flags("abc", RegexFlags::case_insensitive()).unwrap().prepare_to_match();
+
+        assert!(matches_prepared("abc", &matcher) == Some(3));
+        assert!(matches_prepared("ABC", &matcher) == Some(3));
+        assert!(matches_prepared("AbC", &matcher) == Some(3));
+        assert!(matches_prepared("abd", &matcher).is_none());
+    }
+
+    #[test]
+    fn case_insensitive_classes_match_both_cases() {
+        let matcher = Pattern::from_regex_with_flags("[b-d]+[^x]", RegexFlags::case_insensitive()).unwrap().prepare_to_match();
+
+        assert!(matches_prepared("bCdD1", &matcher) == Some(5));
+        assert!(matches_prepared("bX", &matcher).is_none());
+        assert!(matches_prepared("bx", &matcher).is_none());
+    }
+
+    #[test]
+    fn case_insensitive_unicode_literal() {
+        let matcher = Pattern::from_regex_with_flags("é1", RegexFlags::case_insensitive()).unwrap().prepare_to_match();
+
+        assert!(matches_prepared("É1", &matcher) == Some(2));
+        assert!(matches_prepared("é1", &matcher) == Some(2));
+    }
+
+    #[test]
+    fn case_insensitive_regex_ignores_conversions_to_several_characters() {
+        let sharp_s = Pattern::from_regex_with_flags("ß", RegexFlags::case_insensitive()).unwrap();
+        let dotted  = Pattern::from_regex_with_flags("İ", RegexFlags::case_insensitive()).unwrap();
+
+        assert!(matches("S", sharp_s.clone()).is_none());
+        assert!(matches("ß", sharp_s.clone()) == Some(1));
+        assert!(matches("i", dotted.clone()).is_none());
+        assert!(sharp_s.prepare_to_match().is_equivalent(&exactly("ß").case_insensitive().prepare_to_match()));
+        assert!(dotted.prepare_to_match().is_equivalent(&exactly("İ").case_insensitive().prepare_to_match()));
+    }
+
+    #[test]
+    fn case_sensitive_by_default() {
+        assert!(Pattern::from_regex_with_flags("a1", RegexFlags::default()) == Ok(exactly("a1")));
+        assert!(matches("A", Pattern::from_regex("a").unwrap()).is_none());
+    }
+
+    #[test]
+    fn ascii_case_insensitive_leaves_unicode_alone() {
+        let matcher = exactly("abcé").ascii_case_insensitive().prepare_to_match();
+
+        assert!(matches_prepared("ABCé", &matcher) == Some(4));
+        assert!(matches_prepared("aBcé", &matcher) == Some(4));
+        assert!(matches_prepared("ABCÉ", &matcher).is_none());
+    }
+
+    #[test]
+    fn ascii_case_insensitive_ranges() {
+        let matcher = MatchRange('a', 'c').append(MatchRange('0', '9')).ascii_case_insensitive().prepare_to_match();
+
+        assert!(matches_prepared("B1", &matcher) == Some(2));
+        assert!(matches_prepared("b1", &matcher) == Some(2));
+        assert!(matches_prepared("D1", &matcher).is_none());
+    }
+
+    #[test]
+    fn case_insensitive_matches_any_case() {
+        let abc = exactly("abc").case_insensitive();
+
+        assert!(matches("ABC", abc.clone()) == Some(3));
+        assert!(matches("aBc", abc.clone()) == Some(3));
+        assert!(matches("abd", abc.clone()).is_none());
+    }
+
+    #[test]
+    fn case_insensitive_uses_unicode_cases() {
+        assert!(matches("ÇA", exactly("ça").case_insensitive()) == Some(2));
+        assert!(matches("Ä", MatchRange('à', 'å').case_insensitive()) == Some(1));
+        assert!(matches("ΣΑ", exactly("σα").case_insensitive()) == Some(2));
+    }
+
+    #[test]
+    fn case_insensitive_leaves_caseless_characters_alone() {
+        assert!(exactly("1-2").case_insensitive() == exactly("1-2"));
+        assert!(matches("ß", exactly("ß").case_insensitive()) == Some(1));
+        assert!(matches("SS", exactly("ß").case_insensitive()).is_none());
     }
-}
\ No newline at end of file
+}
@@ -59,7 +59,7 @@ use super::countable::*;
 ///
 /// A Pattern represents a matching pattern in a regular language
 ///
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Pattern<Symbol: Clone> {
     ///
     /// Matches nothing
@@ -99,20 +99,37 @@ pub enum Pattern<Symbol: Clone> {
     ///
     /// Matches any one of a set of patterns
     ///
-    MatchAny(Vec<Pattern<Symbol>>)
+    MatchAny(Vec<Pattern<Symbol>>),
+
+    ///
+    /// Matches a pattern, recording the range of input it matched as the capture group with the specified ID
+    ///
+    /// Captures don't change which strings are matched: a `CaptureMatcher` is needed to find out where they are.
+    ///
+    Capture(usize, Box<Pattern<Symbol>>),
+
+    ///
+    /// Matches the empty string, but only at the very start of the input (see `start_anchor()`)
+    ///
+    StartOfInput,
+
+    ///
+    /// Matches the empty string, but only once there are no more symbols in the input (see `end_anchor()`)
+    ///
+    EndOfInput
 }
 
 impl<Symbol: Clone+Ord+Countable> Pattern<Symbol> {
     ///
     /// Compiles this pattern onto a state machine, returning the accepting symbol
     ///
-    pub fn compile<OutputSymbol>(&self, state_machine: &mut MutableStateMachine<SymbolRange<Symbol>, OutputSymbol>, start_state: StateId) -> StateId {
+    pub fn compile<OutputSymbol>(&self, state_machine: &mut dyn MutableStateMachine<SymbolRange<Symbol>, OutputSymbol>, start_state: StateId) -> StateId {
         match self {
-            &Epsilon => {
+            Epsilon => {
                 start_state
             },
 
-            &Match(ref symbols) => {
+            Match(symbols) => {
                 // Match each symbol in turn
                 let mut current_state = start_state;
 
@@ -125,13 +142,13 @@ impl<Symbol: Clone+Ord+Countable> Pattern<Symbol> {
                 current_state
             },
 
-            &MatchRange(ref first, ref last) => {
+            MatchRange(first, last) => {
                 let next_state = state_machine.count_states();
                 state_machine.add_transition(start_state, SymbolRange::new(first.clone(), last.clone()), next_state);
                 next_state
             },
 
-            &RepeatInfinite(ref count, ref pattern) => {
+            RepeatInfinite(count, pattern) => {
                 // Create a target state
                 let target_state = state_machine.count_states();
                 state_machine.create_state(target_state);
@@ -157,7 +174,7 @@ impl<Symbol: Clone+Ord+Countable> Pattern<Symbol> {
                 target_state
             },
 
-            &Repeat(ref range, ref pattern) => {
+            Repeat(range, pattern) => {
                 // Create a target state
                 let target_state = state_machine.count_states();
                 state_machine.create_state(target_state);
@@ -177,7 +194,7 @@ impl<Symbol: Clone+Ord+Countable> Pattern<Symbol> {
                 target_state
             },
 
-            &MatchAll(ref patterns) => {
+            MatchAll(patterns) => {
                 // Match each pattern in turn
                 let mut current_state = start_state;
 
@@ -189,7 +206,7 @@ impl<Symbol: Clone+Ord+Countable> Pattern<Symbol> {
                 current_state
             },
 
-            &MatchAny(ref patterns) => {
+            MatchAny(patterns) => {
                 // Everything is compiled starting at a particular state, and everything ends on a particular state
                 let target_state = state_machine.count_states();
                 state_machine.create_state(target_state);
@@ -200,15 +217,347 @@ impl<Symbol: Clone+Ord+Countable> Pattern<Symbol> {
                 }
 
                 target_state
+            },
+
+            Capture(_, pattern) => {
+                // State machines don't track captures, so this is just the same as the pattern being captured
+                pattern.compile(state_machine, start_state)
+            },
+
+            StartOfInput | EndOfInput => {
+                // Anchors that can succeed are replaced by `at_start_of_input()` or `at_end_of_input()`, so any that are
+                // left can never match: the target state is left unreachable
+                let target_state = state_machine.count_states();
+                state_machine.create_state(target_state);
+
+                target_state
+            }
+        }
+    }
+}
+
+impl<Symbol: Clone> Pattern<Symbol> {
+    ///
+    /// Marks this pattern as a capture group, so a `CaptureMatcher` will report the range of input that it matched
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let key_value = MatchRange('a', 'z').repeat_forever(1).append("=").append(MatchRange('0', '9').repeat_forever(1).capture(1));
+    /// # assert!(matches("key=42", key_value) == Some(6));
+    /// ```
+    ///
+    pub fn capture(self, id: usize) -> Pattern<Symbol> {
+        Capture(id, Box::new(self))
+    }
+
+    ///
+    /// Returns true if this pattern contains a `start_anchor()`
+    ///
+    pub fn has_start_anchor(&self) -> bool {
+        match self {
+            StartOfInput                    => true,
+            EndOfInput                      => false,
+            Epsilon                         => false,
+            Match(_)                        => false,
+            MatchRange(_, _)                => false,
+            RepeatInfinite(_, pattern)      => pattern.has_start_anchor(),
+            Repeat(_, pattern)              => pattern.has_start_anchor(),
+            Capture(_, pattern)             => pattern.has_start_anchor(),
+            MatchAll(patterns)              => patterns.iter().any(|pattern| pattern.has_start_anchor()),
+            MatchAny(patterns)              => patterns.iter().any(|pattern| pattern.has_start_anchor())
+        }
+    }
+
+    ///
+    /// Returns true if this pattern contains an `end_anchor()`
+    ///
+    pub fn has_end_anchor(&self) -> bool {
+        match self {
+            EndOfInput                      => true,
+            StartOfInput                    => false,
+            Epsilon                         => false,
+            Match(_)                        => false,
+            MatchRange(_, _)                => false,
+            RepeatInfinite(_, pattern)      => pattern.has_end_anchor(),
+            Repeat(_, pattern)              => pattern.has_end_anchor(),
+            Capture(_, pattern)             => pattern.has_end_anchor(),
+            MatchAll(patterns)              => patterns.iter().any(|pattern| pattern.has_end_anchor()),
+            MatchAny(patterns)              => patterns.iter().any(|pattern| pattern.has_end_anchor())
+        }
+    }
+
+    ///
+    /// Returns true if this pattern can't match anything other than the empty string
+    ///
+    fn is_zero_width(&self) -> bool {
+        match self {
+            StartOfInput                    => true,
+            EndOfInput                      => true,
+            Epsilon                         => true,
+            Match(symbols)                  => symbols.is_empty(),
+            MatchRange(_, _)                => false,
+            RepeatInfinite(_, pattern)      => pattern.is_zero_width(),
+            Repeat(_, pattern)              => pattern.is_zero_width(),
+            Capture(_, pattern)             => pattern.is_zero_width(),
+            MatchAll(patterns)              => patterns.iter().all(|pattern| pattern.is_zero_width()),
+            MatchAny(patterns)              => patterns.iter().all(|pattern| pattern.is_zero_width())
+        }
+    }
+
+    ///
+    /// Returns a pattern that matches the empty string wherever this pattern can, or None if this pattern never matches the
+    /// empty string
+    ///
+    /// Any anchors that the empty match has to pass through are kept, so the result only matches where they succeed.
+    ///
+    fn empty_match(&self) -> Option<Pattern<Symbol>> {
+        match self {
+            StartOfInput                    => Some(StartOfInput),
+            EndOfInput                      => Some(EndOfInput),
+            Epsilon                         => Some(Epsilon),
+            Match(symbols)                  => if symbols.is_empty() { Some(Epsilon) } else { None },
+            MatchRange(_, _)                => None,
+            RepeatInfinite(count, pattern)  => if *count == 0 { Some(Epsilon) } else { pattern.empty_match() },
+            Capture(id, pattern)            => pattern.empty_match().map(|pattern| Capture(*id, Box::new(pattern))),
+
+            Repeat(range, pattern) => {
+                if range.end <= range.start     { None }
+                else if range.start == 0        { Some(Epsilon) }
+                else                            { pattern.empty_match() }
+            },
+
+            MatchAll(patterns) => {
+                patterns.iter()
+                    .map(|pattern| pattern.empty_match())
+                    .collect::<Option<Vec<_>>>()
+                    .map(MatchAll)
+            },
+
+            MatchAny(patterns) => {
+                let empty_matches = patterns.iter().filter_map(|pattern| pattern.empty_match()).collect::<Vec<_>>();
+
+                if empty_matches.is_empty() { None } else { Some(MatchAny(empty_matches)) }
+            }
+        }
+    }
+
+    ///
+    /// Returns the pattern to use for a sequence of patterns that begins at the very start of the input
+    ///
+    fn sequence_at_start_of_input(patterns: &[Pattern<Symbol>]) -> Pattern<Symbol> {
+        let mut result = vec![];
+
+        for (index, pattern) in patterns.iter().enumerate() {
+            let at_start    = pattern.at_start_of_input();
+            let rest        = &patterns[index+1..];
+
+            if pattern.is_zero_width() {
+                // We're still at the start of the input after a pattern that can only match the empty string
+                result.push(at_start);
+                continue;
+            }
+
+            let empty_match = if rest.iter().any(|pattern| pattern.has_start_anchor()) { at_start.empty_match() } else { None };
+            result.push(at_start);
+            result.extend(rest.iter().cloned());
+
+            if let Some(empty_match) = empty_match {
+                // If this pattern matches the empty string then the rest of the sequence is still at the start of the input
+                let mut still_at_start = result[0..index].to_vec();
+                still_at_start.push(empty_match);
+                still_at_start.push(Self::sequence_at_start_of_input(rest));
+
+                return MatchAny(vec![MatchAll(result), MatchAll(still_at_start)]);
+            }
+
+            break;
+        }
+
+        MatchAll(result)
+    }
+
+    ///
+    /// Returns the pattern to use for matches that begin at the very start of the input
+    ///
+    /// The start anchors that are able to succeed there are replaced with `Epsilon`. Any others are left alone, and will
+    /// never match.
+    ///
+    pub fn at_start_of_input(&self) -> Pattern<Symbol> {
+        if !self.has_start_anchor() {
+            return self.clone();
+        }
+
+        match self {
+            StartOfInput                    => Epsilon,
+            EndOfInput                      => EndOfInput,
+            Epsilon                         => Epsilon,
+            Match(symbols)                  => Match(symbols.clone()),
+            MatchRange(first, last)         => MatchRange(first.clone(), last.clone()),
+            Capture(id, pattern)            => Capture(*id, Box::new(pattern.at_start_of_input())),
+            MatchAny(patterns)              => MatchAny(patterns.iter().map(|pattern| pattern.at_start_of_input()).collect()),
+
+            MatchAll(patterns)              => Self::sequence_at_start_of_input(patterns),
+
+            RepeatInfinite(count, pattern) => {
+                // Only the first repetition is at the start of the input, unless it matches the empty string
+                let first   = pattern.at_start_of_input();
+                let rest    = RepeatInfinite(count.saturating_sub(1), pattern.clone());
+
+                if *count == 0 {
+                    // Repetitions that match the empty string can be left out
+                    MatchAny(vec![MatchAll(vec![first, rest]), Epsilon])
+                } else if let Some(empty_match) = first.empty_match() {
+                    let rest_at_start = rest.at_start_of_input();
+                    MatchAny(vec![MatchAll(vec![first, rest]), MatchAll(vec![empty_match, rest_at_start])])
+                } else {
+                    MatchAll(vec![first, rest])
+                }
+            },
+
+            Repeat(range, pattern) => {
+                if range.end <= range.start || range.end == 0 {
+                    // Empty ranges don't match anything
+                    Repeat(range.clone(), pattern.clone())
+                } else {
+                    let first   = pattern.at_start_of_input();
+                    let rest    = Repeat(range.start.saturating_sub(1)..(range.end-1), pattern.clone());
+
+                    if range.start == 0 {
+                        MatchAny(vec![MatchAll(vec![first, rest]), Epsilon])
+                    } else if let Some(empty_match) = first.empty_match() {
+                        let rest_at_start = rest.at_start_of_input();
+                        MatchAny(vec![MatchAll(vec![first, rest]), MatchAll(vec![empty_match, rest_at_start])])
+                    } else {
+                        MatchAll(vec![first, rest])
+                    }
+                }
+            }
+        }
+    }
+
+    ///
+    /// Returns the pattern to use for a sequence of patterns that ends at the very end of the input
+    ///
+    fn sequence_at_end_of_input(patterns: &[Pattern<Symbol>]) -> Pattern<Symbol> {
+        let mut result = vec![];
+
+        for (index, pattern) in patterns.iter().enumerate().rev() {
+            let at_end  = pattern.at_end_of_input();
+            let before  = &patterns[0..index];
+
+            if pattern.is_zero_width() {
+                // We're still at the end of the input before a pattern that can only match the empty string
+                result.push(at_end);
+                continue;
+            }
+
+            let empty_match = if before.iter().any(|pattern| pattern.has_end_anchor()) { at_end.empty_match() } else { None };
+            result.push(at_end);
+            result.extend(before.iter().rev().cloned());
+            result.reverse();
+
+            if let Some(empty_match) = empty_match {
+                // If this pattern matches the empty string then the start of the sequence is still at the end of the input
+                let mut still_at_end = vec![Self::sequence_at_end_of_input(before), empty_match];
+                still_at_end.extend(result[index+1..].iter().cloned());
+
+                return MatchAny(vec![MatchAll(result), MatchAll(still_at_end)]);
+            }
+
+            return MatchAll(result);
+        }
+
+        result.reverse();
+        MatchAll(result)
+    }
+
+    ///
+    /// Returns the pattern to use for matches that end at the very end of the input
+    ///
+    /// The end anchors that are able to succeed there are replaced with `Epsilon`. Any others are left alone, and will
+    /// never match.
+    ///
+    pub fn at_end_of_input(&self) -> Pattern<Symbol> {
+        if !self.has_end_anchor() {
+            return self.clone();
+        }
+
+        match self {
+            EndOfInput                      => Epsilon,
+            StartOfInput                    => StartOfInput,
+            Epsilon                         => Epsilon,
+            Match(symbols)                  => Match(symbols.clone()),
+            MatchRange(first, last)         => MatchRange(first.clone(), last.clone()),
+            Capture(id, pattern)            => Capture(*id, Box::new(pattern.at_end_of_input())),
+            MatchAny(patterns)              => MatchAny(patterns.iter().map(|pattern| pattern.at_end_of_input()).collect()),
+
+            MatchAll(patterns)              => Self::sequence_at_end_of_input(patterns),
+
+            RepeatInfinite(count, pattern) => {
+                // Only the last repetition is at the end of the input, unless it matches the empty string
+                let rest    = RepeatInfinite(count.saturating_sub(1), pattern.clone());
+                let last    = pattern.at_end_of_input();
+
+                if *count == 0 {
+                    // Repetitions that match the empty string can be left out
+                    MatchAny(vec![MatchAll(vec![rest, last]), Epsilon])
+                } else if let Some(empty_match) = last.empty_match() {
+                    let rest_at_end = rest.at_end_of_input();
+                    MatchAny(vec![MatchAll(vec![rest, last]), MatchAll(vec![rest_at_end, empty_match])])
+                } else {
+                    MatchAll(vec![rest, last])
+                }
+            },
+
+            Repeat(range, pattern) => {
+                if range.end <= range.start || range.end == 0 {
+                    // Empty ranges don't match anything
+                    Repeat(range.clone(), pattern.clone())
+                } else {
+                    let rest    = Repeat(range.start.saturating_sub(1)..(range.end-1), pattern.clone());
+                    let last    = pattern.at_end_of_input();
+
+                    if range.start == 0 {
+                        MatchAny(vec![MatchAll(vec![rest, last]), Epsilon])
+                    } else if let Some(empty_match) = last.empty_match() {
+                        let rest_at_end = rest.at_end_of_input();
+                        MatchAny(vec![MatchAll(vec![rest, last]), MatchAll(vec![rest_at_end, empty_match])])
+                    } else {
+                        MatchAll(vec![rest, last])
+                    }
+                }
             }
         }
     }
+
+    ///
+    /// Creates a pattern that matches the reverse of every string matched by this pattern
+    ///
+    /// This can be used with a `ReversedSymbolReader` to find patterns that are easiest to recognise from the end of the
+    /// input.
+    ///
+    pub fn reversed(&self) -> Pattern<Symbol> {
+        match self {
+            Epsilon                         => Epsilon,
+            Match(symbols)                  => Match(symbols.iter().rev().cloned().collect()),
+            MatchRange(first, last)         => MatchRange(first.clone(), last.clone()),
+            RepeatInfinite(count, pattern)  => RepeatInfinite(*count, Box::new(pattern.reversed())),
+            Repeat(range, pattern)          => Repeat(range.clone(), Box::new(pattern.reversed())),
+            MatchAll(patterns)              => MatchAll(patterns.iter().rev().map(|pattern| pattern.reversed()).collect()),
+            MatchAny(patterns)              => MatchAny(patterns.iter().map(|pattern| pattern.reversed()).collect()),
+            Capture(id, pattern)            => Capture(*id, Box::new(pattern.reversed())),
+
+            // The start of the input becomes the end once it's reversed
+            StartOfInput                    => EndOfInput,
+            EndOfInput                      => StartOfInput
+        }
+    }
 }
 
 impl<Symbol: Clone+Ord+Countable+'static> ToNdfa<SymbolRange<Symbol>> for Pattern<Symbol> {
-    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<StateMachine<SymbolRange<Symbol>, OutputSymbol>> {
+    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<dyn StateMachine<SymbolRange<Symbol>, OutputSymbol>> {
         let mut result  = Ndfa::new();
-        let end_state   = self.compile(&mut result, 0);
+        let end_state   = self.simplify().compile(&mut result, 0);
 
         result.set_output_symbol(end_state, output);
         result.fix_overlapping_ranges();
@@ -217,20 +566,20 @@ impl<Symbol: Clone+Ord+Countable+'static> ToNdfa<SymbolRange<Symbol>> for Patter
     }
 }
 
-impl<Symbol: Clone+Ord+Countable+'static> ToNdfa<SymbolRange<Symbol>> for ToPattern<Symbol> {
-    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<StateMachine<SymbolRange<Symbol>, OutputSymbol>> {
+impl<Symbol: Clone+Ord+Countable+'static> ToNdfa<SymbolRange<Symbol>> for dyn ToPattern<Symbol> {
+    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<dyn StateMachine<SymbolRange<Symbol>, OutputSymbol>> {
         self.to_pattern().to_ndfa(output)
     }
 }
 
 impl ToNdfa<SymbolRange<char>> for str {
-    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<StateMachine<SymbolRange<char>, OutputSymbol>> {
+    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<dyn StateMachine<SymbolRange<char>, OutputSymbol>> {
         self.to_pattern().to_ndfa(output)
     }
 }
 
 impl<Symbol: Clone+Ord+Countable+'static> ToNdfa<SymbolRange<Symbol>> for [Symbol] {
-    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<StateMachine<SymbolRange<Symbol>, OutputSymbol>> {
+    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<dyn StateMachine<SymbolRange<Symbol>, OutputSymbol>> {
         self.to_pattern().to_ndfa(output)
     }
 }
@@ -245,6 +594,13 @@ pub trait ToPattern<Symbol: Clone> {
     /// Converts a particular object into a pattern that will match it
     ///
     fn to_pattern(&self) -> Pattern<Symbol>;
+
+    ///
+    /// Creates a pattern that matches either this object or the empty string
+    ///
+    fn optional(&self) -> Pattern<Symbol> {
+        self.to_pattern().optional()
+    }
 }
 
 ///
@@ -271,21 +627,21 @@ impl<Symbol: Clone> IntoPattern<Symbol> for Box<Pattern<Symbol>> {
     }
 }
 
-impl<'a, Symbol: Clone, PatternType: ToPattern<Symbol>> IntoPattern<Symbol> for &'a PatternType {
+impl<Symbol: Clone, PatternType: ToPattern<Symbol>> IntoPattern<Symbol> for &PatternType {
     #[inline]
     fn into_pattern(self) -> Pattern<Symbol> {
         self.to_pattern()
     }
 }
 
-impl<'a, Symbol: Clone> IntoPattern<Symbol> for &'a [Symbol] {
+impl<Symbol: Clone> IntoPattern<Symbol> for &[Symbol] {
     #[inline]
     fn into_pattern(self) -> Pattern<Symbol> {
         self.to_pattern()
     }
 }
 
-impl<'a> IntoPattern<char> for &'a str {
+impl IntoPattern<char> for &str {
     #[inline]
     fn into_pattern(self) -> Pattern<char> {
         self.to_pattern()
@@ -320,6 +676,25 @@ impl<Symbol: Clone> ToPattern<Symbol> for [Symbol] {
     }
 }
 
+impl<Symbol: Clone> ToPattern<Symbol> for &[Symbol] {
+    #[inline]
+    fn to_pattern(&self) -> Pattern<Symbol> {
+        Match(self.to_vec())
+    }
+}
+
+impl<Symbol: Clone+Ord+Countable> ToPattern<Symbol> for WrappingRange<Symbol> {
+    fn to_pattern(&self) -> Pattern<Symbol> {
+        let mut ranges = self.to_ranges().into_iter().map(|range| MatchRange(range.lowest, range.highest));
+        let first      = ranges.next().unwrap();
+
+        match ranges.next() {
+            Some(second)    => MatchAny(vec![first, second]),
+            None            => first
+        }
+    }
+}
+
 impl ToPattern<char> for str {
     #[inline]
     fn to_pattern(&self) -> Pattern<char> {
@@ -338,6 +713,13 @@ pub trait PatternTransformer<Symbol: Clone> {
 
     /// Repeats the current pattern for a certain number of iterations
     fn repeat(self, count: Range<u32>) -> Pattern<Symbol>;
+
+    /// Matches either the current pattern or the empty string
+    fn optional(self) -> Pattern<Symbol>;
+
+    /// Repeats the current pattern between `min` and `max` times (inclusive). Panics if `min` is greater than `max`.
+    /// A maximum of `u32::MAX` has no upper limit, the same as `repeat_forever(min)`.
+    fn repeat_between(self, min: u32, max: u32) -> Pattern<Symbol>;
 }
 
 ///
@@ -348,6 +730,160 @@ pub fn exactly<Symbol: Clone, PatternType: IntoPattern<Symbol>>(item: PatternTyp
     item.into_pattern()
 }
 
+///
+/// Creates a pattern that matches an exact sequence of symbols
+///
+/// This is useful for alphabets other than `char`, such as bytes or the output of another tokenizer.
+///
+/// ```
+/// # use concordance::*;
+/// let elf_magic = exactly_symbols(&[0x7fu8, b'E', b'L', b'F']);
+///
+/// matches(&vec![0x7f, b'E', b'L', b'F', 2], elf_magic); // == Some(4)
+/// # assert!(matches(&vec![0x7f, b'E', b'L', b'F', 2], exactly_symbols(&[0x7fu8, b'E', b'L', b'F'])) == Some(4));
+/// ```
+///
+#[inline]
+pub fn exactly_symbols<Symbol: Clone>(symbols: &[Symbol]) -> Pattern<Symbol> {
+    Match(symbols.to_vec())
+}
+
+///
+/// Creates a pattern that matches the empty string, but only at the very start of the input
+///
+/// `matches()` always matches from the start of the input, so this is mostly useful for searches such as `find()` or
+/// for the patterns in a `TokenMatcher`, where it stops a pattern from matching anywhere else.
+///
+/// An anchor only succeeds if everything before it in the pattern has matched the empty string: `"x".optional()`
+/// followed by an anchor succeeds when the 'x' is skipped. Inside a repetition, this means that the anchor can only
+/// succeed during the first repetition: `start_anchor().append("ab").repeat_forever(1)` matches 'abab' at the start of
+/// the input, but nothing after that.
+///
+/// ```
+/// # use concordance::*;
+/// let leading_ab = start_anchor().append(exactly("ab"));
+///
+/// find("abab", leading_ab.clone());   // == Some(0..2)
+/// find("xab", leading_ab.clone());    // == None
+/// # assert!(find("abab", leading_ab.clone()) == Some(0..2));
+/// # assert!(find("xab", leading_ab.clone()).is_none());
+/// ```
+///
+#[inline]
+pub fn start_anchor<Symbol: Clone>() -> Pattern<Symbol> {
+    StartOfInput
+}
+
+///
+/// Creates a pattern that matches the empty string, but only when there are no more symbols in the input
+///
+/// This can be used to require that a pattern consumes the rest of the input: without it, `matches()` and `find()` report
+/// the longest match even if there are more symbols after it.
+///
+/// An anchor only succeeds if everything after it in the pattern matches the empty string: `end_anchor()` followed by
+/// `"y".optional()` succeeds when the 'y' is skipped. Inside a repetition, this means that the anchor can only succeed
+/// during the last repetition.
+///
+/// ```
+/// # use concordance::*;
+/// let whole_ab = exactly("ab").append(end_anchor());
+///
+/// matches("ab", whole_ab.clone());    // == Some(2)
+/// matches("abc", whole_ab.clone());   // == None
+/// # assert!(matches("ab", whole_ab.clone()) == Some(2));
+/// # assert!(matches("abc", whole_ab.clone()).is_none());
+/// ```
+///
+#[inline]
+pub fn end_anchor<Symbol: Clone>() -> Pattern<Symbol> {
+    EndOfInput
+}
+
+///
+/// Creates a pattern that matches any one of a set of patterns
+///
+/// Literals that start with the same symbols are combined so that their common prefix is only matched once: for
+/// example, `any_of(["cat", "car"])` produces the same pattern as `exactly("ca").append(exactly("t").or("r"))`.
+///
+/// ```
+/// # use concordance::*;
+/// let keyword = any_of(["if", "else", "while"]);
+///
+/// matches("else", keyword.clone());   // == Some(4)
+/// matches("elif", keyword.clone());   // == None
+/// # assert!(matches("else", keyword.clone()) == Some(4));
+/// # assert!(matches("elif", keyword.clone()).is_none());
+/// ```
+///
+pub fn any_of<Symbol, PatternType, Patterns>(patterns: Patterns) -> Pattern<Symbol>
+where   Symbol: Clone+PartialEq
+,       PatternType: IntoPattern<Symbol>
+,       Patterns: IntoIterator<Item=PatternType> {
+    let mut literals        = vec![];
+    let mut alternatives    = vec![];
+
+    for pattern in patterns {
+        match pattern.into_pattern() {
+            Match(symbols)  => if !literals.contains(&symbols) { literals.push(symbols) },
+            pattern         => alternatives.push(pattern)
+        }
+    }
+
+    let mut result = shared_prefix_alternatives(literals);
+    result.extend(alternatives);
+
+    if result.len() == 1 {
+        result.pop().unwrap()
+    } else {
+        MatchAny(result)
+    }
+}
+
+///
+/// Turns a set of distinct literals into a set of alternative patterns where literals with the same first symbol are
+/// combined into a single pattern that matches their common prefix first
+///
+fn shared_prefix_alternatives<Symbol: Clone+PartialEq>(literals: Vec<Vec<Symbol>>) -> Vec<Pattern<Symbol>> {
+    let mut result      = vec![];
+    let mut remaining   = literals;
+
+    while !remaining.is_empty() {
+        let first = remaining.remove(0);
+
+        if first.is_empty() {
+            result.push(Epsilon);
+            continue;
+        }
+
+        // Find the other literals that start with the same symbol
+        let (mut group, rest): (Vec<_>, Vec<_>) = remaining.into_iter().partition(|literal| literal.first() == Some(&first[0]));
+        remaining = rest;
+
+        if group.is_empty() {
+            result.push(Match(first));
+            continue;
+        }
+
+        group.insert(0, first);
+
+        // Match the prefix that all of the literals share, followed by the alternatives for the rest of them
+        let mut prefix_len = 1;
+        while group.iter().all(|literal| literal.len() > prefix_len && literal[prefix_len] == group[0][prefix_len]) {
+            prefix_len += 1;
+        }
+
+        let prefix      = group[0][0..prefix_len].to_vec();
+        let suffixes    = group.into_iter().map(|literal| literal[prefix_len..].to_vec()).collect();
+
+        let mut tails   = shared_prefix_alternatives(suffixes);
+        let tail        = if tails.len() == 1 { tails.pop().unwrap() } else { MatchAny(tails) };
+
+        result.push(Match(prefix).append(tail));
+    }
+
+    result
+}
+
 ///
 /// Implemented by things that combine patterns together to create new patterns
 ///
@@ -367,6 +903,21 @@ impl<Symbol: Clone> PatternTransformer<Symbol> for Pattern<Symbol> {
     fn repeat(self, count: Range<u32>) -> Pattern<Symbol> {
         Repeat(count, Box::new(self))
     }
+
+    fn optional(self) -> Pattern<Symbol> {
+        // The epsilon alternative compiles to a branch that skips this pattern
+        MatchAny(vec![self, Epsilon])
+    }
+
+    fn repeat_between(self, min: u32, max: u32) -> Pattern<Symbol> {
+        assert!(min <= max, "repeat_between() needs a minimum ({}) that isn't greater than the maximum ({})", min, max);
+
+        // Repeat compiles to the required copies followed by the optional ones (its range can't include u32::MAX itself)
+        match max.checked_add(1) {
+            Some(end)   => Repeat(min..end, Box::new(self)),
+            None        => self.repeat_forever(min)
+        }
+    }
 }
 
 impl<Symbol: Clone, SecondPatternType: IntoPattern<Symbol>> PatternCombiner<Symbol, SecondPatternType> for Pattern<Symbol> {
@@ -378,10 +929,10 @@ impl<Symbol: Clone, SecondPatternType: IntoPattern<Symbol>> PatternCombiner<Symb
         // Combination rules depend on what the patterns are
         match (first_pattern, second_pattern) {
             // Combining 'Match(x)' and 'Match(y)' should produce 'Match(xy)'
-            (Match(first_string), Match(second_string)) => Match(first_string.into_iter().chain(second_string.into_iter()).collect()),
+            (Match(first_string), Match(second_string)) => Match(first_string.into_iter().chain(second_string).collect()),
 
             // Combining 'MatchAll(x)' and 'MatchAll(y)' should produce 'MatchAll(xy)'
-            (MatchAll(first_string), MatchAll(second_string)) => MatchAll(first_string.into_iter().chain(second_string.into_iter()).collect()),
+            (MatchAll(first_string), MatchAll(second_string)) => MatchAll(first_string.into_iter().chain(second_string).collect()),
 
             // Combining 'MatchAll(x)' and 'y' should produce 'MatchAll(xy)'
             (MatchAll(first_string), second) => {
@@ -412,7 +963,7 @@ impl<Symbol: Clone, SecondPatternType: IntoPattern<Symbol>> PatternCombiner<Symb
         // Combination rules depend on what the patterns are
         match (first_pattern, second_pattern) {
             // Combining 'MatchAny(x)' and 'MatchAny(y)' should produce 'MatchAny(xy)'
-            (MatchAny(first_string), MatchAny(second_string)) => MatchAny(first_string.into_iter().chain(second_string.into_iter()).collect()),
+            (MatchAny(first_string), MatchAny(second_string)) => MatchAny(first_string.into_iter().chain(second_string).collect()),
 
             // Everything else is just MatchAny(xy)
             (first, second) => MatchAny(vec![first, second])
@@ -420,10 +971,151 @@ impl<Symbol: Clone, SecondPatternType: IntoPattern<Symbol>> PatternCombiner<Symb
     }
 }
 
+impl<Symbol: Clone+Ord+Countable> Pattern<Symbol> {
+    ///
+    /// For a pattern that matches a single symbol (a `MatchRange`, a single-symbol `Match` or a `MatchAny` made up of these),
+    /// returns a new pattern that matches the same symbols except for those in the specified list
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let lowercase_except_q = MatchRange('a', 'z').excluding(&['q']).unwrap();
+    /// # assert!(matches("p", lowercase_except_q.clone()) == Some(1));
+    /// # assert!(matches("q", lowercase_except_q.clone()).is_none());
+    /// ```
+    ///
+    /// This returns `None` if the pattern can match anything other than a single symbol, or if every symbol it matches is
+    /// excluded (so the result would never match anything).
+    ///
+    pub fn excluding(&self, symbols: &[Symbol]) -> Option<Pattern<Symbol>> {
+        // Work out the ranges matched by this pattern, then remove the symbols from them
+        let mut ranges = self.single_symbol_ranges()?;
+
+        for symbol in symbols {
+            let without = SymbolRange::new(symbol.clone(), symbol.clone());
+
+            ranges = ranges.into_iter()
+                .flat_map(|range| range.difference(&without))
+                .collect();
+        }
+
+        // Generate the resulting pattern
+        let mut patterns: Vec<Pattern<Symbol>> = ranges.into_iter()
+            .map(|range| MatchRange(range.lowest, range.highest))
+            .collect();
+
+        match patterns.len() {
+            0 => None,
+            1 => patterns.pop(),
+            _ => Some(MatchAny(patterns))
+        }
+    }
+
+    ///
+    /// Returns a simpler pattern that matches the same strings as this one
+    ///
+    /// Alternatives that match a single symbol are joined into as few ranges as possible, alternatives that are repeated
+    /// are removed and nested sequences and alternatives are flattened. This makes the NDFA generated for patterns built
+    /// from many calls to `or()` smaller.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let pattern = MatchRange('a', 'c').or(MatchRange('d', 'f'));
+    ///
+    /// pattern.simplify(); // == MatchRange('a', 'f')
+    /// # assert!(pattern.simplify() == MatchRange('a', 'f'));
+    /// ```
+    ///
+    pub fn simplify(&self) -> Pattern<Symbol> {
+        match self {
+            Epsilon                         => Epsilon,
+            Match(symbols)                  => Match(symbols.clone()),
+            MatchRange(first, last)         => MatchRange(first.clone(), last.clone()),
+            RepeatInfinite(count, pattern)  => RepeatInfinite(*count, Box::new(pattern.simplify())),
+            Repeat(range, pattern)          => Repeat(range.clone(), Box::new(pattern.simplify())),
+            MatchAll(patterns)              => Self::simplify_sequence(patterns),
+            MatchAny(patterns)              => Self::simplify_alternatives(patterns),
+            Capture(id, pattern)            => Capture(*id, Box::new(pattern.simplify())),
+            StartOfInput                    => StartOfInput,
+            EndOfInput                      => EndOfInput
+        }
+    }
+
+    ///
+    /// Simplifies a pattern that matches a sequence of patterns
+    ///
+    fn simplify_sequence(patterns: &[Pattern<Symbol>]) -> Pattern<Symbol> {
+        let mut result = vec![];
+
+        for pattern in patterns.iter().map(|pattern| pattern.simplify()) {
+            match pattern {
+                Epsilon                 => { },
+                MatchAll(patterns)      => result.extend(patterns),
+                pattern                 => result.push(pattern)
+            }
+        }
+
+        match result.len() {
+            0 => Epsilon,
+            1 => result.pop().unwrap(),
+            _ => MatchAll(result)
+        }
+    }
+
+    ///
+    /// Simplifies a pattern that matches any of a set of patterns
+    ///
+    fn simplify_alternatives(patterns: &[Pattern<Symbol>]) -> Pattern<Symbol> {
+        let mut ranges          = vec![];
+        let mut alternatives    = vec![];
+
+        for pattern in patterns.iter().map(|pattern| pattern.simplify()) {
+            let nested = match pattern {
+                MatchAny(patterns)  => patterns,
+                pattern             => vec![pattern]
+            };
+
+            for pattern in nested {
+                match pattern {
+                    MatchRange(first, last)                 => ranges.push(SymbolRange::new(first, last)),
+                    Match(ref symbols) if symbols.len() == 1 => ranges.push(SymbolRange::new(symbols[0].clone(), symbols[0].clone())),
+                    pattern                                 => if !alternatives.contains(&pattern) { alternatives.push(pattern) }
+                }
+            }
+        }
+
+        // Single-symbol alternatives come first, as they are likely to be the most common
+        let mut result: Vec<_> = SymbolRange::join_all(ranges).into_iter()
+            .map(|range| if range.lowest == range.highest { Match(vec![range.lowest]) } else { MatchRange(range.lowest, range.highest) })
+            .collect();
+        result.extend(alternatives);
+
+        if result.len() == 1 {
+            result.pop().unwrap()
+        } else {
+            MatchAny(result)
+        }
+    }
+
+    ///
+    /// Returns the ranges matched by a pattern that only matches a single symbol
+    ///
+    fn single_symbol_ranges(&self) -> Option<Vec<SymbolRange<Symbol>>> {
+        match self {
+            MatchRange(first, last)                 => Some(vec![SymbolRange::new(first.clone(), last.clone())]),
+            Match(symbols) if symbols.len() == 1    => Some(vec![SymbolRange::new(symbols[0].clone(), symbols[0].clone())]),
+            MatchAny(patterns)                      => {
+                let ranges = patterns.iter().map(|pattern| pattern.single_symbol_ranges()).collect::<Option<Vec<_>>>()?;
+                Some(ranges.into_iter().flatten().collect())
+            },
+            _                                       => None
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use super::super::state_machine::*;
+    use super::super::matches::*;
 
     #[test]
     fn can_convert_vec_to_pattern() {
@@ -439,6 +1131,43 @@ mod test {
         assert!(pattern == Match(vec![0, 1, 2]));
     }
 
+    #[test]
+    fn can_convert_byte_slice_to_pattern() {
+        let bytes: &[u8]    = &[1, 2, 3];
+        let pattern         = bytes.to_pattern();
+
+        assert!(pattern == Match(vec![1, 2, 3]));
+        assert!(matches(&vec![1u8, 2, 3, 4], bytes.to_pattern()) == Some(3));
+        assert!(matches(&vec![1u8, 2, 4], bytes.to_pattern()).is_none());
+    }
+
+    #[test]
+    fn can_reverse_literal() {
+        let reversed = exactly("ing").reversed();
+
+        assert!(reversed == exactly("gni"));
+        assert!(matches("gni", reversed.clone()) == Some(3));
+        assert!(matches("ing", reversed).is_none());
+    }
+
+    #[test]
+    fn can_reverse_sequence() {
+        let reversed = exactly("ab").append(MatchRange('0', '9').repeat_forever(1)).or("xyz").reversed();
+
+        assert!(matches("123ba", reversed.clone()) == Some(5));
+        assert!(matches("zyx", reversed.clone()) == Some(3));
+        assert!(matches("ab1", reversed).is_none());
+    }
+
+    #[test]
+    fn can_match_wrapping_range() {
+        let clock = SymbolRange::wrapping(10u8, 1u8, 12u8).to_pattern().repeat_forever(1);
+
+        assert!(matches(&vec![10u8, 11, 0, 1], clock.clone()) == Some(4));
+        assert!(matches(&vec![11u8, 5], clock.clone()) == Some(1));
+        assert!(matches(&vec![5u8], clock).is_none());
+    }
+
     #[test]
     fn can_convert_string_to_pattern() {
         let pattern = exactly("abc");
@@ -453,6 +1182,177 @@ mod test {
         assert!(pattern == Repeat(1..2, Box::new(Match(vec!['a', 'b', 'c']))));
     }
 
+    #[test]
+    fn optional_pattern_can_be_skipped() {
+        let pattern = exactly("ab").optional();
+
+        assert!(pattern == MatchAny(vec![Match(vec!['a', 'b']), Epsilon]));
+        assert!(matches("", pattern.clone()) == Some(0));
+        assert!(matches("ab", pattern.clone()) == Some(2));
+
+        // Only the empty prefix of 'a' is matched
+        assert!(matches("a", pattern.clone()) == Some(0));
+        assert!(matches("xab", "x".optional().append("ab")) == Some(3));
+    }
+
+    #[test]
+    fn repeat_between_is_inclusive() {
+        let pattern = MatchRange('0', '9').repeat_between(2, 3);
+
+        assert!(pattern == Repeat(2..4, Box::new(MatchRange('0', '9'))));
+        assert!(matches("12", pattern.clone()) == Some(2));
+        assert!(matches("123", pattern.clone()) == Some(3));
+        assert!(matches("1", pattern.clone()).is_none());
+
+        // Only the first three digits of '1234' are matched
+        assert!(matches("1234", pattern.clone()) == Some(3));
+    }
+
+    #[test]
+    fn repeat_between_the_largest_count_repeats_forever() {
+        let pattern = MatchRange('0', '9').repeat_between(1, u32::MAX);
+
+        assert!(pattern == RepeatInfinite(1, Box::new(MatchRange('0', '9'))));
+        assert!(matches("12345", pattern.clone()) == Some(5));
+        assert!(matches("", pattern.clone()).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn repeat_between_rejects_inverted_bounds() {
+        MatchRange('0', '9').repeat_between(3, 2);
+    }
+
+    #[test]
+    fn any_of_shares_prefixes() {
+        let pattern = any_of(["cat", "car"]);
+
+        assert!(pattern == MatchAll(vec![Match(vec!['c', 'a']), MatchAny(vec![Match(vec!['t']), Match(vec!['r'])])]));
+        assert!(matches("cat", pattern.clone()) == Some(3));
+        assert!(matches("car", pattern.clone()) == Some(3));
+        assert!(matches("can", pattern.clone()).is_none());
+    }
+
+    #[test]
+    fn any_of_keeps_prefixes_of_other_literals() {
+        let pattern = any_of(vec![exactly("in"), exactly("int"), exactly("if"), MatchRange('0', '9')]);
+
+        assert!(matches("in", pattern.clone()) == Some(2));
+        assert!(matches("int", pattern.clone()) == Some(3));
+        assert!(matches("if", pattern.clone()) == Some(2));
+        assert!(matches("5", pattern.clone()) == Some(1));
+        assert!(matches("i", pattern.clone()).is_none());
+    }
+
+    #[test]
+    fn exactly_symbols_matches_bytes() {
+        let elf_magic = exactly_symbols(&[0x7fu8, 0x45, 0x4c, 0x46]);
+
+        assert!(elf_magic == Match(vec![0x7f, 0x45, 0x4c, 0x46]));
+        assert!(matches(&vec![0x7fu8, 0x45, 0x4c, 0x46, 0x01], elf_magic.clone()) == Some(4));
+        assert!(matches(&vec![0x7fu8, 0x45, 0x4c], elf_magic.clone()).is_none());
+        assert!(matches(&vec![0x4du8, 0x5a], elf_magic).is_none());
+    }
+
+    #[test]
+    fn start_anchor_only_matches_at_start() {
+        let leading_ab = start_anchor().append(exactly("ab"));
+
+        assert!(matches("abc", leading_ab.clone()) == Some(2));
+        assert!(find("abab", leading_ab.clone()) == Some(0..2));
+        assert!(find("xab", leading_ab.clone()).is_none());
+        assert!(find_all("abab", leading_ab.clone()).map(|range| (range.start, range.end)).collect::<Vec<_>>() == vec![(0, 2)]);
+    }
+
+    #[test]
+    fn start_anchor_after_symbols_never_matches() {
+        let late_anchor = exactly("ab").append(start_anchor());
+
+        assert!(matches("ab", late_anchor.clone()).is_none());
+        assert!(matches("ab", exactly("x").or(start_anchor()).append("ab")) == Some(2));
+    }
+
+    #[test]
+    fn start_anchor_after_optional_symbols_matches_when_they_are_skipped() {
+        let optional_x = exactly("x").optional().append(start_anchor()).append("ab");
+
+        assert!(matches("ab", optional_x.clone()) == Some(2));
+        assert!(matches("xab", optional_x.clone()).is_none());
+        assert!(find("cab", optional_x.clone()).is_none());
+        assert!(matches("ab", exactly("x").repeat_forever(0).append(start_anchor()).append("ab")) == Some(2));
+        assert!(matches("ab", exactly("x").optional().append(start_anchor()).repeat(2..3).append("ab")) == Some(2));
+        assert!(matches("xab", exactly("x").optional().append(start_anchor()).repeat(2..3).append("ab")).is_none());
+    }
+
+    #[test]
+    fn start_anchor_only_applies_to_first_repetition() {
+        let repeated = start_anchor().append(exactly("ab")).repeat_forever(1);
+
+        assert!(matches("ababx", repeated.clone()) == Some(2));
+        assert!(find_all("ab ab", repeated.clone()).map(|range| (range.start, range.end)).collect::<Vec<_>>() == vec![(0, 2)]);
+        assert!(matches("ababx", start_anchor().append(exactly("ab").repeat_forever(1))) == Some(4));
+    }
+
+    #[test]
+    fn end_anchor_only_matches_at_end() {
+        let whole_ab = exactly("ab").append(end_anchor());
+
+        assert!(matches("ab", whole_ab.clone()) == Some(2));
+        assert!(matches("abc", whole_ab.clone()).is_none());
+        assert!(find("xab", whole_ab.clone()) == Some(1..3));
+        assert!(find("abxab", whole_ab.clone()) == Some(3..5));
+        assert!(find("abc", whole_ab.clone()).is_none());
+    }
+
+    #[test]
+    fn end_anchor_falls_back_to_shorter_matches() {
+        let pattern = exactly("ab").append(end_anchor()).or(exactly("a"));
+
+        assert!(matches("ab", pattern.clone()) == Some(2));
+        assert!(matches("abc", pattern.clone()) == Some(1));
+        assert!(matches("a", exactly("a").append(end_anchor()).or(exactly("ab"))) == Some(1));
+    }
+
+    #[test]
+    fn end_anchor_before_symbols_never_matches() {
+        let early_anchor = end_anchor().append(exactly("ab"));
+
+        assert!(matches("ab", early_anchor.clone()).is_none());
+        assert!(matches("ab", exactly("ab").append(exactly("x").or(end_anchor()))) == Some(2));
+        assert!(matches("", end_anchor()) == Some(0));
+    }
+
+    #[test]
+    fn end_anchor_before_optional_symbols_matches_when_they_are_skipped() {
+        let optional_y = exactly("x").append(end_anchor()).append(exactly("y").optional());
+
+        assert!(matches("x", optional_y.clone()) == Some(1));
+        assert!(matches("xy", optional_y.clone()).is_none());
+        assert!(matches("xz", optional_y.clone()).is_none());
+        assert!(matches("x", exactly("x").append(end_anchor()).append(exactly("y").repeat_forever(0))) == Some(1));
+        assert!(matches("x", exactly("x").append(end_anchor().append(exactly("y").optional()).repeat(2..3))) == Some(1));
+        assert!(matches("xy", exactly("x").append(end_anchor().append(exactly("y").optional()).repeat(2..3))).is_none());
+    }
+
+    #[test]
+    fn end_anchor_only_applies_to_last_repetition() {
+        let repeated = exactly("ab").append(end_anchor()).repeat_forever(1);
+
+        assert!(matches("ab", repeated.clone()) == Some(2));
+        assert!(matches("abab", repeated.clone()).is_none());
+        assert!(find_all("ab ab", repeated.clone()).map(|range| (range.start, range.end)).collect::<Vec<_>>() == vec![(3, 5)]);
+        assert!(matches("abab", exactly("ab").repeat_forever(1).append(end_anchor())) == Some(4));
+    }
+
+    #[test]
+    fn reversing_swaps_anchors() {
+        let leading_ab = start_anchor().append(exactly("ab")).reversed();
+
+        assert!(leading_ab.has_end_anchor() && !leading_ab.has_start_anchor());
+        assert!(matches("ba", leading_ab.clone()) == Some(2));
+        assert!(matches("bac", leading_ab.clone()).is_none());
+    }
+
     #[test]
     fn can_repeat_pattern_forever() {
         let pattern = exactly("abc").repeat_forever(0);
@@ -509,6 +1409,69 @@ mod test {
         assert!(pattern == MatchAny(vec![Match(vec!['a', 'b', 'c']), Match(vec!['d', 'e', 'f'])]));
     }
 
+    #[test]
+    fn can_exclude_symbol_from_range() {
+        let pattern = MatchRange('a', 'z').excluding(&['q']).unwrap();
+
+        assert!(pattern == MatchAny(vec![MatchRange('a', 'p'), MatchRange('r', 'z')]));
+    }
+
+    #[test]
+    fn can_exclude_symbols_at_ends_of_range() {
+        let pattern = MatchRange('a', 'z').excluding(&['a', 'z']).unwrap();
+
+        assert!(pattern == MatchRange('b', 'y'));
+    }
+
+    #[test]
+    fn excluded_range_matches_remaining_symbols() {
+        let pattern = MatchRange('a', 'z').excluding(&['q']).unwrap();
+
+        assert!(matches("a", pattern.clone()) == Some(1));
+        assert!(matches("z", pattern.clone()) == Some(1));
+        assert!(matches("q", pattern.clone()).is_none());
+    }
+
+    #[test]
+    fn cannot_exclude_from_sequence() {
+        assert!(exactly("abc").excluding(&['b']).is_none());
+        assert!(exactly("a").or("bc").excluding(&['b']).is_none());
+    }
+
+    #[test]
+    fn excluding_every_symbol_gives_none() {
+        assert!(MatchRange('a', 'b').excluding(&['a', 'b']).is_none());
+        assert!(exactly("a").or("b").excluding(&['b', 'a']).is_none());
+        assert!(exactly("a").or("b").excluding(&['b']) == Some(MatchRange('a', 'a')));
+    }
+
+    #[test]
+    fn simplify_joins_adjacent_ranges() {
+        let pattern = MatchRange('a', 'c').or(MatchRange('d', 'f'));
+
+        // The NDFA for the simplified pattern needs fewer states than compiling the original pattern directly
+        let mut unsimplified: Ndfa<SymbolRange<char>, ()> = Ndfa::new();
+        pattern.compile(&mut unsimplified, 0);
+
+        assert!(pattern.simplify() == MatchRange('a', 'f'));
+        assert!(pattern.to_ndfa(()).count_states() < unsimplified.count_states());
+    }
+
+    #[test]
+    fn simplify_removes_repeated_alternatives() {
+        let pattern = exactly("abc").or("def").or(MatchRange('x', 'z')).or("abc").or("x");
+
+        assert!(pattern.simplify() == MatchAny(vec![MatchRange('x', 'z'), exactly("abc"), exactly("def")]));
+    }
+
+    #[test]
+    fn simplify_flattens_sequences() {
+        let pattern = MatchAll(vec![exactly("a"), MatchAll(vec![Epsilon, exactly("b")]), Epsilon]);
+
+        assert!(pattern.simplify() == MatchAll(vec![exactly("a"), exactly("b")]));
+        assert!(MatchAny(vec![MatchAll(vec![Epsilon::<char>])]).simplify() == Epsilon);
+    }
+
     #[test]
     fn can_build_ndfa() {
         let pattern = exactly("abc").or("xyz").repeat_forever(0);
@@ -0,0 +1,154 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! Replacing matches of a pattern in a stream of symbols. Matches are found in the same way as a `Tokenizer` finds tokens
+//! (the longest match at the earliest position that doesn't overlap the previous match) and the output is generated
+//! lazily, so the input stream doesn't need to be read all at once.
+//!
+//! ```
+//! # use concordance::*;
+//! let replaced: String = replace_iter("a12b", MatchRange('0', '9').repeat_forever(1), "#").collect();
+//!
+//! // replaced == "a#b"
+//! # assert!(replaced == "a#b");
+//! ```
+//!
+
+use super::countable::*;
+use super::symbol_range_dfa::*;
+use super::symbol_reader::*;
+use super::prepare::*;
+use super::tokenizer::*;
+
+///
+/// Iterator that returns the symbols from a stream with every match of a pattern replaced
+///
+pub struct ReplaceIter<'a, Symbol: Clone+Ord+Countable+'a, OutputSymbol: Clone+Ord+'a, Reader: SymbolReader<Symbol>> {
+    /// Tokenizer that finds the matches in the source stream
+    tokenizer: Tokenizer<'a, Symbol, OutputSymbol, Reader>,
+
+    /// The symbols to replace each match with
+    replacement: Vec<Symbol>,
+
+    /// The index of the next symbol to return from the replacement, if a replacement is being returned
+    replacing: Option<usize>
+}
+
+impl<'a, Symbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<Symbol>> Iterator for ReplaceIter<'a, Symbol, OutputSymbol, Reader> {
+    type Item = Symbol;
+
+    fn next(&mut self) -> Option<Symbol> {
+        // Finish off the current replacement
+        if let Some(index) = self.replacing {
+            if index < self.replacement.len() {
+                self.replacing = Some(index+1);
+                return Some(self.replacement[index].clone());
+            }
+
+            self.replacing = None;
+        }
+
+        loop {
+            if self.tokenizer.next_token().is_some() {
+                // Start returning the replacement (or look for the next match if the replacement is empty)
+                if !self.replacement.is_empty() {
+                    self.replacing = Some(1);
+                    return Some(self.replacement[0].clone());
+                }
+            } else {
+                // Symbols that aren't part of a match are returned unchanged
+                return self.tokenizer.skip_input();
+            }
+        }
+    }
+}
+
+///
+/// Creates an iterator that returns the symbols in a source stream with every match of a pattern replaced
+///
+pub fn replace_iter<'a, Symbol, OutputSymbol, Prepare, Reader, Source, Replacement>(source: Source, pattern: Prepare, replacement: Replacement) -> ReplaceIter<'a, Symbol, OutputSymbol, Reader>
+where   Prepare: PrepareToMatch<SymbolRangeDfa<Symbol, OutputSymbol>>
+,       Reader: SymbolReader<Symbol>+'a
+,       Source: SymbolSource<'a, Symbol, SymbolReader=Reader>
+,       Replacement: SymbolSource<'a, Symbol>
+,       Symbol: Clone+Ord+Countable
+,       OutputSymbol: Clone+Ord+'static {
+    let tokenizer   = Tokenizer::new(source.read_symbols(), pattern);
+    let replacement = replacement.read_symbols().to_vec();
+
+    ReplaceIter { tokenizer, replacement, replacing: None }
+}
+
+///
+/// Replaces every match of a pattern in a string
+///
+/// Adjacent matches are each replaced separately. Zero-length matches are never replaced, so a pattern that can match an
+/// empty string only affects the parts of the input where it matches at least one character.
+///
+/// ```
+/// # use concordance::*;
+/// replace_all("a1b22c", MatchRange('0', '9').repeat_forever(1), "#"); // == "a#b#c"
+/// # assert!(replace_all("a1b22c", MatchRange('0', '9').repeat_forever(1), "#") == "a#b#c");
+/// ```
+///
+pub fn replace_all<OutputSymbol, Prepare>(source: &str, pattern: Prepare, replacement: &str) -> String
+where   Prepare: PrepareToMatch<SymbolRangeDfa<char, OutputSymbol>>
+,       OutputSymbol: Clone+Ord+'static {
+    replace_iter(source, pattern, replacement).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn can_replace_digits() {
+        let replaced: String = replace_iter("a12b", MatchRange('0', '9').repeat_forever(1), "#").collect();
+
+        assert!(replaced == "a#b");
+    }
+
+    #[test]
+    fn can_replace_with_longer_and_empty_strings() {
+        let longer: String  = replace_iter("1a22", MatchRange('0', '9').repeat_forever(1), "<n>").collect();
+        let empty: String   = replace_iter("1a22", MatchRange('0', '9').repeat_forever(1), "").collect();
+
+        assert!(longer == "<n>a<n>");
+        assert!(empty == "a");
+    }
+
+    #[test]
+    fn replace_iter_is_lazy() {
+        let mut replaced = replace_iter("ab1", "b", "x");
+
+        assert!(replaced.next() == Some('a'));
+        assert!(replaced.next() == Some('x'));
+        assert!(replaced.map(|chr| chr.to_ascii_uppercase()).collect::<String>() == "1");
+    }
+
+    #[test]
+    fn replace_all_digits() {
+        assert!(replace_all("a1b22c", MatchRange('0', '9').repeat_forever(1), "#") == "a#b#c");
+    }
+
+    #[test]
+    fn replace_all_adjacent_and_zero_width_matches() {
+        assert!(replace_all("aab", "a", "x") == "xxb");
+        assert!(replace_all("a1b", MatchRange('0', '9').repeat_forever(0), "#") == "a#b");
+        assert!(replace_all("ab", MatchRange('0', '9').repeat_forever(0), "#") == "ab");
+    }
+}
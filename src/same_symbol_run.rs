@@ -0,0 +1,149 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! Matches a run of a single repeated symbol, such as "aaaa" or "bbbb" (but not "abab").
+//!
+//! This is a restricted form of a backreference: the pattern needs to remember the first symbol that it saw and compare
+//! every following symbol against it. The language this describes is not regular for any alphabet with more than a handful
+//! of symbols (a DFA would need a separate branch for every possible symbol), so it can't be represented as a `Pattern` or
+//! compiled into a `SymbolRangeDfa`. Instead, it's implemented directly as a matcher, and can be run using `match_pattern`:
+//!
+//! ```
+//! # use concordance::*;
+//! let run_of_three = Pattern::same_symbol_run(3);
+//!
+//! let match_result = match_pattern(run_of_three.start(), &mut "aaab".read_symbols()); // == Accept(3, &())
+//! # assert!(match match_result { Accept(count, _) => count == 3, _ => false });
+//! ```
+//!
+
+use std::marker::PhantomData;
+
+use super::regular_pattern::*;
+use super::pattern_matcher::*;
+
+///
+/// Matcher that accepts runs of a single repeated symbol
+///
+#[derive(Clone, Debug)]
+pub struct SameSymbolRun<Symbol> {
+    /// The minimum length of a run that will be accepted
+    min_count: u32,
+
+    phantom: PhantomData<Symbol>
+}
+
+///
+/// A state of a same symbol run matcher
+///
+#[derive(Clone)]
+pub struct SameSymbolRunState<'a, Symbol: 'a> {
+    /// The first symbol that was read (None if no symbols have been read yet)
+    symbol: Option<Symbol>,
+
+    /// The number of symbols in the run so far
+    count: usize,
+
+    /// The matcher that this state is for
+    matcher: &'a SameSymbolRun<Symbol>
+}
+
+impl<Symbol: Clone> Pattern<Symbol> {
+    ///
+    /// Creates a matcher that accepts a run of the same symbol repeated at least `min_count` times
+    ///
+    /// Note that this does not return a `Pattern`: a run of an identical symbol can't be expressed as a regular language, so
+    /// this returns a matcher that can be used with `match_pattern` instead.
+    ///
+    pub fn same_symbol_run(min_count: u32) -> SameSymbolRun<Symbol> {
+        SameSymbolRun { min_count, phantom: PhantomData }
+    }
+}
+
+impl<Symbol: PartialEq> SameSymbolRun<Symbol> {
+    ///
+    /// Returns a `MatchAction` for the initial state of this matcher
+    ///
+    pub fn start<'a>(&'a self) -> MatchAction<'a, (), SameSymbolRunState<'a, Symbol>> {
+        More(SameSymbolRunState { symbol: None, count: 0, matcher: self })
+    }
+}
+
+impl<'a, Symbol: PartialEq+'a> MatchingState<'a, Symbol, ()> for SameSymbolRunState<'a, Symbol> {
+    fn next(self, symbol: Symbol) -> MatchAction<'a, (), Self> {
+        let continues_run = match self.symbol {
+            None                    => true,
+            Some(ref run_symbol)    => *run_symbol == symbol
+        };
+
+        if continues_run {
+            // Symbol extends the run: remember the first symbol so we can compare against it later
+            let run_symbol = if self.symbol.is_none() { Some(symbol) } else { self.symbol };
+            More(SameSymbolRunState { symbol: run_symbol, count: self.count+1, matcher: self.matcher })
+        } else {
+            // Run has finished
+            self.finish()
+        }
+    }
+
+    fn finish(self) -> MatchAction<'a, (), Self> {
+        if self.count >= self.matcher.min_count as usize {
+            Accept(self.count, &())
+        } else {
+            Reject
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn accepts_runs_of_one_symbol() {
+        let run = Pattern::same_symbol_run(3);
+
+        assert!(match_pattern(run.start(), &mut "aaa".read_symbols()).is_accepted(&()));
+        assert!(match_pattern(run.start(), &mut "bbb".read_symbols()).is_accepted(&()));
+    }
+
+    #[test]
+    fn rejects_mixed_symbols() {
+        let run = Pattern::same_symbol_run(3);
+
+        assert!(!match_pattern(run.start(), &mut "abc".read_symbols()).is_accepted(&()));
+        assert!(!match_pattern(run.start(), &mut "abab".read_symbols()).is_accepted(&()));
+    }
+
+    #[test]
+    fn matches_longest_run() {
+        let run = Pattern::same_symbol_run(2);
+
+        match match_pattern(run.start(), &mut "aaaab".read_symbols()) {
+            Accept(count, _)    => assert!(count == 4),
+            _                   => panic!()
+        }
+    }
+
+    #[test]
+    fn rejects_short_runs() {
+        let run = Pattern::same_symbol_run(3);
+
+        assert!(!match_pattern(run.start(), &mut "aab".read_symbols()).is_accepted(&()));
+        assert!(!match_pattern(run.start(), &mut "".read_symbols()).is_accepted(&()));
+    }
+}
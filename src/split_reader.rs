@@ -44,7 +44,7 @@ pub struct SplitSymbolReader<'a, Symbol: Clone+'a> {
 ///
 struct SplitSymbolReaderBuffer<'a, Symbol: 'a> {
     /// The underlying symbol reader
-    symbol_reader: &'a mut SymbolReader<Symbol>,
+    symbol_reader: &'a mut dyn SymbolReader<Symbol>,
 
     /// Positions for the various split readers that are using this object, relative to the buffer
     positions: Vec<usize>,
@@ -54,7 +54,7 @@ struct SplitSymbolReaderBuffer<'a, Symbol: 'a> {
 }
 
 impl<'a, Symbol: Clone> SplitSymbolReaderBuffer<'a, Symbol> {
-    fn new(reader: &'a mut SymbolReader<Symbol>) -> SplitSymbolReaderBuffer<'a, Symbol> {
+    fn new(reader: &'a mut dyn SymbolReader<Symbol>) -> SplitSymbolReaderBuffer<'a, Symbol> {
         SplitSymbolReaderBuffer { symbol_reader: reader, positions: vec![], buffer: VecDeque::new() }
     }
 
@@ -64,10 +64,10 @@ impl<'a, Symbol: Clone> SplitSymbolReaderBuffer<'a, Symbol> {
     fn clear_buffer(&mut self) {
         let lowest_pos = self.positions.iter()
             .min()
-            .map(|x| *x);
+            .copied();
 
         if let Some(lowest_pos) = lowest_pos {
-            if lowest_pos < usize::max_value() {
+            if lowest_pos < usize::MAX {
                 for _ in 0..lowest_pos {
                     self.buffer.pop_front();
                 }
@@ -148,7 +148,7 @@ impl<'a, Symbol: Clone+'a> Drop for SplitSymbolReader<'a, Symbol> {
     fn drop(&mut self) {
         let mut buffer_ref = (*self.buffer).borrow_mut();
 
-        buffer_ref.positions[self.reader_id] = usize::max_value();
+        buffer_ref.positions[self.reader_id] = usize::MAX;
         buffer_ref.clear_buffer();
     }
 }
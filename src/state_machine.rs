@@ -53,6 +53,16 @@ pub trait StateMachine<InputSymbol, OutputSymbol> {
     /// If a state is an accepting state, then this returns the output symbol that should be produced if this is the longest match
     ///
     fn output_symbol_for_state(&self, state: StateId) -> Option<&OutputSymbol>;
+
+    ///
+    /// Returns the states that are reached from a state without reading any input (including the state itself), in order
+    ///
+    /// A state behaves as if it has all of the transitions and output symbols of the states in its closure. State
+    /// machines without epsilon transitions only have the state itself in the closure.
+    ///
+    fn epsilon_closure(&self, state: StateId) -> Vec<StateId> {
+        vec![state]
+    }
 }
 
 ///
@@ -96,13 +106,13 @@ pub trait MutableStateMachine<InputSymbol, OutputSymbol> : StateMachine<InputSym
 /// the output state.
 ///
 pub trait ToNdfa<InputSymbol> {
-    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<StateMachine<InputSymbol, OutputSymbol>>;
+    fn to_ndfa<OutputSymbol: 'static>(&self, output: OutputSymbol) -> Box<dyn StateMachine<InputSymbol, OutputSymbol>>;
 }
 
 ///
 /// Any reference to a state machine is also a state machine
 ///
-impl<InputSymbol, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> for Rc<StateMachine<InputSymbol, OutputSymbol>> {
+impl<InputSymbol, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> for Rc<dyn StateMachine<InputSymbol, OutputSymbol>> {
     #[inline]
     fn count_states(&self) -> StateId {
         (**self).count_states()
@@ -117,12 +127,17 @@ impl<InputSymbol, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> for Rc<S
     fn output_symbol_for_state(&self, state: StateId) -> Option<&OutputSymbol> {
         (**self).output_symbol_for_state(state)
     }
+
+    #[inline]
+    fn epsilon_closure(&self, state: StateId) -> Vec<StateId> {
+        (**self).epsilon_closure(state)
+    }
 }
 
 ///
 /// Any reference to a state machine is also a state machine
 ///
-impl<'a, InputSymbol, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> for Box<StateMachine<InputSymbol, OutputSymbol>+'a> {
+impl<'a, InputSymbol, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> for Box<dyn StateMachine<InputSymbol, OutputSymbol>+'a> {
     #[inline]
     fn count_states(&self) -> StateId {
         (**self).count_states()
@@ -137,4 +152,9 @@ impl<'a, InputSymbol, OutputSymbol> StateMachine<InputSymbol, OutputSymbol> for
     fn output_symbol_for_state(&self, state: StateId) -> Option<&OutputSymbol> {
         (**self).output_symbol_for_state(state)
     }
+
+    #[inline]
+    fn epsilon_closure(&self, state: StateId) -> Vec<StateId> {
+        (**self).epsilon_closure(state)
+    }
 }
@@ -0,0 +1,235 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! A streaming tokenizer is fed its input in chunks, rather than reading it from a `SymbolReader`. This is useful when the
+//! input arrives a piece at a time (for instance, from a network connection) and it isn't possible to block waiting for
+//! more of it.
+//!
+//! ```
+//! # use concordance::*;
+//! let dfa             = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+//! let mut tokenizer   = StreamingTokenizer::new(&dfa);
+//!
+//! tokenizer.feed(&['a', 'b']);
+//! tokenizer.pull();           // == None (the token might continue in the next chunk)
+//!
+//! tokenizer.feed(&['c', ' ']);
+//! tokenizer.pull();           // == Some(Token { output: (), location: 0..3 })
+//! # tokenizer.end_input();
+//! # assert!(tokenizer.pull().is_none());
+//! ```
+//!
+//! Only the symbols that have been fed to the tokenizer but not yet returned as part of a token are kept, and the state
+//! of the DFA is kept between calls so the symbols of a partial token are only matched once. Once the last chunk has
+//! been fed in, `end_input()` should be called so that a token at the end of the input can be completed.
+//!
+
+use std::collections::VecDeque;
+
+use super::countable::*;
+use super::pattern_matcher::*;
+use super::symbol_range_dfa::*;
+use super::annotated_stream::*;
+
+///
+/// Tokenizer that is fed its input a chunk at a time
+///
+pub struct StreamingTokenizer<'a, InputSymbol: Ord+'a, OutputSymbol: 'a> {
+    /// The DFA that matches the tokens
+    dfa: &'a SymbolRangeDfa<InputSymbol, OutputSymbol>,
+
+    /// Symbols that have been fed in but that are not part of a token yet
+    buffer: VecDeque<InputSymbol>,
+
+    /// The position in the input of the first symbol in the buffer
+    position: usize,
+
+    /// The state of the DFA for the token that's being matched, if it has been started
+    state: Option<SymbolRangeState<'a, InputSymbol, OutputSymbol>>,
+
+    /// The number of symbols in the buffer that have been passed to the DFA
+    scanned: usize,
+
+    /// True once there's no more input to be fed in
+    end_of_input: bool
+}
+
+impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+'static> StreamingTokenizer<'a, InputSymbol, OutputSymbol> {
+    ///
+    /// Creates a new streaming tokenizer that will find the tokens matched by a DFA
+    ///
+    pub fn new(dfa: &'a SymbolRangeDfa<InputSymbol, OutputSymbol>) -> StreamingTokenizer<'a, InputSymbol, OutputSymbol> {
+        StreamingTokenizer {
+            dfa,
+            buffer:         VecDeque::new(),
+            position:       0,
+            state:          None,
+            scanned:        0,
+            end_of_input:   false
+        }
+    }
+
+    ///
+    /// Adds the next chunk of input to this tokenizer
+    ///
+    pub fn feed(&mut self, chunk: &[InputSymbol]) {
+        self.buffer.extend(chunk.iter().cloned());
+    }
+
+    ///
+    /// Indicates that there's no more input, so any token that's being matched can be finished
+    ///
+    pub fn end_input(&mut self) {
+        self.end_of_input = true;
+    }
+
+    ///
+    /// Returns the number of symbols that have been fed in but not returned as part of a token
+    ///
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    ///
+    /// Returns the next token, if one can be found in the input that has been fed in so far
+    ///
+    /// This returns `None` if more input is needed to decide where the next token ends. Input that doesn't match a token
+    /// is skipped.
+    ///
+    pub fn pull(&mut self) -> Option<Token<OutputSymbol>> {
+        loop {
+            // Continue the token that's being matched, or start a new one
+            let mut action = match self.state.take() {
+                Some(state) => More(state),
+                None        => if self.position == 0 { self.dfa.start() } else { self.dfa.start_mid_input() }
+            };
+
+            // Pass in the symbols that the DFA hasn't seen yet
+            while self.scanned < self.buffer.len() {
+                if let More(state) = action {
+                    action = state.next(self.buffer[self.scanned].clone());
+                    self.scanned += 1;
+                } else {
+                    break;
+                }
+            }
+
+            // If the DFA needs more symbols, wait for the next chunk (unless there isn't one)
+            let action = match action {
+                More(state) => {
+                    if self.end_of_input {
+                        state.finish()
+                    } else {
+                        self.state = Some(state);
+                        return None;
+                    }
+                },
+
+                action      => action
+            };
+
+            self.scanned = 0;
+
+            match action {
+                Accept(length, output) if length > 0 => {
+                    let location = self.position..(self.position + length);
+
+                    self.buffer.drain(..length);
+                    self.position += length;
+
+                    return Some(Token { output: output.clone(), location });
+                },
+
+                _ => {
+                    // No token starts here: skip a symbol and try again
+                    self.buffer.pop_front()?;
+                    self.position += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn tokens_can_span_chunks() {
+        let dfa             = MatchRange('a', 'z').repeat_forever(1).prepare_to_match();
+        let mut tokenizer   = StreamingTokenizer::new(&dfa);
+
+        tokenizer.feed(&['a', 'b']);
+        assert!(tokenizer.pull().is_none());
+
+        tokenizer.feed(&['c', 'd']);
+        assert!(tokenizer.pull().is_none());
+
+        tokenizer.end_input();
+        assert!(tokenizer.pull() == Some(Token { output: (), location: 0..4 }));
+        assert!(tokenizer.pull().is_none());
+        assert!(tokenizer.buffered_len() == 0);
+    }
+
+    #[test]
+    fn only_unfinished_tokens_are_buffered() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), 1);
+        token_matcher.add_pattern(exactly("->"), 2);
+
+        let dfa             = token_matcher.prepare_to_match();
+        let mut tokenizer   = StreamingTokenizer::new(&dfa);
+
+        tokenizer.feed(&['1', '2', ' ', '3']);
+        assert!(tokenizer.pull() == Some(Token { output: 1, location: 0..2 }));
+        assert!(tokenizer.pull().is_none());
+        assert!(tokenizer.buffered_len() == 1);
+
+        tokenizer.feed(&['4', '-']);
+        assert!(tokenizer.pull() == Some(Token { output: 1, location: 3..5 }));
+        assert!(tokenizer.pull().is_none());
+
+        tokenizer.feed(&['>', ' ', '5']);
+        tokenizer.end_input();
+        assert!(tokenizer.pull() == Some(Token { output: 2, location: 5..7 }));
+        assert!(tokenizer.pull() == Some(Token { output: 1, location: 8..9 }));
+        assert!(tokenizer.pull().is_none());
+    }
+
+    #[test]
+    fn partial_tokens_that_fail_are_rematched() {
+        // 'abc' is a token, but if the input is 'abd' then 'a' and 'b' need to be matched again as individual letters
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(exactly("abc"), 1);
+        token_matcher.add_pattern(MatchRange('a', 'z'), 2);
+
+        let dfa             = token_matcher.prepare_to_match();
+        let mut tokenizer   = StreamingTokenizer::new(&dfa);
+
+        tokenizer.feed(&['a', 'b']);
+        assert!(tokenizer.pull().is_none());
+
+        tokenizer.feed(&['d']);
+        assert!(tokenizer.pull() == Some(Token { output: 2, location: 0..1 }));
+        assert!(tokenizer.pull() == Some(Token { output: 2, location: 1..2 }));
+        assert!(tokenizer.pull().is_none());
+
+        tokenizer.end_input();
+        assert!(tokenizer.pull() == Some(Token { output: 2, location: 2..3 }));
+        assert!(tokenizer.pull().is_none());
+    }
+}
@@ -24,13 +24,23 @@
 //! possible to represent the entire range of symbols in a particular type: exclusive ranges have to exclude at least one symbol
 //! so can never represent the entire range without having to treat it as a special case.
 //!
+//! Some alphabets are cyclic (for example, the positions on a clock face). `SymbolRange::wrapping()` creates a range in
+//! one of these alphabets that can run past the end and continue from the start: for instance, 10 to 1 on a clock face
+//! covers 10, 11, 0 and 1.
+//!
 
 use std::cmp::*;
 
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+
+use super::countable::*;
+
 ///
 /// Represents a range of symbols
 ///
-#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+/// Ranges are serialized as a `(lowest, highest)` pair, as state machines can contain a very large number of them.
+///
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub struct SymbolRange<Symbol: Ord> {
     ///
     /// Lowest symbol in the range
@@ -46,6 +56,20 @@ pub struct SymbolRange<Symbol: Ord> {
     pub highest: Symbol
 }
 
+impl<Symbol: Ord+Serialize> Serialize for SymbolRange<Symbol> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.lowest, &self.highest).serialize(serializer)
+    }
+}
+
+impl<'de, Symbol: Ord+Deserialize<'de>> Deserialize<'de> for SymbolRange<Symbol> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (lowest, highest) = <(Symbol, Symbol)>::deserialize(deserializer)?;
+
+        Ok(SymbolRange { lowest, highest })
+    }
+}
+
 impl<Symbol: Ord> PartialOrd for SymbolRange<Symbol> {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
@@ -69,27 +93,33 @@ impl<Symbol: Ord> SymbolRange<Symbol> {
     ///
     /// Creates a new range covering everything between the specified two symbols
     ///
+    /// Both `lowest` and `highest` are included in the range, which is how ranges are always stored.
+    ///
     #[inline]
     pub fn new(lowest: Symbol, highest: Symbol) -> SymbolRange<Symbol> {
         if lowest > highest {
             panic!("lowest must be <= highest when creating SymbolRanges");
         } else {
-            SymbolRange { lowest: lowest, highest: highest }
+            SymbolRange { lowest, highest }
         }
     }
 
+    ///
+    /// Creates a range that includes both `lowest` and `highest` and every symbol between them
+    ///
+    /// This is the same as `new()`, but makes it clear at the call site that the range is inclusive.
+    ///
+    #[inline]
+    pub fn inclusive(lowest: Symbol, highest: Symbol) -> SymbolRange<Symbol> {
+        Self::new(lowest, highest)
+    }
+
     ///
     /// True if this range overlaps another
     ///
     #[inline]
     pub fn overlaps(&self, with: &SymbolRange<Symbol>) -> bool {
-        if self.highest < with.lowest {
-            false
-        } else if self.lowest > with.highest {
-            false
-        } else {
-            true
-        }
+        self.highest >= with.lowest && self.lowest <= with.highest
     }
 
     ///
@@ -101,7 +131,29 @@ impl<Symbol: Ord> SymbolRange<Symbol> {
     }
 }
 
+impl<Symbol: Ord> SymbolRange<Symbol> {
+    ///
+    /// Creates a range in a cyclic alphabet of symbols less than `modulus`, which wraps around to the start of the alphabet
+    /// if `highest` is less than `lowest`
+    ///
+    pub fn wrapping(lowest: Symbol, highest: Symbol, modulus: Symbol) -> WrappingRange<Symbol> {
+        if lowest >= modulus || highest >= modulus {
+            panic!("lowest and highest must be less than the modulus when creating WrappingRanges");
+        } else {
+            WrappingRange { lowest, highest, modulus }
+        }
+    }
+}
+
 impl<Symbol: Ord+Clone> SymbolRange<Symbol> {
+    ///
+    /// Creates a range that contains only one symbol
+    ///
+    #[inline]
+    pub fn single(symbol: Symbol) -> SymbolRange<Symbol> {
+        SymbolRange { lowest: symbol.clone(), highest: symbol }
+    }
+
     ///
     /// Joins this range with another
     ///
@@ -114,12 +166,192 @@ impl<Symbol: Ord+Clone> SymbolRange<Symbol> {
             highest: if with.highest<self.highest { self.highest.clone() } else { with.highest.clone() }
         }
     }
+
+    ///
+    /// Returns the range of symbols that are in both this range and another, or None if they don't overlap
+    ///
+    pub fn intersect(&self, with: &SymbolRange<Symbol>) -> Option<SymbolRange<Symbol>> {
+        if self.overlaps(with) {
+            Some(SymbolRange {
+                lowest:  if with.lowest>self.lowest   { with.lowest.clone()  } else { self.lowest.clone()  },
+                highest: if with.highest<self.highest { with.highest.clone() } else { self.highest.clone() }
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl<Symbol: Ord+Clone+Countable> SymbolRange<Symbol> {
+    ///
+    /// Creates a range that includes `lowest` and every symbol up to but not including `end`, like Rust's `Range` type
+    ///
+    /// Ranges are stored inclusively, so the highest symbol is the one before `end`. Empty ranges can't be represented, so
+    /// this panics if `end` is not greater than `lowest`.
+    ///
+    pub fn exclusive(lowest: Symbol, end: Symbol) -> SymbolRange<Symbol> {
+        if end <= lowest {
+            panic!("end must be > lowest when creating exclusive SymbolRanges");
+        } else {
+            SymbolRange { lowest, highest: end.prev() }
+        }
+    }
+
+    ///
+    /// Sorts a set of ranges, joining any that overlap or are next to each other
+    ///
+    pub fn join_all(ranges: Vec<SymbolRange<Symbol>>) -> Vec<SymbolRange<Symbol>> {
+        let mut ranges = ranges;
+        ranges.sort();
+
+        let mut joined: Vec<SymbolRange<Symbol>> = vec![];
+
+        for range in ranges {
+            match joined.last_mut() {
+                Some(last) if last.overlaps(&range) || range.lowest.prev() == last.highest  => *last = last.join(&range),
+                _                                                                           => joined.push(range)
+            }
+        }
+
+        joined
+    }
+
+    ///
+    /// Returns the ranges that contain the symbols in either this range or another
+    ///
+    /// This is a single range if the two ranges overlap or are next to each other, or both ranges in order if they're
+    /// disjoint.
+    ///
+    pub fn union(&self, with: &SymbolRange<Symbol>) -> Vec<SymbolRange<Symbol>> {
+        Self::join_all(vec![self.clone(), with.clone()])
+    }
+
+    ///
+    /// Returns the ranges that contain the symbols in this range that are not in another range
+    ///
+    /// This is empty if `without` covers all of this range, and two ranges if `without` is in the middle of it.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let letters = SymbolRange::new('a', 'f');
+    ///
+    /// letters.difference(&SymbolRange::new('c', 'd')); // == vec![SymbolRange::new('a', 'b'), SymbolRange::new('e', 'f')]
+    /// # assert!(letters.difference(&SymbolRange::new('c', 'd')) == vec![SymbolRange::new('a', 'b'), SymbolRange::new('e', 'f')]);
+    /// ```
+    ///
+    pub fn difference(&self, without: &SymbolRange<Symbol>) -> Vec<SymbolRange<Symbol>> {
+        if !self.overlaps(without) {
+            return vec![self.clone()];
+        }
+
+        let mut result = vec![];
+
+        if self.lowest < without.lowest {
+            result.push(SymbolRange { lowest: self.lowest.clone(), highest: without.lowest.prev() });
+        }
+
+        if self.highest > without.highest {
+            result.push(SymbolRange { lowest: without.highest.next(), highest: self.highest.clone() });
+        }
+
+        result
+    }
+}
+
+///
+/// Represents a range of symbols in a cyclic alphabet, which may wrap around from the end of the alphabet to the start
+///
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub struct WrappingRange<Symbol: Ord> {
+    /// First symbol in the range
+    pub lowest: Symbol,
+
+    /// Last symbol in the range (inclusive). If this is less than `lowest` the range wraps around
+    pub highest: Symbol,
+
+    /// The symbols in the alphabet are all less than this value
+    pub modulus: Symbol
+}
+
+impl<Symbol: Ord> WrappingRange<Symbol> {
+    ///
+    /// True if this range wraps around from the end of the alphabet to the start
+    ///
+    #[inline]
+    pub fn wraps(&self) -> bool {
+        self.highest < self.lowest
+    }
+
+    ///
+    /// True if this range contains a symbol
+    ///
+    #[inline]
+    pub fn includes(&self, symbol: &Symbol) -> bool {
+        if *symbol >= self.modulus {
+            false
+        } else if self.wraps() {
+            *symbol >= self.lowest || *symbol <= self.highest
+        } else {
+            self.lowest <= *symbol && *symbol <= self.highest
+        }
+    }
+}
+
+impl<Symbol: Ord+Clone+Countable> WrappingRange<Symbol> {
+    ///
+    /// Converts this range into the equivalent non-wrapping symbol ranges
+    ///
+    /// The alphabet is taken to start at the type's minimum symbol (0 for the unsigned integer types), so this will panic
+    /// for wrapping ranges of a type that doesn't have a minimum symbol.
+    ///
+    pub fn to_ranges(&self) -> Vec<SymbolRange<Symbol>> {
+        if self.wraps() {
+            let min_symbol = Symbol::min_symbol().expect("Wrapping ranges can only be converted if their symbols have a minimum value");
+
+            vec![SymbolRange::new(min_symbol, self.highest.clone()), SymbolRange::new(self.lowest.clone(), self.modulus.prev())]
+        } else {
+            vec![SymbolRange::new(self.lowest.clone(), self.highest.clone())]
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    #[test]
+    fn single_range_contains_one_symbol() {
+        let range = SymbolRange::single('a');
+
+        assert!(range.includes(&'a'));
+        assert!(!range.includes(&'b'));
+        assert!(!range.includes(&'`'));
+        assert!(range == SymbolRange::new('a', 'a'));
+    }
+
+    #[test]
+    fn inclusive_range_contains_both_ends() {
+        let range = SymbolRange::inclusive('a', 'c');
+
+        assert!(('a'..='c').all(|symbol| range.includes(&symbol)));
+        assert!(!range.includes(&'d'));
+        assert!(!range.includes(&'`'));
+    }
+
+    #[test]
+    fn exclusive_range_leaves_out_end() {
+        let range = SymbolRange::exclusive('a', 'd');
+
+        assert!(range == SymbolRange::inclusive('a', 'c'));
+        assert!(SymbolRange::exclusive(0u8, 1u8) == SymbolRange::single(0u8));
+    }
+
+    #[test]
+    #[should_panic]
+    fn exclusive_range_cannot_be_empty() {
+        SymbolRange::exclusive(3, 3);
+    }
+
     #[test]
     fn can_create_range() {
         let range = SymbolRange::new(1, 2);
@@ -193,6 +425,13 @@ mod test {
         assert!(joined.highest == 4);
     }
 
+    #[test]
+    fn join_all_merges_adjacent_ranges() {
+        let joined = SymbolRange::join_all(vec![SymbolRange::new(5u8, 9u8), SymbolRange::new(0u8, 2u8), SymbolRange::new(3u8, 4u8), SymbolRange::new(20u8, 30u8)]);
+
+        assert!(joined == vec![SymbolRange::new(0u8, 9u8), SymbolRange::new(20u8, 30u8)]);
+    }
+
     #[test]
     fn includes_single_item() {
         let just_zero = SymbolRange::new(0,0);
@@ -227,4 +466,80 @@ mod test {
         let just_zero = SymbolRange::new(1,4);
         assert!(!just_zero.includes(&5));        
     }
+
+    #[test]
+    fn wrapping_range_includes_both_ends() {
+        let clock = SymbolRange::wrapping(10, 1, 12);
+
+        for included in [10, 11, 0, 1].iter() {
+            assert!(clock.includes(included));
+        }
+
+        for excluded in [2, 5, 9, 12].iter() {
+            assert!(!clock.includes(excluded));
+        }
+    }
+
+    #[test]
+    fn non_wrapping_range_is_normal() {
+        let range = SymbolRange::wrapping(2u8, 5u8, 12u8);
+
+        assert!(!range.wraps());
+        assert!(range.includes(&3));
+        assert!(!range.includes(&6));
+        assert!(range.to_ranges() == vec![SymbolRange::new(2, 5)]);
+    }
+
+    #[test]
+    fn wrapping_range_splits_into_two() {
+        let clock = SymbolRange::wrapping(10u8, 1u8, 12u8);
+
+        assert!(clock.to_ranges() == vec![SymbolRange::new(0, 1), SymbolRange::new(10, 11)]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrapping_range_outside_modulus_panics() {
+        SymbolRange::wrapping(10, 12, 12);
+    }
+
+    #[test]
+    fn ranges_serialize_as_pairs() {
+        let json                        = ::serde_json::to_string(&SymbolRange::new('a', 'z')).unwrap();
+        let loaded: SymbolRange<char>   = ::serde_json::from_str(&json).unwrap();
+
+        assert!(json == "[\"a\",\"z\"]");
+        assert!(loaded == SymbolRange::new('a', 'z'));
+    }
+
+    #[test]
+    fn intersect_overlapping_ranges() {
+        assert!(SymbolRange::new('a', 'f').intersect(&SymbolRange::new('d', 'z')) == Some(SymbolRange::new('d', 'f')));
+        assert!(SymbolRange::new('a', 'z').intersect(&SymbolRange::new('c', 'd')) == Some(SymbolRange::new('c', 'd')));
+        assert!(SymbolRange::new('a', 'c').intersect(&SymbolRange::new('c', 'e')) == Some(SymbolRange::single('c')));
+    }
+
+    #[test]
+    fn intersect_disjoint_ranges() {
+        assert!(SymbolRange::new('a', 'c').intersect(&SymbolRange::new('d', 'f')).is_none());
+        assert!(SymbolRange::new('x', 'z').intersect(&SymbolRange::new('a', 'c')).is_none());
+    }
+
+    #[test]
+    fn union_of_ranges() {
+        // Overlapping and adjacent ranges are joined, disjoint ones are kept separate
+        assert!(SymbolRange::new('a', 'd').union(&SymbolRange::new('c', 'f')) == vec![SymbolRange::new('a', 'f')]);
+        assert!(SymbolRange::new('d', 'f').union(&SymbolRange::new('a', 'c')) == vec![SymbolRange::new('a', 'f')]);
+        assert!(SymbolRange::new('x', 'z').union(&SymbolRange::new('a', 'c')) == vec![SymbolRange::new('a', 'c'), SymbolRange::new('x', 'z')]);
+    }
+
+    #[test]
+    fn difference_of_ranges() {
+        assert!(SymbolRange::new('a', 'f').difference(&SymbolRange::new('c', 'd')) == vec![SymbolRange::new('a', 'b'), SymbolRange::new('e', 'f')]);
+        assert!(SymbolRange::new('a', 'f').difference(&SymbolRange::new('d', 'z')) == vec![SymbolRange::new('a', 'c')]);
+        assert!(SymbolRange::new('a', 'f').difference(&SymbolRange::new('a', 'a')) == vec![SymbolRange::new('b', 'f')]);
+        assert!(SymbolRange::new('a', 'f').difference(&SymbolRange::new('g', 'z')) == vec![SymbolRange::new('a', 'f')]);
+        assert!(SymbolRange::new('c', 'd').difference(&SymbolRange::new('a', 'f')).is_empty());
+        assert!(SymbolRange::new(0u8, 255u8).difference(&SymbolRange::new(0u8, 254u8)) == vec![SymbolRange::single(255u8)]);
+    }
 }
@@ -19,9 +19,11 @@
 //!
 
 use std::mem::*;
+use std::collections::HashMap;
 
 use super::countable::*;
 use super::dfa_builder::*;
+use super::overlapping_symbols::*;
 use super::pattern_matcher::*;
 use super::symbol_range::*;
 use super::state_machine::*;
@@ -29,7 +31,22 @@ use super::state_machine::*;
 ///
 /// DFA that decides on transitions based on non-overlapping, sorted lists of input symbols
 ///
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Two DFAs that match the same patterns can number their states differently, so they only compare (or hash) as equal
+/// once `canonicalize()` has been called on both of them.
+///
+/// DFAs can be serialized with serde, so a tokenizer can be compiled ahead of time and loaded as data rather than being
+/// rebuilt from its patterns every time it's needed.
+///
+/// A DFA compiled from patterns that contain a `start_anchor()` has a second initial state, used for matches that begin at
+/// the very start of the input (see `start()` and `start_mid_input()`). Operations that build a new DFA from the
+/// transitions of this one, such as `minimize()` or `intersect()`, follow the DFA from both of its initial states.
+///
+/// Patterns that contain an `end_anchor()` can only match some strings once the input has run out. States where this
+/// happens have a second output symbol, which is only used when the matcher is finished at the end of the input. States
+/// can also reject at the end of the input when they would otherwise accept (the complement of an end anchored pattern
+/// needs this).
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SymbolRangeDfa<InputSymbol: Ord, OutputSymbol> {
     //
     // Indexes of where each state starts in the transition table (it ends at the start of the next state)
@@ -44,7 +61,52 @@ pub struct SymbolRangeDfa<InputSymbol: Ord, OutputSymbol> {
     //
     // The accepting symbol for each state
     //
-    accept: Vec<Option<OutputSymbol>>
+    accept: Vec<Option<OutputSymbol>>,
+
+    //
+    // The state to start in when matching from the very start of the input, if it's not state 0
+    //
+    #[serde(default)]
+    start_of_input: Option<StateId>,
+
+    //
+    // What each state does when there's no more input (empty if every state behaves the same as it does mid-input)
+    //
+    #[serde(default = "Vec::new")]
+    accept_at_end: Vec<OutputAtEnd<OutputSymbol>>
+}
+
+///
+/// What a state of a `SymbolRangeDfa` does when there are no more symbols in the input
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum OutputAtEnd<OutputSymbol> {
+    /// The state accepts or rejects in the same way as it does when there's more input
+    Unchanged,
+
+    /// The state accepts with this output symbol
+    Accept(OutputSymbol),
+
+    /// The state rejects, even if it would accept when there's more input
+    Reject
+}
+
+///
+/// Statistics about the size of a DFA, as returned by `SymbolRangeDfa::stats()`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DfaStats {
+    /// The number of states in the DFA
+    pub num_states: usize,
+
+    /// The total number of transitions, across all of the states
+    pub num_transitions: usize,
+
+    /// The number of states that produce an output symbol (including those that only accept at the end of the input)
+    pub num_accepting_states: usize,
+
+    /// The number of transitions in the state that has the most of them
+    pub max_transitions_per_state: usize
 }
 
 ///
@@ -53,12 +115,47 @@ pub struct SymbolRangeDfa<InputSymbol: Ord, OutputSymbol> {
 pub struct SymbolRangeDfaBuilder<InputSymbol: Ord+Countable, OutputSymbol> {
     states: Vec<usize>,
     transitions: Vec<(SymbolRange<InputSymbol>, StateId)>,
-    accept: Vec<Option<OutputSymbol>>
+    accept: Vec<Option<OutputSymbol>>,
+    accept_at_end: Vec<OutputAtEnd<OutputSymbol>>,
+    start_of_input: Option<StateId>
+}
+
+impl<InputSymbol: Ord+Countable, OutputSymbol> Default for SymbolRangeDfaBuilder<InputSymbol, OutputSymbol> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<InputSymbol: Ord+Countable, OutputSymbol> SymbolRangeDfaBuilder<InputSymbol, OutputSymbol> {
     pub fn new() -> SymbolRangeDfaBuilder<InputSymbol, OutputSymbol> {
-        SymbolRangeDfaBuilder { states: vec![], transitions: vec![], accept: vec![] }
+        SymbolRangeDfaBuilder { states: vec![], transitions: vec![], accept: vec![], accept_at_end: vec![], start_of_input: None }
+    }
+
+    ///
+    /// Sets the output symbol that the current state produces if there are no more symbols in the input
+    ///
+    /// The output symbol set by `accept()` is only used when there are more symbols in the input after this state.
+    ///
+    pub fn accept_at_end(&mut self, symbol: OutputSymbol) {
+        self.accept_at_end.pop();
+        self.accept_at_end.push(OutputAtEnd::Accept(symbol));
+    }
+
+    ///
+    /// Sets the current state to reject if there are no more symbols in the input, even if it's an accepting state
+    ///
+    pub fn reject_at_end(&mut self) {
+        self.accept_at_end.pop();
+        self.accept_at_end.push(OutputAtEnd::Reject);
+    }
+
+    ///
+    /// Sets the state that the DFA begins in when matching from the very start of the input
+    ///
+    /// State 0 is used for all matches if this isn't called.
+    ///
+    pub fn set_start_of_input(&mut self, state: StateId) {
+        self.start_of_input = Some(state);
     }
 }
 
@@ -87,6 +184,7 @@ impl<InputSymbol: Ord+Countable+Clone, OutputSymbol> DfaBuilder<SymbolRange<Inpu
         // Begin the next state
         self.states.push(self.transitions.len());
         self.accept.push(None);
+        self.accept_at_end.push(OutputAtEnd::Unchanged);
     }
 
     fn transition(&mut self, symbol: SymbolRange<InputSymbol>, target_state: StateId) {
@@ -100,7 +198,10 @@ impl<InputSymbol: Ord+Countable+Clone, OutputSymbol> DfaBuilder<SymbolRange<Inpu
 
     fn build(self) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
         // Turn into a RangeDfa
-        let mut result = SymbolRangeDfa { states: self.states, transitions: self.transitions, accept: self.accept };
+        let start_of_input  = self.start_of_input.filter(|state| *state != 0);
+        let changes_at_end  = self.accept_at_end.iter().any(|output| !matches!(output, OutputAtEnd::Unchanged));
+        let accept_at_end   = if changes_at_end { self.accept_at_end } else { vec![] };
+        let mut result      = SymbolRangeDfa { states: self.states, transitions: self.transitions, accept: self.accept, start_of_input, accept_at_end };
 
         // 'Cap' the last state so we don't need to special-case it later 
         // ie, we can always find the index of the last symbol by looking at the next state and don't need to handle the final state differently
@@ -147,6 +248,11 @@ impl<InputSymbol: Ord+Clone, OutputSymbol> StateMachine<SymbolRange<InputSymbol>
     }
 }
 
+///
+/// Function that decides whether or not an accepting state should accept while a DFA is running
+///
+pub type AcceptPredicate<'a, OutputSymbol> = &'a dyn Fn(StateId, &OutputSymbol) -> bool;
+
 ///
 /// A state of a symbol range state machine
 ///
@@ -161,23 +267,259 @@ pub struct SymbolRangeState<'a, InputSymbol: Ord+'a, OutputSymbol: 'a> {
     // If something other than none, the most recent accepting state
     accept: Option<(usize, &'a OutputSymbol)>,
 
+    // The most recent accepting state before the current state (used if the current state rejects at the end of the input)
+    accept_before: Option<(usize, &'a OutputSymbol)>,
+
+    // If set, accepting states only accept if this returns true
+    accept_predicate: Option<AcceptPredicate<'a, OutputSymbol>>,
+
     // The state machine this is running
     state_machine: &'a SymbolRangeDfa<InputSymbol, OutputSymbol>
 }
 
+impl<'a, InputSymbol: Ord+'a, OutputSymbol: 'a> SymbolRangeState<'a, InputSymbol, OutputSymbol> {
+    ///
+    /// Returns the output symbol for a state if it should accept during this run
+    ///
+    #[inline]
+    fn accepted_output(&self, state: StateId) -> Option<&'a OutputSymbol> {
+        self.if_accepted(state, self.state_machine.accept[state as usize].as_ref())
+    }
+
+    ///
+    /// Returns true if reading more symbols could move the state machine to an accepting state
+    ///
+    /// This is false once the state machine is in a 'dead' state, where no further input can produce a match.
+    ///
+    pub fn can_accept_more_input(&self) -> bool {
+        self.state_machine.can_accept_after(self.state)
+    }
+
+
+    ///
+    /// Returns the output symbol for an accepting state, provided that the accept predicate allows it
+    ///
+    #[inline]
+    fn if_accepted(&self, state: StateId, output: Option<&'a OutputSymbol>) -> Option<&'a OutputSymbol> {
+        let output = output?;
+
+        match self.accept_predicate {
+            Some(predicate) if !predicate(state, output)    => None,
+            _                                               => Some(output)
+        }
+    }
+}
+
 impl<InputSymbol: Ord, OutputSymbol> SymbolRangeDfa<InputSymbol, OutputSymbol> {
     ///
-    /// Returns a `MatchAction` for the initial state of the DFA
+    /// Returns a `MatchAction` for the initial state of the DFA, when matching from the start of the input
     ///
     pub fn start<'a>(&'a self) -> MatchAction<'a, OutputSymbol, SymbolRangeState<'a, InputSymbol, OutputSymbol>> {
-        // TODO: if state 0 is accepting, then this will erroneously not move straight to the accepting state
-        if let Some(ref outputsymbol) = self.accept[0] {
-            More(SymbolRangeState { state: 0, count: 0, accept: Some((0, outputsymbol)), state_machine: self })
-        } else {
-            More(SymbolRangeState { state: 0, count: 0, accept: None, state_machine: self })
+        self.start_state(self.initial_state(true), None)
+    }
+
+    ///
+    /// Returns a `MatchAction` for the initial state of the DFA, when matching from a position after the start of the input
+    ///
+    /// This is the same as `start()` unless the DFA was compiled from a pattern containing a `start_anchor()`, as these can
+    /// only match at the start of the input.
+    ///
+    pub fn start_mid_input<'a>(&'a self) -> MatchAction<'a, OutputSymbol, SymbolRangeState<'a, InputSymbol, OutputSymbol>> {
+        self.start_state(self.initial_state(false), None)
+    }
+
+    ///
+    /// Returns a `MatchAction` for a particular state of the DFA
+    ///
+    /// This can be used to continue matching from a state that was recorded earlier, such as one returned by
+    /// `AnnotatedStream::boundary_state()`.
+    ///
+    pub fn start_from_state<'a>(&'a self, state: StateId) -> MatchAction<'a, OutputSymbol, SymbolRangeState<'a, InputSymbol, OutputSymbol>> {
+        self.start_state(state, None)
+    }
+
+    ///
+    /// Returns the state that matching begins in, depending on whether or not the match begins at the start of the input
+    ///
+    #[inline]
+    pub fn initial_state(&self, at_start_of_input: bool) -> StateId {
+        match self.start_of_input {
+            Some(state) if at_start_of_input    => state,
+            _                                   => 0
         }
     }
 
+    ///
+    /// Returns true if this DFA has a separate initial state for matches at the start of the input
+    ///
+    pub fn has_start_of_input_state(&self) -> bool {
+        self.start_of_input.is_some()
+    }
+
+    ///
+    /// Returns a `MatchAction` for the initial state of the DFA, which will only accept in states where the predicate returns
+    /// true
+    ///
+    /// The predicate is called with the accepting state and its output symbol whenever the DFA reaches an accepting state.
+    /// This makes it possible to decide whether or not to accept a match based on information that's only available when
+    /// the DFA is run, without changing the DFA itself.
+    ///
+    pub fn start_with_accept_predicate<'a>(&'a self, predicate: AcceptPredicate<'a, OutputSymbol>) -> MatchAction<'a, OutputSymbol, SymbolRangeState<'a, InputSymbol, OutputSymbol>> {
+        self.start_state(self.initial_state(true), Some(predicate))
+    }
+
+    ///
+    /// Creates the matching state for this DFA that begins in a particular state
+    ///
+    fn start_state<'a>(&'a self, initial_state: StateId, accept_predicate: Option<AcceptPredicate<'a, OutputSymbol>>) -> MatchAction<'a, OutputSymbol, SymbolRangeState<'a, InputSymbol, OutputSymbol>> {
+        let mut state = SymbolRangeState { state: initial_state, count: 0, accept: None, accept_before: None, accept_predicate, state_machine: self };
+
+        // TODO: if the initial state is accepting, then this will erroneously not move straight to the accepting state
+        state.accept = state.accepted_output(initial_state).map(|output| (0, output));
+
+        More(state)
+    }
+
+    ///
+    /// Matches this DFA against a slice, starting at a particular offset, and returns the length of the longest match
+    ///
+    /// This gives the same result as matching against a reader for `&input[start..]`, but works directly on the slice so
+    /// that scanning for matches at many offsets doesn't need to create a reader for each one. `find_all_in_slice()` uses
+    /// this to search a whole slice.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let dfa     = MatchRange(0u8, 9u8).repeat_forever(1).prepare_to_match();
+    /// let input   = [20, 1, 2, 3, 20, 4];
+    ///
+    /// dfa.match_at_slice(&input, 1); // == Some(3)
+    /// # assert!(dfa.match_at_slice(&input, 1) == Some(3));
+    /// # assert!(dfa.match_at_slice(&input, 0).is_none());
+    /// ```
+    ///
+    pub fn match_at_slice(&self, input: &[InputSymbol], start: usize) -> Option<usize> {
+        let mut state           = self.initial_state(start == 0) as usize;
+        let mut longest         = if self.accept[state].is_some() { Some(0) } else { None };
+        let mut longest_before  = None;
+
+        for (index, symbol) in input.iter().enumerate().skip(start) {
+            let transitions = &self.transitions[self.states[state]..self.states[state+1]];
+            let next_state  = transitions.iter()
+                .find(|(range, _)| range.includes(symbol))
+                .map(|(_, next_state)| *next_state as usize);
+
+            match next_state {
+                Some(next_state) => {
+                    state           = next_state;
+                    longest_before  = longest;
+
+                    if self.accept[state].is_some() {
+                        longest = Some(index+1-start);
+                    }
+                },
+
+                None => return longest
+            }
+        }
+
+        // All of the input was read, so end anchors can succeed
+        match self.accept_at_end.get(state) {
+            Some(OutputAtEnd::Accept(_))    => Some(input.len().saturating_sub(start)),
+            Some(OutputAtEnd::Reject)       => longest_before,
+            _                               => longest
+        }
+    }
+
+    ///
+    /// Returns true if an accepting state can be reached from a state by reading at least one more symbol
+    ///
+    fn can_accept_after(&self, state: StateId) -> bool {
+        let mut visited     = vec![false; self.accept.len()];
+        let mut to_visit    = vec![state as usize];
+
+        while let Some(state) = to_visit.pop() {
+            for &(_, target) in self.transitions[self.states[state]..self.states[state+1]].iter() {
+                let target = target as usize;
+
+                if self.accept[target].is_some() || self.accepts_at_end(target as StateId) {
+                    return true;
+                }
+
+                if !visited[target] {
+                    visited[target] = true;
+                    to_visit.push(target);
+                }
+            }
+        }
+
+        false
+    }
+
+    ///
+    /// Returns true if a state accepts when there are no more symbols in the input, in addition to its usual output
+    ///
+    #[inline]
+    fn accepts_at_end(&self, state: StateId) -> bool {
+        matches!(self.accept_at_end.get(state as usize), Some(OutputAtEnd::Accept(_)))
+    }
+
+    ///
+    /// Returns true if some states of this DFA accept or reject differently when there are no more symbols in the input
+    ///
+    /// This is the case for DFAs compiled from patterns that contain an `end_anchor()`.
+    ///
+    pub fn has_end_of_input_outputs(&self) -> bool {
+        !self.accept_at_end.is_empty()
+    }
+
+    ///
+    /// Returns the output symbol for a state when there are no more symbols in the input, or `None` if it doesn't accept
+    /// at the end of the input
+    ///
+    /// This is the same as `output()` unless the DFA was compiled from a pattern containing an `end_anchor()`.
+    ///
+    pub fn output_at_end(&self, state: StateId) -> Option<&OutputSymbol> {
+        match self.accept_at_end.get(state as usize) {
+            Some(OutputAtEnd::Accept(output))   => Some(output),
+            Some(OutputAtEnd::Reject)           => None,
+            _                                   => self.accept[state as usize].as_ref()
+        }
+    }
+
+    ///
+    /// Returns the IDs of the states in this DFA
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let dfa = exactly("ab").prepare_to_match();
+    ///
+    /// for state in dfa.states() {
+    ///     println!("{}: {} transitions, output {:?}", state, dfa.transitions(state).len(), dfa.output(state));
+    /// }
+    /// # assert!(dfa.states().count() == 3);
+    /// ```
+    ///
+    pub fn states(&self) -> impl Iterator<Item=StateId> {
+        0..(self.accept.len() as StateId)
+    }
+
+    ///
+    /// Returns the transitions out of a state, sorted by symbol range
+    ///
+    /// Unlike `get_transitions_for_state()`, this borrows the transitions from the DFA instead of copying them.
+    ///
+    pub fn transitions(&self, state: StateId) -> &[(SymbolRange<InputSymbol>, StateId)] {
+        &self.transitions[self.states[state as usize]..self.states[state as usize+1]]
+    }
+
+    ///
+    /// Returns the output symbol for a state, or `None` if it's not an accepting state
+    ///
+    #[inline]
+    pub fn output(&self, state: StateId) -> Option<&OutputSymbol> {
+        self.accept[state as usize].as_ref()
+    }
+
     ///
     /// Returns a description of this DFA
     ///
@@ -189,6 +531,335 @@ impl<InputSymbol: Ord, OutputSymbol> SymbolRangeDfa<InputSymbol, OutputSymbol> {
 
         format!("SymbolRangeDfa: {} states, {} total transitions. {} bytes", self.states.len(), self.transitions.len(), total_size)
     }
+
+    ///
+    /// Returns statistics about the size of this DFA
+    ///
+    /// This is useful for finding out why a set of patterns generates a large DFA: for example, a state with a very large
+    /// number of transitions often indicates that a character class has been split up by many overlapping ranges.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let dfa     = exactly("abc").prepare_to_match();
+    /// let stats   = dfa.stats();
+    ///
+    /// // stats.num_states == 4, stats.num_accepting_states == 1
+    /// # assert!(stats.num_states == 4);
+    /// # assert!(stats.num_accepting_states == 1);
+    /// ```
+    ///
+    pub fn stats(&self) -> DfaStats {
+        let num_states = self.accept.len();
+
+        DfaStats {
+            num_states,
+            num_transitions:            self.transitions.len(),
+            num_accepting_states:       (0..num_states).filter(|state| self.accept[*state].is_some() || self.accepts_at_end(*state as StateId)).count(),
+            max_transitions_per_state:  (0..num_states).map(|state| self.states[state+1] - self.states[state]).max().unwrap_or(0)
+        }
+    }
+}
+
+impl<InputSymbol: Ord+Clone, OutputSymbol: Clone> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Returns a copy of this DFA with its states numbered in a standard order, and with any unreachable states removed
+    ///
+    /// States are numbered in the order they're found by a breadth-first search from the start state, following transitions
+    /// in symbol order. DFAs built in different ways will have the same structure after this call, which means that they can
+    /// be compared or hashed.
+    ///
+    pub fn canonicalize(&self) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+        // Work out the new order of the states
+        let mut order: Vec<usize>               = vec![0];
+        let mut new_ids: Vec<Option<StateId>>   = vec![None; self.accept.len()];
+        new_ids[0] = Some(0);
+
+        let mut index = 0;
+        loop {
+            while index < order.len() {
+                let state = order[index];
+
+                for &(_, target) in self.transitions[self.states[state]..self.states[state+1]].iter() {
+                    if new_ids[target as usize].is_none() {
+                        new_ids[target as usize] = Some(order.len() as StateId);
+                        order.push(target as usize);
+                    }
+                }
+
+                index += 1;
+            }
+
+            // The states that are only reachable from the start of input state are numbered after the others
+            match self.start_of_input {
+                Some(state) if new_ids[state as usize].is_none() => {
+                    new_ids[state as usize] = Some(order.len() as StateId);
+                    order.push(state as usize);
+                },
+
+                _ => break
+            }
+        }
+
+        // Generate the new DFA
+        let mut states      = vec![];
+        let mut transitions = vec![];
+        let mut accept      = vec![];
+        let mut at_end      = vec![];
+
+        for state in order {
+            states.push(transitions.len());
+            accept.push(self.accept[state].clone());
+
+            if !self.accept_at_end.is_empty() {
+                at_end.push(self.accept_at_end[state].clone());
+            }
+
+            for (range, target) in self.transitions[self.states[state]..self.states[state+1]].iter() {
+                transitions.push((range.clone(), new_ids[*target as usize].unwrap()));
+            }
+        }
+
+        states.push(transitions.len());
+
+        let start_of_input = self.start_of_input.map(|state| new_ids[state as usize].unwrap());
+
+        SymbolRangeDfa { states, transitions, accept, start_of_input, accept_at_end: at_end }
+    }
+
+    ///
+    /// Returns a copy of this DFA with any states that can't lead to an accepting state removed
+    ///
+    /// Transitions to these 'dead' states are removed too, so the DFA stops as soon as it can no longer match instead of
+    /// reading further symbols. The same strings are accepted as before. The states are renumbered as by `canonicalize()`.
+    ///
+    pub fn prune_dead_states(&self) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+        let num_states = self.accept.len();
+
+        // Find the states that move to each state
+        let mut sources = vec![vec![]; num_states];
+
+        for state in 0..num_states {
+            for &(_, target) in self.transitions[self.states[state]..self.states[state+1]].iter() {
+                sources[target as usize].push(state);
+            }
+        }
+
+        // A state is live if it's accepting or if it has a transition to a live state
+        let mut live: Vec<bool>     = (0..num_states).map(|state| self.accept[state].is_some() || self.accepts_at_end(state as StateId)).collect();
+        let mut to_visit: Vec<usize> = (0..num_states).filter(|state| live[*state]).collect();
+
+        while let Some(state) = to_visit.pop() {
+            for &source in sources[state].iter() {
+                if !live[source] {
+                    live[source] = true;
+                    to_visit.push(source);
+                }
+            }
+        }
+
+        // Remove the transitions to dead states: canonicalizing will then remove the states themselves
+        let mut states      = vec![];
+        let mut transitions = vec![];
+
+        for state in 0..num_states {
+            states.push(transitions.len());
+
+            for (range, target) in self.transitions[self.states[state]..self.states[state+1]].iter() {
+                if live[*target as usize] {
+                    transitions.push((range.clone(), *target));
+                }
+            }
+        }
+
+        states.push(transitions.len());
+
+        SymbolRangeDfa { states, transitions, accept: self.accept.clone(), start_of_input: self.start_of_input, accept_at_end: self.accept_at_end.clone() }.canonicalize()
+    }
+}
+
+impl<InputSymbol: Ord+Clone, OutputSymbol> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Returns a copy of this DFA with the same states and transitions, with every output symbol replaced using a function
+    ///
+    pub fn map_output_symbols<NewOutputSymbol, MapFn: Fn(&OutputSymbol) -> NewOutputSymbol>(&self, map_output: MapFn) -> SymbolRangeDfa<InputSymbol, NewOutputSymbol> {
+        SymbolRangeDfa {
+            states:         self.states.clone(),
+            transitions:    self.transitions.clone(),
+            accept:         self.accept.iter().map(|output| output.as_ref().map(&map_output)).collect(),
+            start_of_input: self.start_of_input,
+            accept_at_end:  self.accept_at_end.iter().map(|output| match output {
+                OutputAtEnd::Unchanged      => OutputAtEnd::Unchanged,
+                OutputAtEnd::Accept(output) => OutputAtEnd::Accept(map_output(output)),
+                OutputAtEnd::Reject         => OutputAtEnd::Reject
+            }).collect()
+        }
+    }
+
+    ///
+    /// Returns a DFA that behaves like this one, except that matches that begin at the very start of the input are made
+    /// using another DFA
+    ///
+    /// This is how patterns containing a `start_anchor()` are compiled: `at_start` is the DFA for the pattern where the
+    /// anchors succeed. The states of `at_start` are added after the states of this DFA.
+    ///
+    pub fn with_start_of_input(self, at_start: SymbolRangeDfa<InputSymbol, OutputSymbol>) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+        let mut result  = self;
+        let offset      = result.accept.len() as StateId;
+        let first_index = result.transitions.len();
+        let num_states  = at_start.accept.len();
+
+        // The outputs at the end of the input only need to be stored if one of the DFAs has them
+        if !result.accept_at_end.is_empty() || !at_start.accept_at_end.is_empty() {
+            let mut at_end = at_start.accept_at_end;
+            at_end.resize_with(num_states, || OutputAtEnd::Unchanged);
+
+            result.accept_at_end.resize_with(offset as usize, || OutputAtEnd::Unchanged);
+            result.accept_at_end.extend(at_end);
+        }
+
+        // The last entry in 'states' caps the final state, and is replaced by the first state of the new DFA
+        result.states.pop();
+        result.states.extend(at_start.states.into_iter().map(|index| index + first_index));
+        result.transitions.extend(at_start.transitions.into_iter().map(|(range, target)| (range, target + offset)));
+        result.accept.extend(at_start.accept);
+        result.start_of_input = Some(at_start.start_of_input.unwrap_or(0) + offset);
+
+        result
+    }
+
+    ///
+    /// Returns the longest sequence of symbols that every string accepted by this DFA must begin with
+    ///
+    /// This is empty if the DFA can accept more than one symbol at the start (for example, if the pattern begins with
+    /// a range). A search can use this to find candidate positions with a fast literal search before running the DFA:
+    /// `find_all_in_slice()` does this. The prefix is for matches that start in state 0, so it doesn't apply at the start of
+    /// the input if the DFA has a separate state for it.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let dfa = exactly("foo").append(MatchRange('0', '9').repeat_forever(1)).prepare_to_match();
+    ///
+    /// dfa.required_prefix(); // == vec!['f', 'o', 'o']
+    /// # assert!(dfa.required_prefix() == vec!['f', 'o', 'o']);
+    /// ```
+    ///
+    pub fn required_prefix(&self) -> Vec<InputSymbol> {
+        let mut prefix  = vec![];
+        let mut state   = 0;
+
+        // Each state in the prefix is visited once, so the prefix can't be longer than the number of states
+        while prefix.len() < self.accept.len() {
+            // Stop at any accepting state, as accepted strings can end here
+            if self.accept[state].is_some() || self.accepts_at_end(state as StateId) {
+                break;
+            }
+
+            // The prefix continues only if there's a single transition for a single symbol
+            let start_transition    = self.states[state];
+            let end_transition      = self.states[state+1];
+
+            if end_transition - start_transition != 1 {
+                break;
+            }
+
+            let (ref range, next_state) = self.transitions[start_transition];
+            if range.lowest != range.highest {
+                break;
+            }
+
+            prefix.push(range.lowest.clone());
+            state = next_state as usize;
+        }
+
+        prefix
+    }
+}
+
+///
+/// A state in two DFAs that are being run together (`None` once a DFA has no transition for the input)
+///
+pub type PairedState = (Option<StateId>, Option<StateId>);
+
+///
+/// Returns the target of the transition that includes a symbol, if there is one
+///
+fn target_for_symbol<Symbol: Ord>(transitions: &[(SymbolRange<Symbol>, StateId)], symbol: &Symbol) -> Option<StateId> {
+    transitions.iter()
+        .find(|(range, _)| range.includes(symbol))
+        .map(|(_, target)| *target)
+}
+
+impl<InputSymbol: Ord+Clone+Countable, OutputSymbol> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Returns the transitions from a pair of states when this DFA and another one are run together
+    ///
+    /// The symbols are split so that each range moves both DFAs to a single state. A state is `None` once its DFA has no
+    /// transition for the input (so it can't accept anything any more), so the pairs of states found by following the
+    /// results cover every string that either DFA can read.
+    ///
+    pub fn paired_transitions<OtherOutputSymbol>(&self, other: &SymbolRangeDfa<InputSymbol, OtherOutputSymbol>, states: PairedState) -> Vec<(SymbolRange<InputSymbol>, PairedState)> {
+        let (our_state, their_state) = states;
+        let our_transitions     = our_state.map(|state| self.transitions(state)).unwrap_or_default();
+        let their_transitions   = their_state.map(|state| other.transitions(state)).unwrap_or_default();
+
+        let mut symbols = SymbolMap::new();
+        for (range, _) in our_transitions.iter().chain(their_transitions.iter()) {
+            symbols.add_range(range);
+        }
+
+        symbols.to_non_overlapping_map().ranges().iter()
+            .map(|range| {
+                let targets = (target_for_symbol(our_transitions, &range.lowest), target_for_symbol(their_transitions, &range.lowest));
+                (range.clone(), targets)
+            })
+            .collect()
+    }
+}
+
+impl<InputSymbol: Ord+Clone+Countable, OutputSymbol: Clone> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Returns a DFA that behaves like this one, except that when there are no more symbols in the input, the outputs of
+    /// another DFA are used
+    ///
+    /// This is how patterns containing an `end_anchor()` are compiled: `at_end` is the DFA for the pattern where the
+    /// anchors succeed. Both DFAs are run together, so each state of the result represents a state from each of them.
+    /// This should be called before `with_start_of_input()`, as only the states reachable from state 0 are combined.
+    ///
+    pub fn with_end_of_input(&self, at_end: &SymbolRangeDfa<InputSymbol, OutputSymbol>) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+        let mut builder                                                         = SymbolRangeDfaBuilder::new();
+        let mut state_ids: HashMap<(Option<StateId>, Option<StateId>), StateId> = HashMap::new();
+        let mut to_process                                                      = vec![(Some(0), Some(0))];
+        let mut next_index                                                      = 0;
+
+        state_ids.insert((Some(0), Some(0)), 0);
+
+        while next_index < to_process.len() {
+            let (our_state, their_state) = to_process[next_index];
+            next_index += 1;
+
+            builder.start_state();
+
+            for (range, target) in self.paired_transitions(at_end, (our_state, their_state)) {
+                let next_id = state_ids.len() as StateId;
+                let target  = *state_ids.entry(target).or_insert_with(|| {
+                    to_process.push(target);
+                    next_id
+                });
+
+                builder.transition(range, target);
+            }
+
+            if let Some(output) = our_state.and_then(|state| self.output_symbol_for_state(state)) {
+                builder.accept(output.clone());
+            }
+
+            if let Some(output) = their_state.and_then(|state| at_end.output_symbol_for_state(state)) {
+                builder.accept_at_end(output.clone());
+            }
+        }
+
+        builder.build()
+    }
 }
 
 impl<'a, InputSymbol: Ord+'a, OutputSymbol: 'a> MatchingState<'a, InputSymbol, OutputSymbol> for SymbolRangeState<'a, InputSymbol, OutputSymbol> {
@@ -208,7 +879,7 @@ impl<'a, InputSymbol: Ord+'a, OutputSymbol: 'a> MatchingState<'a, InputSymbol, O
                 let new_count = self.count+1;
 
                 // If the new state is an accepting state, then remember it in case we reach a rejecting state later
-                let new_accept = if let Some(ref output) = self.state_machine.accept[new_state as usize] {
+                let new_accept = if let Some(output) = self.accepted_output(new_state) {
                     Some((new_count, output))
                 } else {
                     self.accept
@@ -217,15 +888,36 @@ impl<'a, InputSymbol: Ord+'a, OutputSymbol: 'a> MatchingState<'a, InputSymbol, O
                 // Action is 'More'
                 // TODO: might be an option to return Accept or Reject here if the new state has no transitions
                 // (Possible performance advantage, but depends on the regex and input conditions)
-                return More(SymbolRangeState { state: new_state, count: new_count, accept: new_accept, state_machine: self.state_machine });
+                return More(SymbolRangeState { state: new_state, count: new_count, accept: new_accept, accept_before: self.accept, accept_predicate: self.accept_predicate, state_machine: self.state_machine });
             }
         }
 
-        // No matches: finish the state machine
-        self.finish()
+        // No matches: the longest match so far is the result
+        self.longest_match()
     }
 
     fn finish(self) -> MatchAction<'a, OutputSymbol, Self> {
+        // Now that there's no more input, end anchors can succeed. Any match found here is longer than the earlier ones.
+        match self.state_machine.accept_at_end.get(self.state as usize) {
+            Some(OutputAtEnd::Accept(output)) if self.if_accepted(self.state, Some(output)).is_some() => {
+                Accept(self.count, output)
+            },
+
+            Some(OutputAtEnd::Reject) => {
+                // The current state doesn't accept, so the match ends at an earlier state
+                SymbolRangeState { accept: self.accept_before, ..self }.longest_match()
+            },
+
+            _ => self.longest_match()
+        }
+    }
+}
+
+impl<'a, InputSymbol: Ord+'a, OutputSymbol: 'a> SymbolRangeState<'a, InputSymbol, OutputSymbol> {
+    ///
+    /// Stops the state machine, accepting the longest match found so far
+    ///
+    fn longest_match(self) -> MatchAction<'a, OutputSymbol, Self> {
         if let Some(accept_state) = self.accept {
             // We found an accepting state earlier on, so return that
             let (length, symbol) = accept_state;
@@ -240,9 +932,13 @@ impl<'a, InputSymbol: Ord+'a, OutputSymbol: 'a> MatchingState<'a, InputSymbol, O
 #[cfg(test)]
 mod test {
     use super::super::dfa_builder::*;
-    use super::super::symbol_range::*;
     use super::super::pattern_matcher::*;
     use super::super::state_machine::*;
+    use super::super::regular_pattern::*;
+    use super::super::prepare::*;
+    use super::super::symbol_reader::*;
+    use super::super::tokenizer::*;
+    use super::super::matches::*;
     use super::*;
 
     #[test]
@@ -307,4 +1003,216 @@ mod test {
             assert!(false);
         }
     }
+
+    #[test]
+    fn accept_predicate_can_reject_keyword() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Keyword,
+            Identifier
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(exactly("if"), TestToken::Keyword);
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), TestToken::Identifier);
+        let dfa = token_matcher.prepare_to_match();
+
+        let keywords_enabled    = |_: StateId, _: &TestToken| true;
+        let keywords_disabled   = |_: StateId, output: &TestToken| *output != TestToken::Keyword;
+
+        assert!(match_pattern(dfa.start_with_accept_predicate(&keywords_enabled), &mut "if".read_symbols()).is_accepted(&TestToken::Keyword));
+        assert!(match_pattern(dfa.start_with_accept_predicate(&keywords_disabled), &mut "if".read_symbols()).is_accepted(&TestToken::Identifier));
+        assert!(match_pattern(dfa.start_with_accept_predicate(&keywords_disabled), &mut "ifx".read_symbols()).is_accepted(&TestToken::Identifier));
+    }
+
+    #[test]
+    fn required_prefix_for_literal_then_range() {
+        let dfa = exactly("foo").append(MatchRange('0', '9').repeat_forever(1)).prepare_to_match();
+
+        assert!(dfa.required_prefix() == vec!['f', 'o', 'o']);
+    }
+
+    #[test]
+    fn required_prefix_empty_for_range() {
+        let dfa = MatchRange('0', '9').append("foo").prepare_to_match();
+
+        assert!(dfa.required_prefix() == vec![]);
+    }
+
+    #[test]
+    fn required_prefix_stops_at_alternatives() {
+        let dfa = exactly("abc").or("abd").prepare_to_match();
+
+        assert!(dfa.required_prefix() == vec!['a', 'b']);
+    }
+
+    #[test]
+    fn canonical_dfas_can_be_hashed() {
+        use std::collections::HashSet;
+
+        let first   = exactly("abc").prepare_to_match().canonicalize();
+        let second  = "abc".prepare_to_match().canonicalize();
+        let third   = exactly("abd").prepare_to_match().canonicalize();
+
+        let mut set = HashSet::new();
+        set.insert(first);
+        set.insert(second);
+        assert!(set.len() == 1);
+
+        set.insert(third);
+        assert!(set.len() == 2);
+    }
+
+    #[test]
+    fn canonicalize_removes_unreachable_states() {
+        let mut builder = SymbolRangeDfaBuilder::new();
+
+        // State 0: '0', move to state 2 (state 1 is unreachable)
+        builder.start_state();
+        builder.transition(SymbolRange::new(0, 0), 2);
+
+        builder.start_state();
+        builder.transition(SymbolRange::new(1, 1), 2);
+
+        builder.start_state();
+        builder.accept("Success");
+
+        let canonical = builder.build().canonicalize();
+
+        assert!(canonical.count_states() == 2);
+        assert!(canonical.get_transitions_for_state(0) == vec![(SymbolRange::new(0, 0), 1)]);
+        assert!(canonical.output_symbol_for_state(1) == Some(&"Success"));
+    }
+
+    #[test]
+    fn prune_dead_states_removes_branches_that_never_accept() {
+        use super::super::ndfa::*;
+        use super::super::dfa_compiler::*;
+
+        // 'ab' is accepted, but the 'xy' branch never reaches an accepting state
+        let mut ndfa: Ndfa<SymbolRange<char>, ()> = Ndfa::new();
+
+        ndfa.add_transition(0, SymbolRange::new('a', 'a'), 1);
+        ndfa.add_transition(1, SymbolRange::new('b', 'b'), 2);
+        ndfa.add_transition(0, SymbolRange::new('x', 'x'), 3);
+        ndfa.add_transition(3, SymbolRange::new('y', 'y'), 4);
+        ndfa.add_transition(4, SymbolRange::new('y', 'y'), 4);
+        ndfa.set_output_symbol(2, ());
+
+        let dfa     = DfaCompiler::build(ndfa, SymbolRangeDfaBuilder::new());
+        let pruned  = dfa.prune_dead_states();
+
+        assert!(pruned.count_states() < dfa.count_states());
+        assert!(pruned.count_states() == 3);
+        assert!(pruned.get_transitions_for_state(0) == vec![(SymbolRange::new('a', 'a'), 1)]);
+
+        for input in ["ab", "abc", "a", "xy", "xyyy", ""].iter() {
+            assert!(matches_prepared(*input, &pruned) == matches_prepared(*input, &dfa));
+        }
+    }
+
+    #[test]
+    fn match_at_slice_is_same_as_matching_reader() {
+        let dfa     = MatchRange('a', 'z').repeat_forever(1).append(exactly("!").repeat(0..2)).or("123").prepare_to_match();
+        let input   = "ab! 123 x!! 12 zz".chars().collect::<Vec<_>>();
+
+        for start in 0..=input.len() {
+            assert!(dfa.match_at_slice(&input, start) == matches_prepared(&input[start..], &dfa));
+        }
+    }
+
+    #[test]
+    fn dfa_survives_json_round_trip() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+        enum TestToken {
+            Number,
+            Word
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Number);
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), TestToken::Word);
+
+        let dfa                                         = token_matcher.prepare_to_match();
+        let json                                        = ::serde_json::to_string(&dfa).unwrap();
+        let loaded: SymbolRangeDfa<char, TestToken>     = ::serde_json::from_str(&json).unwrap();
+
+        assert!(loaded == dfa);
+
+        let original: Vec<_>    = Tokenizer::new_prepared("12 abc 3".read_symbols(), &dfa).collect();
+        let reloaded: Vec<_>    = Tokenizer::new_prepared("12 abc 3".read_symbols(), &loaded).collect();
+
+        assert!(reloaded == original);
+        assert!(matches_prepared("abc", &loaded) == Some(3));
+    }
+
+    #[test]
+    fn canonicalize_keeps_start_of_input_state() {
+        let dfa         = start_anchor().append("ab").or("cd").prepare_to_match();
+        let canonical   = dfa.canonicalize();
+
+        assert!(canonical.has_start_of_input_state());
+        assert!(canonical.match_at_slice(&['a', 'b'], 0) == Some(2));
+        assert!(canonical.match_at_slice(&['x', 'a', 'b'], 1).is_none());
+        assert!(canonical.match_at_slice(&['x', 'c', 'd'], 1) == Some(2));
+        assert!(canonical.prune_dead_states().match_at_slice(&['a', 'b'], 0) == Some(2));
+    }
+
+    #[test]
+    fn end_of_input_outputs_are_used_at_end_of_slice() {
+        let dfa         = exactly("ab").append(end_anchor()).or("x").prepare_to_match();
+        let canonical   = dfa.canonicalize().prune_dead_states();
+
+        assert!(canonical == dfa.canonicalize());
+        assert!(canonical.match_at_slice(&['a', 'b'], 0) == Some(2));
+        assert!(canonical.match_at_slice(&['x', 'a', 'b'], 1) == Some(2));
+        assert!(canonical.match_at_slice(&['a', 'b', 'c'], 0).is_none());
+        assert!(canonical.match_at_slice(&['x', 'a', 'b', 'c'], 0) == Some(1));
+    }
+
+    #[test]
+    fn stats_count_states_and_transitions() {
+        let mut builder = SymbolRangeDfaBuilder::new();
+
+        // State 0: 'a' or 'e' moves to state 1, 'c' moves to state 2
+        builder.start_state();
+        builder.transition(SymbolRange::new('a', 'a'), 1);
+        builder.transition(SymbolRange::new('c', 'c'), 2);
+        builder.transition(SymbolRange::new('e', 'e'), 1);
+
+        // State 1: accepts, 'x' moves to state 2
+        builder.start_state();
+        builder.transition(SymbolRange::new('x', 'x'), 2);
+        builder.accept(1);
+
+        // State 2: accepts
+        builder.start_state();
+        builder.accept(2);
+
+        let stats = builder.build().stats();
+
+        assert!(stats == DfaStats { num_states: 3, num_transitions: 4, num_accepting_states: 2, max_transitions_per_state: 3 });
+    }
+
+    #[test]
+    fn stats_include_states_that_accept_at_end() {
+        let stats = exactly("ab").append(end_anchor()).prepare_to_match().stats();
+
+        assert!(stats.num_accepting_states == 1);
+        assert!(stats.num_states == 3);
+        assert!(stats.max_transitions_per_state == 1);
+    }
+
+    #[test]
+    fn can_walk_states_and_transitions() {
+        let dfa = exactly("ab").or(MatchRange('0', '9').repeat_forever(1)).prepare_to_match();
+
+        let num_transitions = dfa.states().map(|state| dfa.transitions(state).len()).sum::<usize>();
+        let num_accepting   = dfa.states().filter(|state| dfa.output(*state).is_some()).count();
+
+        assert!(num_transitions == dfa.stats().num_transitions);
+        assert!(num_accepting == dfa.stats().num_accepting_states);
+        assert!(dfa.states().count() == dfa.count_states() as usize);
+        assert!(dfa.states().all(|state| dfa.transitions(state).to_vec() == dfa.get_transitions_for_state(state)));
+    }
 }
@@ -19,9 +19,11 @@
 //!
 
 use std::slice::Iter;
+use std::io;
 use std::io::Read;
-use std::io::Bytes;
+use std::str;
 use std::str::Chars;
+use std::char::ToLowercase;
 use std::marker::PhantomData;
 
 ///
@@ -53,13 +55,17 @@ impl<'a, Symbol: Clone+'a> SymbolSource<'a, Symbol> for &'a Vec<Symbol> {
     }
 }
 
+impl<'a, Symbol: Clone+'a> SymbolSource<'a, Symbol> for &'a [Symbol] {
+    type SymbolReader = Iter<'a, Symbol>;
+
+    fn read_symbols(self) -> Self::SymbolReader {
+        self.iter()
+    }
+}
+
 impl<'a, Symbol: Clone+'a> SymbolReader<Symbol> for Iter<'a, Symbol> {
     fn next_symbol(&mut self) -> Option<Symbol> {
-        if let Some(sym) = self.next() {
-            Some(sym.clone())
-        } else {
-            None
-        }
+        self.next().cloned()
     }
 }
 
@@ -69,18 +75,14 @@ use std::iter::FilterMap;
 impl<I: Iterator, F, Symbol: Clone> SymbolReader<Symbol> for FilterMap<I, F>
 where F: FnMut(I::Item) -> Option<Symbol> {
     fn next_symbol(&mut self) -> Option<Symbol> {
-        if let Some(sym) = self.next() {
-            Some(sym.clone())
-        } else {
-            None
-        }
+        self.next()
     }
 }
 
 // Can read from streams
 
 //
-// TODO: implementing SymbolSource for Read would be nice
+// Implementing SymbolSource directly for Read would be nice.
 // It's just a matter of calling bytes() on the appropriate type, but Rust's type system is simultaneously too clever and not clever enough
 // to allow that in any sensible way. You can't do it an an arbitrary Read because the Bytes object is not sized without knowing its real
 // type; I couldn't figure out a trick using the Self type to get around that, though those do work sometimes. 
@@ -89,6 +91,9 @@ where F: FnMut(I::Item) -> Option<Symbol> {
 // Screwing around with lifetimes and references like below seems like it should work, but Rust doesn't seem to see the implementations on
 // actual streams.
 //
+// Instead, `read_bytes(file)` creates a reader directly, and wrapping a stream in `ByteSource` marks it as something to read
+// bytes from, so `ByteSource(file).read_symbols()` works too.
+//
 
 /*
 impl<'a> SymbolSource<'a, u8> for &'a mut Read {
@@ -112,14 +117,15 @@ impl<'a> SymbolSource<'a, u8> for &'a mut BufRead {
 /// The ByteSymbolReader turns a `std::io::Bytes` object into a symbol reader
 ///
 pub struct ByteSymbolReader<Reader: Read> {
-    bytes: Bytes<Reader>
+    bytes: io::Bytes<Reader>
 }
 
 impl<Reader: Read> ByteSymbolReader<Reader> {
-    pub fn new(bytes: Bytes<Reader>) -> ByteSymbolReader<Reader> {
-        ByteSymbolReader { bytes: bytes }
+    pub fn new(bytes: io::Bytes<Reader>) -> ByteSymbolReader<Reader> {
+        ByteSymbolReader { bytes }
     }
 
+    #[allow(clippy::unbuffered_bytes)]
     pub fn from(reader: Reader) -> ByteSymbolReader<Reader> {
         Self::new(reader.bytes())
     }
@@ -128,13 +134,144 @@ impl<Reader: Read> ByteSymbolReader<Reader> {
 impl<Reader: Read> SymbolReader<u8> for ByteSymbolReader<Reader> {
     fn next_symbol(&mut self) -> Option<u8> {
         if let Some(Ok(sym)) = self.bytes.next() {
-            Some(sym.clone())
+            Some(sym)
         } else {
             None
         }
     }
 }
 
+///
+/// Describes why a `Utf8SymbolReader` stopped before the end of its stream
+///
+#[derive(Debug)]
+pub enum Utf8ReadError {
+    /// The underlying stream returned an error
+    Io(io::Error),
+
+    /// The stream contained bytes that are not valid UTF-8 (these are the bytes of the invalid sequence)
+    InvalidSequence(Vec<u8>)
+}
+
+///
+/// Reads characters from a stream of UTF-8 encoded bytes
+///
+/// Characters are decoded one at a time as they're read, so the stream does not need to be read all at once. If the
+/// stream contains an invalid UTF-8 sequence, or returns an error, then `next_symbol()` will return `None` and the
+/// reason can be retrieved by calling `error()`.
+///
+pub struct Utf8SymbolReader<Reader: Read> {
+    /// The bytes that make up the characters
+    bytes: io::Bytes<Reader>,
+
+    /// Set to the error that stopped this reader, if there is one
+    error: Option<Utf8ReadError>
+}
+
+impl<Reader: Read> Utf8SymbolReader<Reader> {
+    ///
+    /// Creates a new reader that decodes the UTF-8 characters in a stream
+    ///
+    #[allow(clippy::unbuffered_bytes)]
+    pub fn new(reader: Reader) -> Utf8SymbolReader<Reader> {
+        Utf8SymbolReader { bytes: reader.bytes(), error: None }
+    }
+
+    ///
+    /// If this reader stopped due to an error, returns the error
+    ///
+    pub fn error(&self) -> Option<&Utf8ReadError> {
+        self.error.as_ref()
+    }
+
+    ///
+    /// Reads the next character, or returns the error that prevented it from being read
+    ///
+    fn decode_next(&mut self) -> Result<Option<char>, Utf8ReadError> {
+        let first = match self.bytes.next() {
+            None        => return Ok(None),
+            Some(first) => first.map_err(Utf8ReadError::Io)?
+        };
+
+        // The first byte determines how many bytes are in the character
+        let length = match first {
+            0x00..=0x7f => 1,
+            0xc2..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf4 => 4,
+            _           => return Err(Utf8ReadError::InvalidSequence(vec![first]))
+        };
+
+        let mut encoded = vec![first];
+
+        while encoded.len() < length {
+            match self.bytes.next() {
+                Some(next)  => {
+                    let next = next.map_err(Utf8ReadError::Io)?;
+                    encoded.push(next);
+
+                    if next & 0xc0 != 0x80 {
+                        return Err(Utf8ReadError::InvalidSequence(encoded));
+                    }
+                },
+
+                // Stream ended in the middle of a character
+                None        => return Err(Utf8ReadError::InvalidSequence(encoded))
+            }
+        }
+
+        // Let the standard library reject overlong encodings and surrogates
+        match str::from_utf8(&encoded) {
+            Ok(decoded) => Ok(decoded.chars().next()),
+            Err(_)      => Err(Utf8ReadError::InvalidSequence(encoded))
+        }
+    }
+}
+
+impl<Reader: Read> SymbolReader<char> for Utf8SymbolReader<Reader> {
+    fn next_symbol(&mut self) -> Option<char> {
+        if self.error.is_some() {
+            return None;
+        }
+
+        match self.decode_next() {
+            Ok(chr)     => chr,
+            Err(error)  => { self.error = Some(error); None }
+        }
+    }
+}
+
+///
+/// Wraps a stream so that it can be used as a source of byte symbols
+///
+/// For example, `ByteSource(file).read_symbols()` creates a `SymbolReader<u8>` that reads the contents of a file. This is
+/// useful where a `SymbolSource` is needed: `read_bytes()` is the simpler way to create a reader.
+///
+pub struct ByteSource<Reader: Read>(pub Reader);
+
+impl<'a, Reader: Read+'a> SymbolSource<'a, u8> for ByteSource<Reader> {
+    type SymbolReader = ByteSymbolReader<Reader>;
+
+    fn read_symbols(self) -> Self::SymbolReader {
+        ByteSymbolReader::from(self.0)
+    }
+}
+
+///
+/// Creates a symbol reader that reads the bytes from a stream
+///
+/// ```
+/// # use concordance::*;
+/// # use std::io::Cursor;
+/// let mut reader = read_bytes(Cursor::new(b"ELF".to_vec()));
+///
+/// assert!(reader.to_vec() == vec![b'E', b'L', b'F']);
+/// ```
+///
+pub fn read_bytes<Reader: Read>(reader: Reader) -> ByteSymbolReader<Reader> {
+    ByteSymbolReader::from(reader)
+}
+
 //
 // Can read from strings 
 //
@@ -157,7 +294,7 @@ impl<'a> SymbolReader<char> for Chars<'a> {
 //
 // Note the lifetime specifier here (boxes are static by default which can cause a super confusing error message)
 //
-impl<'a, Symbol> SymbolReader<Symbol> for Box<SymbolReader<Symbol>+'a> {
+impl<'a, Symbol> SymbolReader<Symbol> for Box<dyn SymbolReader<Symbol>+'a> {
     fn next_symbol(&mut self) -> Option<Symbol> {
         (**self).next_symbol()
     }
@@ -169,18 +306,28 @@ impl<'a, Symbol> SymbolReader<Symbol> for Box<SymbolReader<Symbol>+'a> {
 pub trait ReaderToVector<Symbol> {
     /// Reads every symbol in this object and returns a vector
     fn to_vec(&mut self) -> Vec<Symbol>;
+
+    /// Reads every symbol in this object and appends them to an existing vector
+    ///
+    /// Readers that are also iterators have an unstable method with the same name, so for those this should be called as
+    /// `ReaderToVector::collect_into(&mut reader, &mut target)`.
+    fn collect_into(&mut self, target: &mut Vec<Symbol>);
 }
 
 impl<Symbol, Reader: SymbolReader<Symbol>> ReaderToVector<Symbol> for Reader {
     #[inline]
     fn to_vec(&mut self) -> Vec<Symbol> {
         let mut result = vec![];
+        self.collect_into(&mut result);
 
+        result
+    }
+
+    #[inline]
+    fn collect_into(&mut self, target: &mut Vec<Symbol>) {
         while let Some(symbol) = self.next_symbol() {
-            result.push(symbol);
+            target.push(symbol);
         }
-
-        result
     }
 }
 
@@ -214,7 +361,7 @@ impl<Symbol, Reader: SymbolReader<Symbol>> MapSymbolReader<Symbol> for Reader {
     where MapFunction: FnMut(Symbol) -> OutputSymbol {
         MappedStream { 
             source_stream:    self, 
-            mapping_function: mapping_function, 
+            mapping_function, 
             phantom:          PhantomData
         }
     }
@@ -235,6 +382,280 @@ where MapFunction: FnMut(InputSymbol) -> OutputSymbol {
     }
 }
 
+///
+/// A symbol stream that only returns the symbols from a source stream that match a predicate
+///
+pub struct FilteredStream<Reader, FilterFunction> {
+    /// The source stream
+    source_stream: Reader,
+
+    /// Returns true for the symbols that should be kept
+    filter_function: FilterFunction
+}
+
+///
+/// Provides a way to remove symbols from a symbol stream
+///
+pub trait FilterSymbolReader<Symbol> : SymbolReader<Symbol>+Sized {
+    /// Creates a stream containing only the symbols in this stream for which the filter function returns true
+    fn filter_symbols<FilterFunction>(self, filter_function: FilterFunction) -> FilteredStream<Self, FilterFunction>
+    where FilterFunction: FnMut(&Symbol) -> bool;
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> FilterSymbolReader<Symbol> for Reader {
+    fn filter_symbols<FilterFunction>(self, filter_function: FilterFunction) -> FilteredStream<Self, FilterFunction>
+    where FilterFunction: FnMut(&Symbol) -> bool {
+        FilteredStream { source_stream: self, filter_function }
+    }
+}
+
+impl<Symbol, FilterFunction, Reader: SymbolReader<Symbol>> SymbolReader<Symbol> for FilteredStream<Reader, FilterFunction>
+where FilterFunction: FnMut(&Symbol) -> bool {
+    fn next_symbol(&mut self) -> Option<Symbol> {
+        // Keep reading until we find a symbol that passes the filter or reach the end of the stream
+        loop {
+            let symbol = self.source_stream.next_symbol()?;
+
+            if (self.filter_function)(&symbol) {
+                return Some(symbol);
+            }
+        }
+    }
+}
+
+///
+/// A symbol stream that pairs each symbol from a source stream with its position
+///
+pub struct PositionedStream<Reader> {
+    /// The source stream
+    source_stream: Reader,
+
+    /// The position of the next symbol to be read
+    position: usize
+}
+
+///
+/// Provides a way to find the position of each symbol read from a stream
+///
+pub trait EnumerateSymbolReader<Symbol> : SymbolReader<Symbol>+Sized {
+    /// Creates a stream that returns each symbol in this stream along with its position (starting at 0)
+    fn with_positions(self) -> PositionedStream<Self>;
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> EnumerateSymbolReader<Symbol> for Reader {
+    fn with_positions(self) -> PositionedStream<Self> {
+        PositionedStream { source_stream: self, position: 0 }
+    }
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> SymbolReader<(usize, Symbol)> for PositionedStream<Reader> {
+    fn next_symbol(&mut self) -> Option<(usize, Symbol)> {
+        let symbol      = self.source_stream.next_symbol()?;
+        let position    = self.position;
+
+        self.position += 1;
+        Some((position, symbol))
+    }
+}
+
+///
+/// A symbol stream that can look at the next symbol without consuming it
+///
+pub struct Peekable<Symbol, Reader: SymbolReader<Symbol>> {
+    /// The source stream
+    source_stream: Reader,
+
+    /// The symbol that was read by the last call to `peek()`, if it hasn't been consumed yet
+    peeked: Option<Option<Symbol>>
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> Peekable<Symbol, Reader> {
+    ///
+    /// Returns the next symbol in the stream without consuming it
+    ///
+    pub fn peek(&mut self) -> Option<&Symbol> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.source_stream.next_symbol());
+        }
+
+        self.peeked.as_ref().and_then(|symbol| symbol.as_ref())
+    }
+}
+
+///
+/// Provides a way to look ahead in a symbol stream
+///
+pub trait PeekableSymbolReader<Symbol> : SymbolReader<Symbol>+Sized {
+    /// Creates a stream that can return the next symbol without consuming it
+    ///
+    /// (This isn't called `peekable()` to avoid clashing with `Iterator::peekable()` for readers that are also iterators)
+    fn peekable_symbols(self) -> Peekable<Symbol, Self>;
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> PeekableSymbolReader<Symbol> for Reader {
+    fn peekable_symbols(self) -> Peekable<Symbol, Self> {
+        Peekable { source_stream: self, peeked: None }
+    }
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> SymbolReader<Symbol> for Peekable<Symbol, Reader> {
+    fn next_symbol(&mut self) -> Option<Symbol> {
+        match self.peeked.take() {
+            Some(peeked)    => peeked,
+            None            => self.source_stream.next_symbol()
+        }
+    }
+}
+
+///
+/// A symbol stream that reads everything from one stream followed by everything from another
+///
+pub struct ChainReader<First, Second> {
+    /// The stream that's read first
+    first: First,
+
+    /// The stream that's read once the first stream is finished
+    second: Second,
+
+    /// True once the first stream has run out of symbols
+    first_finished: bool
+}
+
+///
+/// Provides a way to join symbol streams together
+///
+pub trait ChainSymbolReader<Symbol> : SymbolReader<Symbol>+Sized {
+    /// Creates a stream that reads the symbols in this stream followed by the symbols in another
+    ///
+    /// (This isn't called `chain()` to avoid clashing with `Iterator::chain()` for readers that are also iterators)
+    fn chain_symbols<Second: SymbolReader<Symbol>>(self, second: Second) -> ChainReader<Self, Second>;
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> ChainSymbolReader<Symbol> for Reader {
+    fn chain_symbols<Second: SymbolReader<Symbol>>(self, second: Second) -> ChainReader<Self, Second> {
+        ChainReader { first: self, second, first_finished: false }
+    }
+}
+
+impl<Symbol, First: SymbolReader<Symbol>, Second: SymbolReader<Symbol>> SymbolReader<Symbol> for ChainReader<First, Second> {
+    fn next_symbol(&mut self) -> Option<Symbol> {
+        if !self.first_finished {
+            if let Some(symbol) = self.first.next_symbol() {
+                return Some(symbol);
+            }
+
+            // Don't read from the first stream again once it's finished
+            self.first_finished = true;
+        }
+
+        self.second.next_symbol()
+    }
+}
+
+///
+/// A symbol stream that returns at most a certain number of symbols from a source stream
+///
+pub struct TakeReader<Reader> {
+    /// The source stream
+    source_stream: Reader,
+
+    /// The number of symbols that can still be read
+    remaining: usize
+}
+
+///
+/// A symbol stream that discards a certain number of symbols from the start of a source stream
+///
+pub struct SkipReader<Reader> {
+    /// The source stream
+    source_stream: Reader,
+
+    /// The number of symbols that still need to be skipped
+    to_skip: usize
+}
+
+///
+/// Provides ways to read part of a symbol stream
+///
+/// (These aren't called `take()` and `skip()` to avoid clashing with the `Iterator` methods for readers that are also iterators)
+///
+pub trait LimitSymbolReader<Symbol> : SymbolReader<Symbol>+Sized {
+    /// Creates a stream that returns at most `count` symbols from this stream
+    fn take_symbols(self, count: usize) -> TakeReader<Self>;
+
+    /// Creates a stream that skips the first `count` symbols in this stream
+    fn skip_symbols(self, count: usize) -> SkipReader<Self>;
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> LimitSymbolReader<Symbol> for Reader {
+    fn take_symbols(self, count: usize) -> TakeReader<Self> {
+        TakeReader { source_stream: self, remaining: count }
+    }
+
+    fn skip_symbols(self, count: usize) -> SkipReader<Self> {
+        SkipReader { source_stream: self, to_skip: count }
+    }
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> SymbolReader<Symbol> for TakeReader<Reader> {
+    fn next_symbol(&mut self) -> Option<Symbol> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.remaining -= 1;
+            self.source_stream.next_symbol()
+        }
+    }
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> SymbolReader<Symbol> for SkipReader<Reader> {
+    fn next_symbol(&mut self) -> Option<Symbol> {
+        // Symbols are only skipped once something is read from this stream
+        while self.to_skip > 0 {
+            self.to_skip -= 1;
+            self.source_stream.next_symbol()?;
+        }
+
+        self.source_stream.next_symbol()
+    }
+}
+
+///
+/// A symbol stream that counts the symbols that have been read from another stream
+///
+pub struct CountingReader<Reader> {
+    /// The source stream
+    source_stream: Reader,
+
+    /// The number of symbols that have been read so far
+    count: usize
+}
+
+impl<Reader> CountingReader<Reader> {
+    ///
+    /// Creates a new counting reader that reads symbols from another reader
+    ///
+    pub fn new(source_stream: Reader) -> CountingReader<Reader> {
+        CountingReader { source_stream, count: 0 }
+    }
+
+    ///
+    /// Returns the number of symbols that have been read from this reader
+    ///
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<Symbol, Reader: SymbolReader<Symbol>> SymbolReader<Symbol> for CountingReader<Reader> {
+    fn next_symbol(&mut self) -> Option<Symbol> {
+        let symbol = self.source_stream.next_symbol()?;
+
+        self.count += 1;
+        Some(symbol)
+    }
+}
+
 ///
 /// A VecReader consumes a vector, which can be read out using the SymbolReader trait
 ///
@@ -259,6 +680,183 @@ impl<Symbol> SymbolReader<Symbol> for VecReader<Symbol> {
     }
 }
 
+///
+/// An IterReader reads symbols from any iterator
+///
+pub struct IterReader<I: Iterator> {
+    iterator: I
+}
+
+impl<I: Iterator> IterReader<I> {
+    ///
+    /// Creates a new IterReader that reads symbols from an iterator
+    ///
+    pub fn from_iter(iterator: I) -> IterReader<I> {
+        IterReader { iterator }
+    }
+}
+
+impl<I: Iterator> SymbolReader<I::Item> for IterReader<I> {
+    #[inline]
+    fn next_symbol(&mut self) -> Option<I::Item> {
+        self.iterator.next()
+    }
+}
+
+impl<'a, I: Iterator+'a> SymbolSource<'a, I::Item> for IterReader<I> {
+    type SymbolReader = IterReader<I>;
+
+    fn read_symbols(self) -> Self::SymbolReader {
+        self
+    }
+}
+
+///
+/// Adds the `into_symbol_reader` method to iterators
+///
+pub trait IntoSymbolReader : Iterator+Sized {
+    ///
+    /// Turns this iterator into a symbol reader, without collecting its items first
+    ///
+    fn into_symbol_reader(self) -> IterReader<Self>;
+}
+
+impl<I: Iterator> IntoSymbolReader for I {
+    fn into_symbol_reader(self) -> IterReader<Self> {
+        IterReader::from_iter(self)
+    }
+}
+
+///
+/// Reads characters from another reader, keeping track of the line and column of the next character
+///
+/// Lines and columns are counted from 0. '\n', '\r' and the sequence "\r\n" all end a line.
+///
+pub struct PositionReader<Reader: SymbolReader<char>> {
+    /// The reader where the characters are read from
+    source: Reader,
+
+    /// Number of characters read so far
+    offset: usize,
+
+    /// The line of the next character
+    line: usize,
+
+    /// The column of the next character
+    column: usize,
+
+    /// True if the last character was a '\r' (so a following '\n' is part of the same line ending)
+    after_cr: bool
+}
+
+impl<Reader: SymbolReader<char>> PositionReader<Reader> {
+    ///
+    /// Creates a new reader that tracks the position of the characters in another reader
+    ///
+    pub fn new(source: Reader) -> PositionReader<Reader> {
+        PositionReader { source, offset: 0, line: 0, column: 0, after_cr: false }
+    }
+
+    ///
+    /// Returns the line and column of the next character to be read
+    ///
+    pub fn position(&self) -> (usize, usize) {
+        (self.line, self.column)
+    }
+
+    ///
+    /// Returns the number of characters that have been read so far
+    ///
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<Reader: SymbolReader<char>> SymbolReader<char> for PositionReader<Reader> {
+    fn next_symbol(&mut self) -> Option<char> {
+        let chr = self.source.next_symbol()?;
+
+        match chr {
+            '\n' if self.after_cr  => { },
+            '\n' | '\r'            => { self.line += 1; self.column = 0; },
+            _                      => { self.column += 1; }
+        }
+
+        self.offset     += 1;
+        self.after_cr   = chr == '\r';
+
+        Some(chr)
+    }
+}
+
+///
+/// Reads the symbols from another reader in reverse order
+///
+/// The whole of the source reader has to be read before the last symbol can be returned, so this reads everything into
+/// memory when it's created.
+///
+pub struct ReversedSymbolReader<Symbol> {
+    /// The symbols from the source reader, in their original order
+    symbols: Vec<Symbol>
+}
+
+impl<Symbol> ReversedSymbolReader<Symbol> {
+    ///
+    /// Creates a new reader that returns the symbols from another reader, starting with the last one
+    ///
+    pub fn new<Reader: SymbolReader<Symbol>>(source: Reader) -> ReversedSymbolReader<Symbol> {
+        let mut source = source;
+
+        ReversedSymbolReader { symbols: source.to_vec() }
+    }
+}
+
+impl<Symbol> SymbolReader<Symbol> for ReversedSymbolReader<Symbol> {
+    fn next_symbol(&mut self) -> Option<Symbol> {
+        self.symbols.pop()
+    }
+}
+
+///
+/// Reads characters from another reader, converting them to lower case
+///
+/// Matching a lower case pattern against this reader is a simple way to do a case-insensitive match. Note that some
+/// characters (such as 'İ') become more than one character when converted to lower case, so the positions of symbols
+/// read from this reader won't always match up to positions in the original stream.
+///
+pub struct LowercaseCharReader<Reader: SymbolReader<char>> {
+    /// The reader where the original characters are read from
+    source: Reader,
+
+    /// Characters that are still waiting to be returned from the last character that was converted
+    pending: Option<ToLowercase>
+}
+
+impl<Reader: SymbolReader<char>> LowercaseCharReader<Reader> {
+    ///
+    /// Creates a new reader that returns the lower case version of the characters in another reader
+    ///
+    pub fn new(source: Reader) -> LowercaseCharReader<Reader> {
+        LowercaseCharReader { source, pending: None }
+    }
+}
+
+impl<Reader: SymbolReader<char>> SymbolReader<char> for LowercaseCharReader<Reader> {
+    fn next_symbol(&mut self) -> Option<char> {
+        loop {
+            // Return any characters left over from the last conversion
+            if let Some(ref mut pending) = self.pending {
+                if let Some(chr) = pending.next() {
+                    return Some(chr);
+                }
+            }
+
+            // Convert the next character from the source
+            self.pending = Some(self.source.next_symbol()?.to_lowercase());
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -274,6 +872,31 @@ mod test {
         assert!(reader.next_symbol() == None);
     }
 
+    #[test]
+    fn can_read_from_slice() {
+        let source      = [1, 2, 3, 4];
+        let mut reader  = source[1..3].read_symbols();
+
+        assert!(reader.next_symbol() == Some(2));
+        assert!(reader.next_symbol() == Some(3));
+        assert!(reader.next_symbol().is_none());
+    }
+
+    #[test]
+    fn counting_reader_counts_symbols() {
+        let mut reader = CountingReader::new("abcd".read_symbols());
+
+        assert!(reader.count() == 0);
+        assert!(reader.next_symbol() == Some('a'));
+        assert!(reader.next_symbol() == Some('b'));
+        assert!(reader.next_symbol() == Some('c'));
+        assert!(reader.count() == 3);
+
+        assert!(reader.next_symbol() == Some('d'));
+        assert!(reader.next_symbol().is_none());
+        assert!(reader.count() == 4);
+    }
+
     #[test]
     fn can_read_from_vecreader() {
         let source      = vec![1, 2, 3];
@@ -285,6 +908,16 @@ mod test {
         assert!(reader.next_symbol() == None);
     }
 
+    #[test]
+    fn can_read_from_iterator() {
+        let mut reader = (0u32..3).into_symbol_reader();
+
+        assert!(reader.next_symbol() == Some(0));
+        assert!(reader.next_symbol() == Some(1));
+        assert!(reader.next_symbol() == Some(2));
+        assert!(reader.next_symbol().is_none());
+    }
+
     #[test]
     fn can_convert_to_vec() {
         let source      = vec![1, 2, 3];
@@ -294,6 +927,25 @@ mod test {
         assert!(result == vec![1, 2, 3]);
     }
 
+    #[test]
+    fn can_collect_into_existing_vec() {
+        let mut buffer  = vec![];
+        let mut first   = VecReader::from_vec(vec!['a', 'b', 'c']);
+        let mut second  = VecReader::from_vec(vec!['d', 'e']);
+
+        first.collect_into(&mut buffer);
+        assert!(buffer.len() == 3);
+        assert!(first.next_symbol().is_none());
+
+        second.collect_into(&mut buffer);
+        assert!(buffer == vec!['a', 'b', 'c', 'd', 'e']);
+
+        // Clearing the buffer keeps its allocation for the next reader
+        buffer.clear();
+        ReaderToVector::collect_into(&mut "xy".read_symbols(), &mut buffer);
+        assert!(buffer == vec!['x', 'y']);
+    }
+
     #[test]
     fn can_map_stream() {
         let source = vec![1, 2, 3];
@@ -303,6 +955,96 @@ mod test {
         assert!(result == vec![2, 3, 4]);
     }
 
+    #[test]
+    fn can_filter_stream() {
+        let mut reader = "a b  c ".read_symbols().filter_symbols(|chr| *chr != ' ');
+
+        assert!(reader.to_vec() == vec!['a', 'b', 'c']);
+        assert!(reader.next_symbol().is_none());
+    }
+
+    #[test]
+    fn can_read_with_positions() {
+        let mut reader = "abc".read_symbols().with_positions();
+
+        assert!(reader.next_symbol() == Some((0, 'a')));
+        assert!(reader.next_symbol() == Some((1, 'b')));
+        assert!(reader.next_symbol() == Some((2, 'c')));
+        assert!(reader.next_symbol().is_none());
+    }
+
+    #[test]
+    fn peeking_does_not_advance_stream() {
+        let source      = vec![1, 2];
+        let mut reader  = source.read_symbols().peekable_symbols();
+
+        assert!(reader.peek() == Some(&1));
+        assert!(reader.peek() == Some(&1));
+        assert!(reader.next_symbol() == Some(1));
+        assert!(reader.next_symbol() == Some(2));
+        assert!(reader.peek().is_none());
+        assert!(reader.next_symbol().is_none());
+    }
+
+    #[test]
+    fn peeked_symbol_is_included_in_vec() {
+        let mut reader = "abc".read_symbols().peekable_symbols();
+
+        assert!(reader.peek() == Some(&'a'));
+        assert!(reader.to_vec() == vec!['a', 'b', 'c']);
+    }
+
+    #[test]
+    fn can_chain_readers() {
+        let first   = vec![1, 2];
+        let second  = vec![3, 4];
+        let mut reader = first.read_symbols().chain_symbols(second.read_symbols());
+
+        assert!(reader.to_vec() == vec![1, 2, 3, 4]);
+        assert!(reader.next_symbol().is_none());
+    }
+
+    #[test]
+    fn can_chain_empty_reader() {
+        let empty: Vec<char> = vec![];
+        let mut reader = empty.read_symbols().chain_symbols("ab".read_symbols()).chain_symbols(VecReader::from_vec(vec![]));
+
+        assert!(reader.to_vec() == vec!['a', 'b']);
+    }
+
+    #[test]
+    fn can_skip_then_take() {
+        let source      = vec![1, 2, 3, 4];
+        let mut reader  = source.read_symbols().skip_symbols(1).take_symbols(2);
+
+        assert!(reader.to_vec() == vec![2, 3]);
+        assert!(reader.next_symbol().is_none());
+    }
+
+    #[test]
+    fn take_and_skip_past_end() {
+        let source = vec![1, 2];
+
+        assert!(source.read_symbols().take_symbols(5).to_vec() == vec![1, 2]);
+        assert!(source.read_symbols().skip_symbols(5).to_vec().is_empty());
+    }
+
+    #[test]
+    fn take_and_skip_are_lazy() {
+        let source  = vec![1, 2, 3];
+        let mut read_count = 0;
+
+        {
+            let counting    = source.read_symbols().map_symbols(|symbol| { read_count += 1; symbol });
+            let mut reader  = counting.skip_symbols(1).take_symbols(1);
+
+            assert!(reader.next_symbol() == Some(2));
+            assert!(reader.next_symbol().is_none());
+        }
+
+        assert!(read_count == 2);
+    }
+
     #[test]
     fn can_read_from_bytes_reader() {
         let array: [u8; 3] = [1, 2, 3];
@@ -315,6 +1057,69 @@ mod test {
         assert!(reader.next_symbol() == None);
     }
 
+    #[test]
+    fn can_read_bytes_from_cursor() {
+        use std::io::Cursor;
+
+        let mut reader = ByteSource(Cursor::new(vec![1u8, 2, 3])).read_symbols();
+        assert!(reader.to_vec() == vec![1, 2, 3]);
+
+        let mut reader = read_bytes(Cursor::new(vec![4u8, 5]));
+        assert!(reader.next_symbol() == Some(4));
+        assert!(reader.next_symbol() == Some(5));
+        assert!(reader.next_symbol().is_none());
+    }
+
+    ///
+    /// Stream that returns one byte from each call to read()
+    ///
+    struct OneByteAtATime(Vec<u8>, usize);
+
+    impl Read for OneByteAtATime {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.1 >= self.0.len() || buf.is_empty() {
+                Ok(0)
+            } else {
+                buf[0] = self.0[self.1];
+                self.1 += 1;
+                Ok(1)
+            }
+        }
+    }
+
+    #[test]
+    fn can_decode_utf8_split_across_reads() {
+        let mut reader = Utf8SymbolReader::new(OneByteAtATime("aé€😀".as_bytes().to_vec(), 0));
+
+        assert!(reader.to_vec() == vec!['a', 'é', '€', '😀']);
+        assert!(reader.error().is_none());
+    }
+
+    #[test]
+    fn utf8_reader_stops_at_invalid_sequence() {
+        let mut reader = Utf8SymbolReader::new(&[b'a', 0xe2, 0x82, b'b', b'c'][..]);
+
+        assert!(reader.next_symbol() == Some('a'));
+        assert!(reader.next_symbol().is_none());
+        assert!(reader.next_symbol().is_none());
+
+        match reader.error() {
+            Some(Utf8ReadError::InvalidSequence(bytes)) => assert!(bytes == &vec![0xe2, 0x82, b'b']),
+            _                                           => panic!()
+        }
+    }
+
+    #[test]
+    fn utf8_reader_rejects_truncated_and_overlong_characters() {
+        let mut truncated   = Utf8SymbolReader::new(&[0xf0, 0x9f][..]);
+        let mut overlong    = Utf8SymbolReader::new(&[0xe0, 0x80, 0x80][..]);
+
+        assert!(truncated.next_symbol().is_none());
+        assert!(truncated.error().is_some());
+        assert!(overlong.next_symbol().is_none());
+        assert!(overlong.error().is_some());
+    }
+
     #[test]
     fn can_read_from_string_reader() {
         let mut reader = "abc".read_symbols();
@@ -324,4 +1129,51 @@ mod test {
         assert!(reader.next_symbol() == Some('c'));
         assert!(reader.next_symbol() == None);
     }
+
+    #[test]
+    fn can_reverse_reader() {
+        let mut reader = ReversedSymbolReader::new("testing".read_symbols());
+
+        assert!(reader.next_symbol() == Some('g'));
+        assert!(reader.to_vec() == vec!['n', 'i', 't', 's', 'e', 't']);
+        assert!(reader.next_symbol().is_none());
+    }
+
+    #[test]
+    fn position_reader_tracks_lines_and_columns() {
+        let mut reader = PositionReader::new("ab\ncd".read_symbols());
+
+        assert!(reader.position() == (0, 0));
+        reader.next_symbol();
+        reader.next_symbol();
+        assert!(reader.position() == (0, 2));
+        assert!(reader.next_symbol() == Some('\n'));
+        assert!(reader.position() == (1, 0));
+        reader.next_symbol();
+        assert!(reader.position() == (1, 1));
+        assert!(reader.offset() == 4);
+    }
+
+    #[test]
+    fn position_reader_treats_crlf_as_one_line_ending() {
+        let mut reader = PositionReader::new("a\r\nb\rc\n\nd".read_symbols());
+
+        assert!(reader.to_vec().len() == 9);
+        assert!(reader.position() == (4, 1));
+        assert!(reader.offset() == 9);
+    }
+
+    #[test]
+    fn can_lowercase_string_reader() {
+        let mut reader = LowercaseCharReader::new("AbC".read_symbols());
+
+        assert!(reader.to_vec() == vec!['a', 'b', 'c']);
+    }
+
+    #[test]
+    fn lowercase_can_produce_extra_characters() {
+        let mut reader = LowercaseCharReader::new("\u{130}X".read_symbols());
+
+        assert!(reader.to_vec() == vec!['i', '\u{307}', 'x']);
+    }
 }
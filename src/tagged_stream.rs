@@ -76,7 +76,7 @@ impl<Base: Ord+Clone, Tag: Ord+Clone+'static> TaggedStream<Base, Tag> {
     ///
     /// Creates a basic tagged stream from a source of the base symbol
     ///
-    pub fn from_reader(reader: &mut SymbolReader<Base>) -> TaggedStream<Base, Tag> {
+    pub fn from_reader(reader: &mut dyn SymbolReader<Base>) -> TaggedStream<Base, Tag> {
         // Read from the reader into an array
         let mut symbols = vec![];
 
@@ -95,6 +95,13 @@ impl<Base: Ord+Clone, Tag: Ord+Clone+'static> TaggedStream<Base, Tag> {
         self.data.len()
     }
 
+    ///
+    /// True if this stream contains no symbols
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
     ///
     /// Replaces a range in this stream with a tag
     ///
@@ -188,7 +195,7 @@ impl<Base: Ord+Countable+Clone, Tag: Ord+Clone+'static> TaggedStream<Base, Tag>
     ///
     /// Creates a tagged stream by tokenizing the contents of a symbol source
     ///
-    pub fn from_tokenized_reader(reader: &mut SymbolReader<Base>, token_matcher: &SymbolRangeDfa<Base, Tag>) -> TaggedStream<Base, Tag> {
+    pub fn from_tokenized_reader(reader: &mut dyn SymbolReader<Base>, token_matcher: &SymbolRangeDfa<Base, Tag>) -> TaggedStream<Base, Tag> {
         TaggedStream::from_reader(reader).tokenize(token_matcher, | symbol | {
             match symbol {
                 Untagged(c)  => c,
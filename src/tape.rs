@@ -70,6 +70,50 @@ impl<Symbol: Clone+Sized, SourceReader: SymbolReader<Symbol>> Tape<Symbol, Sourc
         }
     }
 
+    ///
+    /// Creates a tape that continues from a position in a stream
+    ///
+    /// The `lookahead` symbols are read before any symbols from the source: they're usually the result of calling
+    /// `lookahead()` on the tape that's being continued.
+    ///
+    pub fn resume(source: SourceReader, position: usize, lookahead: Vec<Symbol>) -> Tape<Symbol, SourceReader> {
+        let mut tape = Tape::new(source);
+
+        tape.source_position = position;
+
+        for symbol in lookahead {
+            if (tape.last_symbol_index+1)%tape.buffer.len() == tape.first_symbol_index {
+                tape.resize();
+            }
+
+            tape.buffer[tape.last_symbol_index] = Some(symbol);
+
+            tape.last_symbol_index += 1;
+            if tape.last_symbol_index >= tape.buffer.len() { tape.last_symbol_index = 0; }
+        }
+
+        tape
+    }
+
+    ///
+    /// Returns the symbols that have been read from the source but that are after the current read position
+    ///
+    pub fn lookahead(&self) -> Vec<Symbol> {
+        let mut result      = vec![];
+        let mut read_index  = self.read_index;
+
+        while read_index != self.last_symbol_index {
+            if let Some(ref symbol) = self.buffer[read_index] {
+                result.push(symbol.clone());
+            }
+
+            read_index += 1;
+            if read_index >= self.buffer.len() { read_index = 0; }
+        }
+
+        result
+    }
+
     ///
     /// Resizes the buffer so that it can store at least one more symbol
     ///
@@ -148,6 +192,13 @@ impl<Symbol: Clone+Sized, SourceReader: SymbolReader<Symbol>> Tape<Symbol, Sourc
         self.source_position
     }
 
+    ///
+    /// Returns the reader that symbols are read from when they're not in the buffer
+    ///
+    pub fn source_reader(&self) -> &SourceReader {
+        &self.read_from
+    }
+
     pub fn at_end_of_reader(&self) -> bool {
         self.end_of_reader && self.read_index == self.last_symbol_index
     }
@@ -20,6 +20,12 @@
 //! symbols, then the output symbol that is ordered lower is the one that's produced (ie, if the output symbols are numbers, then '0' will
 //! be produced instead of '1' in the event of a clash)
 //!
+//! The priority mode of a `TokenMatcher` can be changed to `PriorityMode::LongestThenInsertion` so that the pattern that was added
+//! first is the one that's produced instead, regardless of how the output symbols are ordered.
+//!
+//! For analysing a set of patterns, `TokenMatcher::prepare_with_all_outputs()` creates a DFA where each token produces
+//! the output symbols of every pattern that matched it, rather than just the one that wins.
+//!
 
 use std::ops::Range;
 
@@ -34,12 +40,38 @@ use super::symbol_reader::*;
 use super::pattern_matcher::*;
 use super::matches::*;
 use super::tape::*;
+use super::dfa_compiler::*;
+use super::annotated_stream::*;
+
+///
+/// Decides which pattern produces a token when more than one pattern matches the same input
+///
+/// The longest match always wins: the priority mode only decides between patterns that match the same number of symbols.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PriorityMode {
+    /// The pattern with the output symbol that's ordered lowest wins
+    #[default]
+    LongestThenOrder,
+
+    /// The pattern that was added to the token matcher first wins
+    LongestThenInsertion
+}
 
 ///
 /// Used for generating tokenizing pattern matchers
 ///
 pub struct TokenMatcher<InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord> {
-    patterns: Vec<(Pattern<InputSymbol>, OutputSymbol)>
+    patterns: Vec<(Pattern<InputSymbol>, OutputSymbol)>,
+
+    /// How clashes between patterns are resolved
+    priority_mode: PriorityMode
+}
+
+impl<InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static> Default for TokenMatcher<InputSymbol, OutputSymbol> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static> TokenMatcher<InputSymbol, OutputSymbol> {
@@ -47,7 +79,7 @@ impl<InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static>
     /// Creates a new TokenMatcher
     ///
     pub fn new() -> TokenMatcher<InputSymbol, OutputSymbol> {
-        TokenMatcher { patterns: vec![] }
+        TokenMatcher { patterns: vec![], priority_mode: PriorityMode::default() }
     }
 
     ///
@@ -57,18 +89,77 @@ impl<InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static>
         self.patterns.push((pattern.to_pattern(), output));
     }
 
+    ///
+    /// Sets how the output symbol is chosen when more than one pattern matches the same input
+    ///
+    pub fn set_priority_mode(&mut self, priority_mode: PriorityMode) {
+        self.priority_mode = priority_mode;
+    }
+
+    ///
+    /// Returns the number of patterns that have been added to this TokenMatcher
+    ///
+    pub fn count_patterns(&self) -> usize {
+        self.patterns.len()
+    }
+
+    ///
+    /// Removes any patterns that are duplicates of an earlier pattern with the same output symbol
+    ///
+    /// Patterns are treated as duplicates if they match exactly the same strings. Patterns that match the same strings
+    /// but that produce a different output symbol are not removed: instead, the pairs of output symbols for these patterns
+    /// are returned, earliest pattern first. Which of the two the tokenizer produces depends on the priority mode: the
+    /// lower output symbol for `LongestThenOrder`, or the first of the pair for `LongestThenInsertion`.
+    ///
+    pub fn deduplicate(&mut self) -> Vec<(OutputSymbol, OutputSymbol)> {
+        let mut kept: Vec<(SymbolRangeDfa<InputSymbol, ()>, Pattern<InputSymbol>, OutputSymbol)> = vec![];
+        let mut conflicts = vec![];
+
+        for (pattern, output) in self.patterns.drain(..) {
+            let dfa = pattern.clone().prepare_to_match();
+
+            // Nothing to do if there's an equivalent pattern with the same output
+            if kept.iter().any(|(existing_dfa, _, existing_output)| *existing_output == output && existing_dfa.is_equivalent(&dfa)) {
+                continue;
+            }
+
+            // Report any equivalent patterns with different outputs
+            for (existing_dfa, _, existing_output) in kept.iter() {
+                if existing_dfa.is_equivalent(&dfa) {
+                    conflicts.push((existing_output.clone(), output.clone()));
+                }
+            }
+
+            kept.push((dfa, pattern, output));
+        }
+
+        self.patterns = kept.into_iter().map(|(_, pattern, output)| (pattern, output)).collect();
+
+        conflicts
+    }
+
     ///
     /// Compiles an NDFA from this TokenMatcher
     ///
-    pub fn to_ndfa(&self) -> Box<StateMachine<SymbolRange<InputSymbol>, OutputSymbol>> {
+    pub fn to_ndfa(&self) -> Box<dyn StateMachine<SymbolRange<InputSymbol>, OutputSymbol>> {
+        self.to_ndfa_with_outputs(|_, output| output.clone())
+    }
+
+    ///
+    /// Compiles an NDFA from this TokenMatcher, using a function to generate the output symbol for each pattern
+    ///
+    /// The function is called with the index of each pattern (in the order they were added) and its output symbol.
+    ///
+    pub fn to_ndfa_with_outputs<NewOutputSymbol: 'static, MapFn>(&self, map_output: MapFn) -> Box<dyn StateMachine<SymbolRange<InputSymbol>, NewOutputSymbol>>
+    where MapFn: Fn(usize, &OutputSymbol) -> NewOutputSymbol {
         let mut ndfa = Ndfa::new();
 
-        for &(ref pattern, ref output) in &self.patterns {
-            // Compile each pattern starting at state 0
-            let end_state = pattern.compile(&mut ndfa, 0);
+        for (index, (pattern, output)) in self.patterns.iter().enumerate() {
+            // Compile each pattern starting at state 0 (simplifying it first, so patterns built with or() produce fewer states)
+            let end_state = pattern.simplify().compile(&mut ndfa, 0);
 
             // Set the output for this pattern
-            ndfa.set_output_symbol(end_state, output.clone());
+            ndfa.set_output_symbol(end_state, map_output(index, output));
         }
 
         // Clear out any overlapping ranges so we can build a valid DFA
@@ -78,13 +169,130 @@ impl<InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static>
     }
 }
 
-impl<'a, InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static> PrepareToMatch<SymbolRangeDfa<InputSymbol, OutputSymbol>> 
-for &'a TokenMatcher<InputSymbol, OutputSymbol> {
-    #[inline]
+impl<InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static> PrepareToMatch<SymbolRangeDfa<InputSymbol, OutputSymbol>> 
+for &TokenMatcher<InputSymbol, OutputSymbol> {
     fn prepare_to_match(self) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
-        let ndfa = self.to_ndfa();
+        self.compile_with_anchors(|matcher| matcher.compile_dfa())
+    }
+}
+
+impl<InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static> TokenMatcher<InputSymbol, OutputSymbol> {
+    ///
+    /// Creates a DFA where each token produces the output symbols of every pattern that matched it
+    ///
+    /// The normal DFA produces a single output symbol for each token, chosen by the priority mode. This keeps all of the
+    /// candidates instead, which is useful for finding out which patterns overlap. The outputs are in priority order, so
+    /// the first one is the output that `prepare_to_match()` would produce.
+    ///
+    /// ```
+    /// # use concordance::*;
+    /// let mut token_matcher = TokenMatcher::new();
+    /// token_matcher.add_pattern(exactly("if"), "Keyword");
+    /// token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), "Word");
+    ///
+    /// let dfa = token_matcher.prepare_with_all_outputs();
+    ///
+    /// matches_with_output("if", dfa.clone());   // == Some((2, vec!["Keyword", "Word"]))
+    /// # assert!(matches_with_output("if", dfa) == Some((2, vec!["Keyword", "Word"])));
+    /// ```
+    ///
+    pub fn prepare_with_all_outputs(&self) -> SymbolRangeDfa<InputSymbol, Vec<OutputSymbol>> {
+        self.compile_with_anchors(|matcher| {
+            // Pair each output with the index of its pattern when the order they were added in decides the priority
+            let by_insertion    = matcher.priority_mode == PriorityMode::LongestThenInsertion;
+            let ndfa            = matcher.to_ndfa_with_outputs(|index, output| vec![(if by_insertion { index } else { 0 }, output.clone())]);
+            let dfa             = DfaCompiler::build_with_policy(ndfa, SymbolRangeDfaBuilder::new(), &AllOutputs);
+
+            // A symbol can be produced by more than one pattern, in which case it's only kept in its highest priority position
+            dfa.map_output_symbols(|outputs| {
+                let mut unique_outputs: Vec<OutputSymbol> = vec![];
+
+                for (_, output) in outputs.iter() {
+                    if !unique_outputs.contains(output) {
+                        unique_outputs.push(output.clone());
+                    }
+                }
+
+                unique_outputs
+            })
+        })
+    }
+
+    ///
+    /// Creates a DFA where each output symbol is paired with the index of the pattern that produced it
+    ///
+    /// The output symbols are chosen in the same way as for `prepare_to_match()`, so this can be used to find out which
+    /// pattern was responsible for each token.
+    ///
+    pub fn prepare_with_pattern_indexes(&self) -> SymbolRangeDfa<InputSymbol, (OutputSymbol, usize)> {
+        self.compile_with_anchors(|matcher| {
+            match matcher.priority_mode {
+                // Output symbols are ordered first, so clashes are resolved by the output symbol
+                PriorityMode::LongestThenOrder      => matcher.to_ndfa_with_outputs(|index, output| (output.clone(), index)).prepare_to_match(),
+
+                PriorityMode::LongestThenInsertion  => {
+                    // Pattern indexes are ordered first, so clashes are resolved by the earliest pattern
+                    let ndfa                                                    = matcher.to_ndfa_with_outputs(|index, output| (index, output.clone()));
+                    let dfa: SymbolRangeDfa<InputSymbol, (usize, OutputSymbol)> = ndfa.prepare_to_match();
+
+                    dfa.map_output_symbols(|(index, output)| (output.clone(), *index))
+                }
+            }
+        })
+    }
+
+    ///
+    /// Returns a TokenMatcher with the same outputs and priority mode as this one, with each pattern replaced by a function
+    ///
+    fn map_patterns<MapFn: Fn(&Pattern<InputSymbol>) -> Pattern<InputSymbol>>(&self, map_pattern: MapFn) -> TokenMatcher<InputSymbol, OutputSymbol> {
+        TokenMatcher {
+            patterns:       self.patterns.iter().map(|(pattern, output)| (map_pattern(pattern), output.clone())).collect(),
+            priority_mode:  self.priority_mode
+        }
+    }
+
+    ///
+    /// Compiles the DFA for this TokenMatcher using a function, adding the states needed for any start or end anchors
+    /// in its patterns
+    ///
+    fn compile_with_anchors<NewOutputSymbol: Clone, CompileFn>(&self, compile: CompileFn) -> SymbolRangeDfa<InputSymbol, NewOutputSymbol>
+    where CompileFn: Fn(&TokenMatcher<InputSymbol, OutputSymbol>) -> SymbolRangeDfa<InputSymbol, NewOutputSymbol> {
+        // Patterns with end anchors need to know what to output if the input ends in each state
+        let with_end_of_input = |matcher: &TokenMatcher<InputSymbol, OutputSymbol>| {
+            let dfa = compile(matcher);
+
+            if matcher.patterns.iter().any(|(pattern, _)| pattern.has_end_anchor()) {
+                dfa.with_end_of_input(&compile(&matcher.map_patterns(|pattern| pattern.at_end_of_input())))
+            } else {
+                dfa
+            }
+        };
+
+        let dfa = with_end_of_input(self);
+
+        // Patterns with start anchors need a separate DFA for matching at the start of the input
+        if self.patterns.iter().any(|(pattern, _)| pattern.has_start_anchor()) {
+            dfa.with_start_of_input(with_end_of_input(&self.map_patterns(|pattern| pattern.at_start_of_input())))
+        } else {
+            dfa
+        }
+    }
 
-        ndfa.prepare_to_match()
+    ///
+    /// Compiles the DFA for this TokenMatcher, using the priority mode to decide between patterns that match the same input
+    ///
+    fn compile_dfa(&self) -> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+        match self.priority_mode {
+            PriorityMode::LongestThenOrder      => self.to_ndfa().prepare_to_match(),
+
+            PriorityMode::LongestThenInsertion  => {
+                // Pair each output with the index of its pattern so the earliest pattern is the lowest output
+                let ndfa                                                    = self.to_ndfa_with_outputs(|index, output| (index, output.clone()));
+                let dfa: SymbolRangeDfa<InputSymbol, (usize, OutputSymbol)> = ndfa.prepare_to_match();
+
+                dfa.map_output_symbols(|(_, output)| output.clone())
+            }
+        }
     }
 }
 
@@ -105,13 +313,63 @@ use self::ReferenceOrOwned::*;
 impl<'a, Thing> ReferenceOrOwned<'a, Thing> {
     #[inline]
     fn get(&'a self) -> &'a Thing {
-        match self {
-            &Reference(ref thing)    => *thing,
-            &Owned(ref thing)        => thing
+        match *self {
+            Reference(thing)    => thing,
+            Owned(ref thing)    => thing
         }
     }
 }
 
+///
+/// A token that also contains the input symbols that it was matched from
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RichToken<InputSymbol, OutputSymbol> {
+    /// The output symbol that was generated for this token
+    pub output: OutputSymbol,
+
+    /// The input symbols that were matched
+    pub symbols: Vec<InputSymbol>,
+
+    /// The range of symbols in the input that make up this token
+    pub location: Range<usize>
+}
+
+///
+/// A token along with the input that was skipped before it
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct TriviaToken<OutputSymbol> {
+    /// The output symbol that was generated for this token
+    pub output: OutputSymbol,
+
+    /// The range of symbols in the input that make up this token
+    pub location: Range<usize>,
+
+    /// The range of symbols between the end of the previous token and the start of this one, which didn't match any token
+    pub leading_trivia: Range<usize>
+}
+
+///
+/// The state of a tokenizer between tokens, which can be used to resume tokenizing elsewhere
+///
+/// The tokenizer always finishes matching a token before returning, so its state machine is always in the start state
+/// between calls. This means that the only information needed to resume is the position in the source and the symbols
+/// that were read ahead of that position while looking for a longer match.
+///
+/// No DFA state or position of the last accepting state is stored, so a tokenizer can only be resumed at a point between
+/// tokens (which is everywhere the tokenizer can stop). Skip patterns aren't stored either: they need to be added to the
+/// resumed tokenizer again with `add_skip_pattern()`.
+///
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ResumeState<InputSymbol> {
+    /// The position in the source stream where tokenizing should resume
+    pub position: usize,
+
+    /// Symbols that have been read from the source stream but not matched yet
+    pub lookahead: Vec<InputSymbol>
+}
+
 ///
 /// A tokenizer is a type of symbol stream that uses a pattern matcher to convert a symbol stream into a stream of tokens
 ///
@@ -121,6 +379,9 @@ pub struct Tokenizer<'a, InputSymbol: Clone+Ord+Countable+'a, OutputSymbol: Clon
 
     /// Tape of input symbols that will be used to generate the result
     tape: Tape<InputSymbol, Reader>,
+
+    /// Matches input that should be consumed without generating a token, along with the DFA compiled from it
+    skip: Option<(Pattern<InputSymbol>, SymbolRangeDfa<InputSymbol, ()>)>
 }
 
 impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<InputSymbol>> Tokenizer<'a, InputSymbol, OutputSymbol, Reader> {
@@ -128,14 +389,33 @@ impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Read
     /// Creates a new tokenizer from a pattern (usually a TokenMatcher)
     ///
     pub fn new<'b, Prepare: PrepareToMatch<SymbolRangeDfa<InputSymbol, OutputSymbol>>>(source: Reader, pattern: Prepare) -> Tokenizer<'b, InputSymbol, OutputSymbol, Reader> {
-        Tokenizer { dfa: Owned(pattern.prepare_to_match()), tape: Tape::new(source) }
+        Tokenizer { dfa: Owned(pattern.prepare_to_match()), tape: Tape::new(source), skip: None }
     }
 
     ///
     /// Creates a new tokenizer from a prepared pattern
     ///
     pub fn new_prepared<'b>(source: Reader, pattern: &'b SymbolRangeDfa<InputSymbol, OutputSymbol>) -> Tokenizer<'b, InputSymbol, OutputSymbol, Reader> {
-        Tokenizer { dfa: Reference(pattern), tape: Tape::new(source) }
+        Tokenizer { dfa: Reference(pattern), tape: Tape::new(source), skip: None }
+    }
+
+    ///
+    /// Creates a tokenizer that continues from a state returned by `resume_state()`
+    ///
+    /// The reader should return the symbols that follow the ones in the resume state's lookahead. Token locations
+    /// continue on from the position where the original tokenizer stopped.
+    ///
+    /// The new tokenizer has no skip patterns, so any that the original tokenizer used should be added again.
+    ///
+    pub fn resume_from<'b>(pattern: &'b SymbolRangeDfa<InputSymbol, OutputSymbol>, source: Reader, state: ResumeState<InputSymbol>) -> Tokenizer<'b, InputSymbol, OutputSymbol, Reader> {
+        Tokenizer { dfa: Reference(pattern), tape: Tape::resume(source, state.position, state.lookahead), skip: None }
+    }
+
+    ///
+    /// Returns the state needed to resume tokenizing from the current position
+    ///
+    pub fn resume_state(&self) -> ResumeState<InputSymbol> {
+        ResumeState { position: self.tape.get_source_position(), lookahead: self.tape.lookahead() }
     }
 
     ///
@@ -145,6 +425,16 @@ impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Read
         self.tape.get_source_position()
     }
 
+    ///
+    /// Returns the reader that the tokenizer is reading symbols from
+    ///
+    /// The tokenizer reads ahead of the current position while looking for the longest match, so the source reader may have
+    /// returned more symbols than `get_source_position()` indicates.
+    ///
+    pub fn source_reader(&self) -> &Reader {
+        self.tape.source_reader()
+    }
+
     ///
     /// Skips an input symbol (returning the symbol that was skipped)
     ///
@@ -168,11 +458,149 @@ impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Read
     /// a new match at the next symbol. 
     ///
     pub fn next_token(&mut self) -> Option<(Range<usize>, OutputSymbol)> {
+        let token = self.match_token();
+
+        if token.is_some() {
+            // Won't try to match anything before this position
+            self.tape.cut();
+        }
+
+        token
+    }
+
+    ///
+    /// Reads the next token from the tokenizer along with the input symbols that were matched
+    ///
+    /// This behaves in the same way as `next_token`, except the symbols that make up the token are kept. 
+    ///
+    pub fn next_rich_token(&mut self) -> Option<RichToken<InputSymbol, OutputSymbol>> {
+        let (location, output) = self.match_token()?;
+
+        // Read the symbols for this token back from the tape
+        self.tape.rewind(location.len());
+        let symbols = (0..location.len()).map(|_| self.tape.next_symbol().unwrap()).collect();
+
+        self.tape.cut();
+
+        Some(RichToken { output, symbols, location })
+    }
+
+    ///
+    /// Converts this tokenizer into an iterator that returns tokens with the input symbols that were matched
+    ///
+    /// Like iterating over the tokenizer, input that doesn't match a token is skipped.
+    ///
+    pub fn rich_tokens(self) -> RichTokens<'a, InputSymbol, OutputSymbol, Reader> {
+        RichTokens { tokenizer: self }
+    }
+
+    ///
+    /// Converts this tokenizer into an iterator that returns each token as a `Token`
+    ///
+    /// Tokens are read from the input as they're needed, so unlike an `AnnotatedStream` the input doesn't need to be read
+    /// all at once. Input that doesn't match a token is skipped.
+    ///
+    pub fn token_stream(self) -> TokenStream<'a, InputSymbol, OutputSymbol, Reader> {
+        TokenStream { tokenizer: self }
+    }
+
+    ///
+    /// Converts this tokenizer into an iterator that returns tokens along with the range of input that was skipped before
+    /// each one
+    ///
+    /// As every symbol of the input is either in a token or in the leading trivia of a token (or in the trailing trivia
+    /// after the last token), this makes it possible to reconstruct the original input exactly.
+    ///
+    pub fn tokens_with_trivia(self) -> TokensWithTrivia<'a, InputSymbol, OutputSymbol, Reader> {
+        let trivia_start = self.get_source_position();
+
+        TokensWithTrivia { tokenizer: self, trivia_start }
+    }
+
+    ///
+    /// Classifies the whole of the input, returning a list of spans that cover every symbol in order
+    ///
+    /// Spans that match a token have the token's output symbol, and runs of input that don't match a token are returned
+    /// as a single span with no output symbol.
+    ///
+    pub fn classify(self) -> Vec<(Range<usize>, Option<OutputSymbol>)> {
+        let mut tokenizer   = self;
+        let mut result      = vec![];
+
+        loop {
+            if let Some((location, output)) = tokenizer.next_token() {
+                result.push((location, Some(output)));
+            } else if tokenizer.skip_input().is_some() {
+                let position = tokenizer.get_source_position();
+
+                // Extend the previous unmatched span if there is one
+                match result.last_mut() {
+                    Some(&mut (ref mut location, None)) => location.end = position,
+                    _                                   => result.push((position-1..position, None))
+                }
+            } else {
+                break;
+            }
+        }
+
+        result
+    }
+
+    ///
+    /// Matches the next token, leaving the tape positioned after it
+    ///
+    fn match_token(&mut self) -> Option<(Range<usize>, OutputSymbol)> {
+        loop {
+            let skip_length = self.match_skip_pattern();
+            let token       = self.match_dfa_token();
+
+            // Tokens win over skip patterns unless the skip pattern matches more input
+            let token_length = token.as_ref().map(|(location, _)| location.len()).unwrap_or(0);
+            if skip_length == 0 || token_length >= skip_length {
+                return token;
+            }
+
+            // Consume the skipped input and try again after it
+            self.tape.rewind(token_length);
+            for _ in 0..skip_length {
+                self.tape.next_symbol();
+            }
+
+            self.tape.cut();
+        }
+    }
+
+    ///
+    /// Returns the length of the input at the current position that matches the skip pattern, without moving the tape
+    ///
+    fn match_skip_pattern(&mut self) -> usize {
+        if let Some((_, ref skip_dfa)) = self.skip {
+            let start_pos       = self.tape.get_source_position();
+            let start_state     = if start_pos == 0 { skip_dfa.start() } else { skip_dfa.start_mid_input() };
+            let match_result    = match_pattern(start_state, &mut self.tape);
+            let end_pos         = self.tape.get_source_position();
+
+            self.tape.rewind(end_pos-start_pos);
+
+            match match_result {
+                Accept(length, _)   => length,
+                _                   => 0
+            }
+        } else {
+            0
+        }
+    }
+
+    ///
+    /// Matches a token using the DFA, leaving the tape after the token if one is found
+    ///
+    fn match_dfa_token(&mut self) -> Option<(Range<usize>, OutputSymbol)> {
         // Start of the next symbol
         let start_pos = self.tape.get_source_position();
 
         // Match against it
-        let match_result = match_pattern(self.dfa.get().start(), &mut self.tape);
+        let start_state     = if start_pos == 0 { self.dfa.get().start() } else { self.dfa.get().start_mid_input() };
+        let match_result    = match_pattern(start_state, &mut self.tape);
 
         let end_pos = self.tape.get_source_position();
         match match_result {
@@ -181,9 +609,6 @@ impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Read
                     // Rewind the tape to after the accepted symbol
                     self.tape.rewind(end_pos-start_pos - length);
 
-                    // Won't try to match anything before this position
-                    self.tape.cut();
-
                     // Result is the oputput symbol
                     let match_range = start_pos..(start_pos+length);
                     Some((match_range, outputsymbol.clone()))
@@ -212,6 +637,25 @@ impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Read
     }
 }
 
+impl<'a, InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<InputSymbol>> Tokenizer<'a, InputSymbol, OutputSymbol, Reader> {
+    ///
+    /// Adds a pattern that matches input that should be consumed without generating a token, such as whitespace or comments
+    ///
+    /// Skipped input competes with the tokens: if a skip pattern matches more input than any token at the same position,
+    /// the input is skipped, otherwise the token is generated. Skipped input is part of the leading trivia of the token
+    /// that follows it.
+    ///
+    pub fn add_skip_pattern<TPattern: ToPattern<InputSymbol>>(&mut self, pattern: TPattern) {
+        let pattern = match self.skip.take() {
+            Some((skip_pattern, _)) => skip_pattern.or(pattern.to_pattern()),
+            None                    => pattern.to_pattern()
+        };
+
+        let skip_dfa = pattern.clone().prepare_to_match();
+        self.skip    = Some((pattern, skip_dfa));
+    }
+}
+
 impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<InputSymbol>> SymbolReader<OutputSymbol> for Tokenizer<'a, InputSymbol, OutputSymbol, Reader> {
     #[inline]
     fn next_symbol(&mut self) -> Option<OutputSymbol> {
@@ -248,6 +692,118 @@ impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Read
     }
 }
 
+///
+/// Iterator that returns the tokens from a tokenizer as they're found
+///
+pub struct TokenStream<'a, InputSymbol: Clone+Ord+Countable+'a, OutputSymbol: Clone+Ord+'a, Reader: SymbolReader<InputSymbol>> {
+    /// The tokenizer that is generating the tokens
+    tokenizer: Tokenizer<'a, InputSymbol, OutputSymbol, Reader>
+}
+
+impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<InputSymbol>> Iterator for TokenStream<'a, InputSymbol, OutputSymbol, Reader> {
+    type Item = Token<OutputSymbol>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Token<OutputSymbol>> {
+        self.tokenizer.next().map(|(location, output)| Token { output, location })
+    }
+}
+
+///
+/// Returns an iterator that lazily reads the tokens matched by a DFA from a symbol reader
+///
+/// ```
+/// # use concordance::*;
+/// let dfa     = MatchRange('0', '9').repeat_forever(1).prepare_to_match();
+/// let first   = tokens(&dfa, "12 34 56".read_symbols()).next();
+///
+/// // first == Some(Token { output: (), location: 0..2 })
+/// # assert!(first == Some(Token { output: (), location: 0..2 }));
+/// ```
+///
+pub fn tokens<'a, InputSymbol, OutputSymbol, Reader>(dfa: &'a SymbolRangeDfa<InputSymbol, OutputSymbol>, reader: Reader) -> TokenStream<'a, InputSymbol, OutputSymbol, Reader>
+where   InputSymbol: Clone+Ord+Countable
+,       OutputSymbol: Clone+Ord+'static
+,       Reader: SymbolReader<InputSymbol> {
+    Tokenizer::new_prepared(reader, dfa).token_stream()
+}
+
+impl<InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static> SymbolRangeDfa<InputSymbol, OutputSymbol> {
+    ///
+    /// Returns an iterator that lazily reads the tokens matched by this DFA from a symbol reader
+    ///
+    /// The DFA is borrowed rather than consumed, so a DFA that was compiled once can tokenize any number of inputs.
+    ///
+    pub fn tokenize<'a, Reader: SymbolReader<InputSymbol>>(&'a self, reader: Reader) -> TokenStream<'a, InputSymbol, OutputSymbol, Reader> {
+        tokens(self, reader)
+    }
+}
+
+///
+/// Iterator that returns the rich tokens from a tokenizer
+///
+pub struct RichTokens<'a, InputSymbol: Clone+Ord+Countable+'a, OutputSymbol: Clone+Ord+'a, Reader: SymbolReader<InputSymbol>> {
+    /// The tokenizer that is generating the tokens
+    tokenizer: Tokenizer<'a, InputSymbol, OutputSymbol, Reader>
+}
+
+impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<InputSymbol>> Iterator for RichTokens<'a, InputSymbol, OutputSymbol, Reader> {
+    type Item = RichToken<InputSymbol, OutputSymbol>;
+
+    fn next(&mut self) -> Option<RichToken<InputSymbol, OutputSymbol>> {
+        loop {
+            if let Some(next) = self.tokenizer.next_rich_token() {
+                return Some(next);
+            } else if self.tokenizer.at_end_of_reader() {
+                return None;
+            } else {
+                self.tokenizer.skip_input();
+            }
+        }
+    }
+}
+
+///
+/// Iterator that returns tokens along with the input that was skipped before them
+///
+pub struct TokensWithTrivia<'a, InputSymbol: Clone+Ord+Countable+'a, OutputSymbol: Clone+Ord+'a, Reader: SymbolReader<InputSymbol>> {
+    /// The tokenizer that is generating the tokens
+    tokenizer: Tokenizer<'a, InputSymbol, OutputSymbol, Reader>,
+
+    /// The position of the first symbol after the most recent token
+    trivia_start: usize
+}
+
+impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<InputSymbol>> TokensWithTrivia<'a, InputSymbol, OutputSymbol, Reader> {
+    ///
+    /// Returns the range of input that has been skipped since the last token
+    ///
+    /// Once the iterator has returned `None`, this is the trivia at the end of the input.
+    ///
+    pub fn trailing_trivia(&self) -> Range<usize> {
+        self.trivia_start..self.tokenizer.get_source_position()
+    }
+}
+
+impl<'a, InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord+'static, Reader: SymbolReader<InputSymbol>> Iterator for TokensWithTrivia<'a, InputSymbol, OutputSymbol, Reader> {
+    type Item = TriviaToken<OutputSymbol>;
+
+    fn next(&mut self) -> Option<TriviaToken<OutputSymbol>> {
+        loop {
+            if let Some((location, output)) = self.tokenizer.next_token() {
+                let leading_trivia  = self.trivia_start..location.start;
+                self.trivia_start   = location.end;
+
+                return Some(TriviaToken { output, location, leading_trivia });
+            } else if self.tokenizer.at_end_of_reader() {
+                return None;
+            } else {
+                self.tokenizer.skip_input();
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::super::*;
@@ -511,4 +1067,351 @@ mod test {
         assert!(tokenizer.next_symbol() == None);
         assert!(tokenizer.at_end_of_reader());
     }
+
+    #[test]
+    fn rich_token_contains_symbols() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Digit
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+
+        let mut tokenizer = Tokenizer::new("12".read_symbols(), &token_matcher);
+
+        assert!(tokenizer.next_rich_token() == Some(RichToken { output: TestToken::Digit, symbols: vec!['1', '2'], location: 0..2 }));
+        assert!(tokenizer.next_rich_token().is_none());
+        assert!(tokenizer.at_end_of_reader());
+    }
+
+    #[test]
+    fn can_iterate_rich_tokens() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Digit,
+            Word
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), TestToken::Word);
+
+        let tokens: Vec<_> = Tokenizer::new("12 ab 3".read_symbols(), &token_matcher).rich_tokens().collect();
+
+        assert!(tokens == vec![
+            RichToken { output: TestToken::Digit, symbols: vec!['1', '2'], location: 0..2 },
+            RichToken { output: TestToken::Word, symbols: vec!['a', 'b'], location: 3..5 },
+            RichToken { output: TestToken::Digit, symbols: vec!['3'], location: 6..7 }
+        ]);
+    }
+
+    #[test]
+    fn classify_covers_whole_input() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Digit
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+
+        assert!(Tokenizer::new("12@34".read_symbols(), &token_matcher).classify() == vec![
+            (0..2, Some(TestToken::Digit)),
+            (2..3, None),
+            (3..5, Some(TestToken::Digit))
+        ]);
+        assert!(Tokenizer::new("ab1cd".read_symbols(), &token_matcher).classify() == vec![
+            (0..2, None),
+            (2..3, Some(TestToken::Digit)),
+            (3..5, None)
+        ]);
+    }
+
+    #[test]
+    fn patterns_are_simplified_before_compiling() {
+        // Each alternative would otherwise finish in a separate DFA state
+        let letters = "abcdefghijklmnopqrstuvwxyz".chars().fold(MatchAny(vec![]), |pattern, letter| pattern.or(MatchRange(letter, letter)));
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(letters, 1);
+
+        let dfa = token_matcher.prepare_to_match();
+
+        assert!(dfa.count_states() == 2);
+        assert!(matches("q", dfa) == Some(1));
+    }
+
+    #[test]
+    fn can_deduplicate_patterns() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Number,
+            Word
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(Pattern::from_regex("[0-9]+").unwrap(), TestToken::Number);
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), TestToken::Word);
+        token_matcher.add_pattern(Pattern::from_regex("[0-9]+").unwrap(), TestToken::Number);
+
+        let before: Vec<_> = Tokenizer::new("12 ab 3".read_symbols(), &token_matcher).collect();
+
+        assert!(token_matcher.deduplicate() == vec![]);
+        assert!(token_matcher.count_patterns() == 2);
+
+        let after: Vec<_> = Tokenizer::new("12 ab 3".read_symbols(), &token_matcher).collect();
+        assert!(before == after);
+    }
+
+    #[test]
+    fn patterns_that_compile_differently_can_be_duplicates() {
+        let first   = Pattern::from_regex("ab|ac").unwrap();
+        let second  = Pattern::from_regex("a(b|c)").unwrap();
+
+        // The DFAs are equivalent but don't have the same structure
+        let first_dfa: SymbolRangeDfa<char, ()>     = first.prepare_to_match();
+        let second_dfa: SymbolRangeDfa<char, ()>    = second.prepare_to_match();
+        assert!(first_dfa.canonicalize() != second_dfa.canonicalize());
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(Pattern::from_regex("ab|ac").unwrap(), 1);
+        token_matcher.add_pattern(Pattern::from_regex("a(b|c)").unwrap(), 1);
+        token_matcher.add_pattern(Pattern::from_regex("a[bc]").unwrap(), 2);
+
+        assert!(token_matcher.deduplicate() == vec![(1, 2)]);
+        assert!(token_matcher.count_patterns() == 2);
+    }
+
+    #[test]
+    fn conflicting_duplicates_are_reported() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Number,
+            Integer
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Number);
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Integer);
+
+        assert!(token_matcher.deduplicate() == vec![(TestToken::Number, TestToken::Integer)]);
+        assert!(token_matcher.count_patterns() == 2);
+    }
+
+    #[test]
+    fn can_resume_from_serialized_state() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Digit,
+            Word
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), TestToken::Word);
+
+        let input               = "12 ab 3 cd";
+        let dfa                 = token_matcher.prepare_to_match();
+        let expected: Vec<_>    = Tokenizer::new_prepared(input.read_symbols(), &dfa).collect();
+
+        // Tokenize the start of the input (the tokenizer reads the space after '12' looking for a longer match)
+        let mut first_half  = Tokenizer::new_prepared(input[0..3].read_symbols(), &dfa);
+        let first_token     = first_half.next_token().unwrap();
+        let serialized      = serde_json::to_string(&first_half.resume_state()).unwrap();
+
+        // Another tokenizer can pick up where the first left off
+        let state: ResumeState<char>    = serde_json::from_str(&serialized).unwrap();
+        let second_half                 = Tokenizer::resume_from(&dfa, input[3..].read_symbols(), state.clone());
+
+        assert!(state == ResumeState { position: 2, lookahead: vec![' '] });
+
+        let mut resumed = vec![first_token];
+        resumed.extend(second_half);
+
+        assert!(resumed == expected);
+    }
+
+    #[test]
+    fn skip_patterns_can_be_added_to_a_resumed_tokenizer() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), ());
+
+        let input   = "12 # 3 # 45";
+        let dfa     = token_matcher.prepare_to_match();
+
+        let mut first_half = Tokenizer::new_prepared(input[0..6].read_symbols(), &dfa);
+        first_half.add_skip_pattern(exactly(" # "));
+
+        assert!(first_half.next_token() == Some((0..2, ())));
+        assert!(first_half.next_token() == Some((5..6, ())));
+
+        let mut second_half = Tokenizer::resume_from(&dfa, input[6..].read_symbols(), first_half.resume_state());
+        second_half.add_skip_pattern(exactly(" # "));
+
+        assert!(second_half.next_token() == Some((9..11, ())));
+        assert!(second_half.next_token() == None);
+    }
+
+    #[test]
+    fn can_count_symbols_read_by_tokenizer() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), ());
+
+        let mut tokenizer = Tokenizer::new(CountingReader::new("12 345".read_symbols()), &token_matcher);
+
+        assert!(tokenizer.next_token() == Some((0..2, ())));
+        assert!(tokenizer.source_reader().count() == 3);
+
+        tokenizer.skip_input();
+        assert!(tokenizer.next_token() == Some((3..6, ())));
+        assert!(tokenizer.source_reader().count() == 6);
+    }
+
+    #[test]
+    fn tokens_carry_leading_trivia() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Digit
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+
+        let mut tokens = Tokenizer::new("  12 3 ".read_symbols(), &token_matcher).tokens_with_trivia();
+
+        assert!(tokens.next() == Some(TriviaToken { output: TestToken::Digit, location: 2..4, leading_trivia: 0..2 }));
+        assert!(tokens.next() == Some(TriviaToken { output: TestToken::Digit, location: 5..6, leading_trivia: 4..5 }));
+        assert!(tokens.next().is_none());
+        assert!(tokens.trailing_trivia() == (6..7));
+    }
+
+    #[test]
+    fn token_stream_reads_input_lazily() {
+        let dfa         = MatchRange('0', '9').repeat_forever(1).prepare_to_match();
+        let source      = "12 34 56 78 90";
+        let mut stream  = tokens(&dfa, CountingReader::new(source.read_symbols()));
+
+        assert!(stream.next() == Some(Token { output: (), location: 0..2 }));
+        assert!(stream.next() == Some(Token { output: (), location: 3..5 }));
+
+        // The tokenizer only needs to read one symbol past the end of the last token
+        assert!(stream.tokenizer.source_reader().count() == 6);
+        assert!(stream.tokenizer.source_reader().count() < source.len());
+    }
+
+    #[test]
+    fn skip_patterns_produce_no_tokens() {
+        let dfa             = MatchRange('0', '9').repeat_forever(1).prepare_to_match();
+        let mut tokenizer   = Tokenizer::new_prepared("12 34".read_symbols(), &dfa);
+
+        tokenizer.add_skip_pattern(exactly(" ").repeat_forever(1));
+
+        assert!(tokenizer.next_token() == Some((0..2, ())));
+        assert!(tokenizer.next_token() == Some((3..5, ())));
+        assert!(tokenizer.next_token().is_none());
+        assert!(tokenizer.at_end_of_reader());
+    }
+
+    #[test]
+    fn skip_patterns_win_when_longer() {
+        let dfa             = MatchRange('0', '9').repeat_forever(1).or(exactly("#")).prepare_to_match();
+        let mut tokenizer   = Tokenizer::new_prepared("12 #34\n# 56".read_symbols(), &dfa);
+
+        // A '#' followed by digits is a comment up to the end of the line, but a '#' on its own is still a token
+        tokenizer.add_skip_pattern(exactly(" "));
+        tokenizer.add_skip_pattern(exactly("#").append(MatchRange('0', '9').repeat_forever(1)).append(exactly("\n")));
+
+        let tokens: Vec<_> = tokenizer.tokens_with_trivia().map(|token| (token.location, token.leading_trivia)).collect();
+
+        assert!(tokens == vec![(0..2, 0..0), (7..8, 2..7), (9..11, 8..9)]);
+    }
+
+    #[test]
+    fn anchored_tokens_only_match_at_start() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(start_anchor().append("#!").append(MatchRange('a', 'z').repeat_forever(1)), 1);
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), 2);
+        token_matcher.add_pattern(exactly("#"), 3);
+
+        let tokenizer   = Tokenizer::new("#!sh #!sh".read_symbols(), &token_matcher);
+        let tokens      = tokenizer.collect::<Vec<_>>();
+
+        assert!(tokens == vec![(0..4, 1), (5..6, 3), (7..9, 2)]);
+    }
+
+    #[test]
+    fn anchored_tokens_only_match_at_end() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1).append(end_anchor()), 1);
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), 2);
+
+        let tokenizer   = Tokenizer::new("ab cd".read_symbols(), &token_matcher);
+        let tokens      = tokenizer.collect::<Vec<_>>();
+
+        assert!(tokens == vec![(0..2, 2), (3..5, 1)]);
+    }
+
+    #[test]
+    fn can_report_all_matching_outputs() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), "word");
+        token_matcher.add_pattern(exactly("abc"), "keyword");
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), "number");
+
+        let dfa         = token_matcher.prepare_with_all_outputs();
+        let tokenizer   = Tokenizer::new_prepared("abc abcd 12".read_symbols(), &dfa);
+        let tokens      = tokenizer.collect::<Vec<_>>();
+
+        assert!(tokens == vec![(0..3, vec!["keyword", "word"]), (4..8, vec!["word"]), (9..11, vec!["number"])]);
+    }
+
+    #[test]
+    fn all_outputs_follow_priority_mode() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), "word");
+        token_matcher.add_pattern(exactly("abc"), "keyword");
+        token_matcher.add_pattern(exactly("ab").append("c"), "word");
+        token_matcher.set_priority_mode(PriorityMode::LongestThenInsertion);
+
+        let dfa = token_matcher.prepare_with_all_outputs();
+
+        assert!(matches_with_output("abc", dfa.clone()) == Some((3, vec!["word", "keyword"])));
+        assert!(matches_with_output("abx", dfa) == Some((3, vec!["word"])));
+    }
+
+    #[test]
+    fn priority_can_follow_insertion_order() {
+        let mut by_order = TokenMatcher::new();
+        by_order.add_pattern(exactly("if"), 2);
+        by_order.add_pattern(MatchRange('a', 'z').repeat_forever(1), 1);
+
+        let mut by_insertion = TokenMatcher::new();
+        by_insertion.add_pattern(exactly("if"), 2);
+        by_insertion.add_pattern(MatchRange('a', 'z').repeat_forever(1), 1);
+        by_insertion.set_priority_mode(PriorityMode::LongestThenInsertion);
+
+        let by_order        = by_order.prepare_to_match();
+        let by_insertion    = by_insertion.prepare_to_match();
+
+        assert!(matches_ref(&by_order, "if") == Some((2, &1)));
+        assert!(matches_ref(&by_insertion, "if") == Some((2, &2)));
+
+        // The longest match still wins whatever the mode
+        assert!(matches_ref(&by_insertion, "iffy") == Some((4, &1)));
+    }
+
+    #[test]
+    fn one_dfa_can_tokenize_many_inputs() {
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), 1);
+        token_matcher.add_pattern(MatchRange('a', 'z').repeat_forever(1), 2);
+
+        let dfa     = token_matcher.prepare_to_match();
+        let first   = dfa.tokenize("12 ab".read_symbols()).collect::<Vec<_>>();
+        let second  = dfa.tokenize("x9".read_symbols()).collect::<Vec<_>>();
+
+        assert!(first == vec![Token { output: 1, location: 0..2 }, Token { output: 2, location: 3..5 }]);
+        assert!(second == vec![Token { output: 2, location: 0..1 }, Token { output: 1, location: 1..2 }]);
+    }
 }
@@ -0,0 +1,138 @@
+//
+//   Copyright 2017 Andrew Hunter
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+//
+
+//!
+//! The tokenizer profiler runs a set of token patterns over a corpus of inputs and counts how many times each pattern
+//! produced a token. This is useful when developing a lexer, to find out which rules fire most often and which rules
+//! never fire at all.
+//!
+//! ```
+//! # use concordance::*;
+//! let mut token_matcher = TokenMatcher::new();
+//! token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), "Number");
+//! token_matcher.add_pattern(exactly("if"), "If");
+//!
+//! let mut profiler = TokenizerProfiler::new(&token_matcher);
+//! profiler.tokenize("12 34".read_symbols());
+//!
+//! let report = profiler.report(); // == vec![(0, 2), (1, 0)]
+//! # assert!(report == vec![(0, 2), (1, 0)]);
+//! ```
+//!
+
+use std::ops::Range;
+
+use super::countable::*;
+use super::symbol_reader::*;
+use super::symbol_range_dfa::*;
+use super::tokenizer::*;
+
+///
+/// Counts how many tokens each pattern in a `TokenMatcher` produces over a series of inputs
+///
+pub struct TokenizerProfiler<InputSymbol: Clone+Ord+Countable, OutputSymbol: Clone+Ord> {
+    /// The prepared tokenizer, where each output symbol is tagged with the index of the pattern that produced it
+    dfa: SymbolRangeDfa<InputSymbol, (OutputSymbol, usize)>,
+
+    /// The number of tokens produced by each pattern
+    counts: Vec<usize>
+}
+
+impl<InputSymbol: Clone+Ord+Countable+'static, OutputSymbol: Clone+Ord+'static> TokenizerProfiler<InputSymbol, OutputSymbol> {
+    ///
+    /// Creates a new profiler for the patterns in a token matcher
+    ///
+    pub fn new(token_matcher: &TokenMatcher<InputSymbol, OutputSymbol>) -> TokenizerProfiler<InputSymbol, OutputSymbol> {
+        // Clashes and anchors are resolved in the same way as for the original token matcher
+        TokenizerProfiler { dfa: token_matcher.prepare_with_pattern_indexes(), counts: vec![0; token_matcher.count_patterns()] }
+    }
+
+    ///
+    /// Tokenizes a stream, updating the number of times each pattern has matched and returning the tokens that were found
+    ///
+    pub fn tokenize<Reader: SymbolReader<InputSymbol>>(&mut self, reader: Reader) -> Vec<(Range<usize>, OutputSymbol)> {
+        let tokenizer   = Tokenizer::new_prepared(reader, &self.dfa);
+        let mut result  = vec![];
+
+        for (range, (output, pattern_index)) in tokenizer {
+            self.counts[pattern_index] += 1;
+            result.push((range, output));
+        }
+
+        result
+    }
+
+    ///
+    /// Returns the number of tokens that have been produced by each pattern, as a list of pattern indexes and counts
+    ///
+    /// Patterns that have never produced a token are included in the report with a count of 0.
+    ///
+    pub fn report(&self) -> Vec<(usize, usize)> {
+        self.counts.iter().cloned().enumerate().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::*;
+
+    #[test]
+    fn reports_zero_for_unused_rule() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Digit,
+            Whitespace,
+            Keyword
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+        token_matcher.add_pattern(exactly(" ").repeat_forever(1), TestToken::Whitespace);
+        token_matcher.add_pattern(exactly("while"), TestToken::Keyword);
+
+        let mut profiler = TokenizerProfiler::new(&token_matcher);
+
+        let tokens = profiler.tokenize("12 34".read_symbols());
+        profiler.tokenize("5 6 7".read_symbols());
+
+        assert!(tokens == vec![(0..2, TestToken::Digit), (2..3, TestToken::Whitespace), (3..5, TestToken::Digit)]);
+        assert!(profiler.report() == vec![(0, 5), (1, 3), (2, 0)]);
+    }
+
+    #[test]
+    fn counts_anchored_rules() {
+        #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+        enum TestToken {
+            Digit,
+            Shebang,
+            One
+        }
+
+        let mut token_matcher = TokenMatcher::new();
+        token_matcher.set_priority_mode(PriorityMode::LongestThenInsertion);
+        token_matcher.add_pattern(start_anchor().append(exactly("#!")), TestToken::Shebang);
+        token_matcher.add_pattern(exactly("1"), TestToken::One);
+        token_matcher.add_pattern(MatchRange('0', '9').repeat_forever(1), TestToken::Digit);
+
+        let mut profiler = TokenizerProfiler::new(&token_matcher);
+
+        let tokens = profiler.tokenize("#!1#!23".read_symbols());
+
+        // The anchored rule only matches at the start, and '1' is produced by the earlier pattern
+        assert!(tokens == vec![(0..2, TestToken::Shebang), (2..3, TestToken::One), (5..7, TestToken::Digit)]);
+        assert!(profiler.report() == vec![(0, 1), (1, 1), (2, 1)]);
+    }
+}